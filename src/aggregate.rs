@@ -0,0 +1,123 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::release_notes_file::StagedRelease;
+use std::collections::BTreeMap;
+
+#[derive(clap::Args, Debug)]
+pub struct AggregateArgs {
+    /// target whose matrix job has to stage the release before anything is published, can be repeated - other targets are merged in when they staged it too
+    #[clap(long, value_name = "TARGET", required = true)]
+    pub require_target: Vec<RustTarget>,
+    /// give up when the required targets haven't all staged the release within this time
+    #[clap(long, default_value = "30m", value_name = "DURATION")]
+    pub aggregate_timeout: humantime::Duration,
+    /// how often the staged releases are checked while waiting
+    #[clap(long, default_value = "15s", value_name = "DURATION")]
+    pub aggregate_poll_interval: humantime::Duration,
+    #[clap(flatten)]
+    pub publish: PublishArgs,
+}
+
+/// targets that staged the release of `commit` and required targets that haven't (yet)
+///
+/// a release staged from another commit under the same version doesn't count, its job is probably still running
+fn staged_targets(
+    required: &[RustTarget],
+    staged_commits: &BTreeMap<RustTarget, String>,
+    commit: &str,
+) -> (Vec<RustTarget>, Vec<RustTarget>) {
+    let present = staged_commits
+        .iter()
+        .filter(|(_, staged)| *staged == commit)
+        .map(|(target, _)| target.clone())
+        .collect_vec();
+    let missing = required
+        .iter()
+        .filter(|target| !present.contains(target))
+        .unique()
+        .cloned()
+        .collect_vec();
+    (present, missing)
+}
+
+/// commits of the releases staged for the current version, by target
+async fn staged_commits(deployments: &[Deployment<'_>]) -> Result<BTreeMap<RustTarget, String>> {
+    let mut commits = BTreeMap::new();
+    for deployment in deployments {
+        let key = derive_staged_release_file_s3_key(
+            deployment.branch,
+            deployment.target,
+            deployment.tauri_conf_json.version(),
+        );
+        if let Some(staged) = storage::fetch_json::<StagedRelease>(deployment.s3_config, &key)
+            .await
+            .wrap_err_with(|| format!("fetching staged release [{key}]"))?
+        {
+            if staged.commit != deployment.git_hash {
+                debug!(
+                    "aggregate :: [{key}] was staged from {}, not {}",
+                    staged.commit, deployment.git_hash
+                );
+            }
+            commits.insert(deployment.target.clone(), staged.commit);
+        }
+    }
+    Ok(commits)
+}
+
+/// polls the staged releases of every target until the required ones are all there, returns the deployments of the targets to publish
+pub async fn wait_for_targets<'a, 'b>(
+    deployments: &'a [Deployment<'b>],
+    args: &AggregateArgs,
+) -> Result<Vec<&'a Deployment<'b>>> {
+    let commit = match deployments.first() {
+        Some(deployment) => deployment.git_hash,
+        None => bail!("no targets to aggregate"),
+    };
+    let timeout: std::time::Duration = args.aggregate_timeout.into();
+    let started = std::time::Instant::now();
+    loop {
+        let staged = staged_commits(deployments).await?;
+        let (present, missing) = staged_targets(&args.require_target, &staged, commit);
+        if missing.is_empty() {
+            info!("aggregate :: merging {present:?}");
+            return Ok(deployments
+                .iter()
+                .filter(|deployment| present.contains(deployment.target))
+                .collect());
+        }
+        if started.elapsed() >= timeout {
+            bail!(
+                "{missing:?} didn't stage the release of {commit} within {}, nothing was published",
+                args.aggregate_timeout
+            );
+        }
+        info!("aggregate :: waiting for {missing:?}, staged so far: {present:?}");
+        tokio::time::sleep(args.aggregate_poll_interval.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staged_targets() {
+        let staged = [
+            (RustTarget::Win64, "abcd1234".to_string()),
+            (RustTarget::Linux64, "abcd1234".to_string()),
+            (RustTarget::MacOsX86_64, "0ld0ld00".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let (present, missing) = staged_targets(
+            &[RustTarget::Win64, RustTarget::MacOsX86_64],
+            &staged,
+            "abcd1234",
+        );
+        assert_eq!(present, vec![RustTarget::Win64, RustTarget::Linux64]);
+        assert_eq!(missing, vec![RustTarget::MacOsX86_64]);
+        let (_, missing) = staged_targets(&[RustTarget::Linux64], &staged, "abcd1234");
+        assert!(missing.is_empty());
+    }
+}
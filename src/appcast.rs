@@ -0,0 +1,189 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::release_notes_file::StagedRelease;
+use ed25519_dalek::Signer;
+
+/// base64 ed25519 seed (sparkle's `generate_keys -x`) the enclosure is signed with, `sparkle:edSignature` is left out without it
+pub const SPARKLE_KEY_ENV: &str = "DEPLOY_SPARKLE_PRIVATE_KEY";
+
+/// installers sparkle can update from, preferred over the tauri updater bundle
+const SPARKLE_ARCHIVE_SUFFIXES: &[&str] = &[".dmg", ".zip", ".tar.xz", ".tar.bz2"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Enclosure {
+    url: String,
+    length: u64,
+    ed_signature: Option<String>,
+}
+
+/// a sparkle archive among the installers, the updater bundle otherwise
+fn enclosure(staged: &StagedRelease) -> Option<Enclosure> {
+    let installer = staged.downloads.iter().find(|download| {
+        SPARKLE_ARCHIVE_SUFFIXES
+            .iter()
+            .any(|suffix| download.file_name.ends_with(suffix))
+    });
+    if let Some(installer) = installer {
+        return Some(Enclosure {
+            url: installer.url.clone(),
+            length: installer.size,
+            ed_signature: None,
+        });
+    }
+    let url = staged.platforms.values().next()?.url.clone();
+    let length = staged
+        .artifacts
+        .iter()
+        .find(|artifact| artifact.url == url)
+        .map(|artifact| artifact.size)
+        .unwrap_or_default();
+    Some(Enclosure {
+        url,
+        length,
+        ed_signature: None,
+    })
+}
+
+fn sparkle_key_from_env() -> Result<Option<ed25519_dalek::SigningKey>> {
+    match std::env::var(SPARKLE_KEY_ENV) {
+        Ok(val) => receipt::decode_key(&val)
+            .map(|seed| Some(ed25519_dalek::SigningKey::from_bytes(&seed)))
+            .wrap_err_with(|| format!("bad {SPARKLE_KEY_ENV}")),
+        Err(_) => Ok(None),
+    }
+}
+
+/// one `<item>` feed, `minimum_version` becomes a critical update for the versions below it and `mandatory` one for all
+fn appcast_xml(
+    app: &str,
+    version: &str,
+    pub_date: &str,
+    notes: &str,
+    enclosure: &Enclosure,
+    policy: &policy::PolicyArgs,
+) -> String {
+    let escape = downloads::escape_html;
+    let critical = match (&policy.minimum_version, policy.mandatory) {
+        (_, true) => "\n      <sparkle:criticalUpdate/>".to_string(),
+        (Some(minimum), false) => format!(
+            "\n      <sparkle:criticalUpdate sparkle:version=\"{}\"/>",
+            escape(minimum)
+        ),
+        (None, false) => String::new(),
+    };
+    let signature = enclosure
+        .ed_signature
+        .as_ref()
+        .map(|signature| format!(" sparkle:edSignature=\"{signature}\""))
+        .unwrap_or_default();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:sparkle="http://www.andymatuschak.org/xml-namespaces/sparkle">
+  <channel>
+    <title>{app}</title>
+    <item>
+      <title>{version}</title>
+      <pubDate>{pub_date}</pubDate>
+      <sparkle:version>{version}</sparkle:version>
+      <sparkle:shortVersionString>{version}</sparkle:shortVersionString>{critical}
+      <description>{notes}</description>
+      <enclosure url="{url}" length="{length}" type="application/octet-stream"{signature}/>
+    </item>
+  </channel>
+</rss>
+"#,
+        app = escape(app),
+        version = escape(version),
+        notes = escape(notes),
+        url = escape(&enclosure.url),
+        length = enclosure.length,
+    )
+}
+
+/// uploads `appcast.xml` next to the manifest of the target, returns its key
+pub async fn publish(
+    deployment: &Deployment<'_>,
+    staged: &StagedRelease,
+    notes: &str,
+    policy: &policy::PolicyArgs,
+) -> Result<String> {
+    if deployment.target != &RustTarget::MacOsX86_64 {
+        warn!(
+            "appcast :: sparkle is macos only, publishing one for {:?} anyway",
+            deployment.target
+        );
+    }
+    let mut enclosure = enclosure(staged)
+        .ok_or_else(|| eyre::eyre!("no installer or updater bundle to put into the appcast"))?;
+    if let Some(signing_key) = sparkle_key_from_env()? {
+        let path = std::env::temp_dir().join(format!("appcast-{}", uuid::Uuid::new_v4()));
+        let content = async {
+            cache::download_to(&enclosure.url, &path).await?;
+            tokio::fs::read(&path)
+                .await
+                .wrap_err_with(|| format!("reading [{}]", path.display()))
+        }
+        .await;
+        tokio::fs::remove_file(&path).await.ok();
+        let content = content.wrap_err("downloading the appcast enclosure to sign it")?;
+        enclosure.length = content.len() as u64;
+        enclosure.ed_signature =
+            Some(data_encoding::BASE64.encode(&signing_key.sign(&content).to_bytes()));
+    } else {
+        warn!("appcast :: {SPARKLE_KEY_ENV} is not set, the enclosure is not signed - sparkle 2 refuses unsigned updates");
+    }
+    let pub_date = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc2822)
+        .wrap_err("formatting current date")?;
+    let xml = appcast_xml(
+        deployment.tauri_conf_json.product_name(),
+        &staged.version,
+        &pub_date,
+        notes,
+        &enclosure,
+        policy,
+    );
+    let key = namespacing::derive_appcast_s3_key(deployment.branch, deployment.target);
+    let url = storage::put_bytes(
+        deployment.s3_config,
+        &key,
+        xml.as_bytes(),
+        "application/xml; charset=utf-8",
+    )
+    .await
+    .wrap_err("uploading appcast")?;
+    info!("appcast :: {url}");
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appcast_xml() {
+        let enclosure = Enclosure {
+            url: "https://cdn.example.com/main/App.dmg?a=1&b=2".to_string(),
+            length: 42,
+            ed_signature: Some("c2ln".to_string()),
+        };
+        let policy = policy::PolicyArgs {
+            minimum_version: Some("1.0.0".to_string()),
+            mandatory: false,
+        };
+        let xml = appcast_xml(
+            "App",
+            "1.2.3",
+            "Sat, 01 Jun 2024 12:00:00 +0000",
+            "fixes <crash>",
+            &enclosure,
+            &policy,
+        );
+        assert!(xml.contains("<sparkle:version>1.2.3</sparkle:version>"));
+        assert!(xml.contains("<description>fixes &lt;crash&gt;</description>"));
+        assert!(xml.contains(r#"<sparkle:criticalUpdate sparkle:version="1.0.0"/>"#));
+        assert!(xml.contains(
+            r#"<enclosure url="https://cdn.example.com/main/App.dmg?a=1&amp;b=2" length="42" type="application/octet-stream" sparkle:edSignature="c2ln"/>"#
+        ));
+    }
+}
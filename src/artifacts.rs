@@ -0,0 +1,339 @@
+use super::*;
+use std::collections::BTreeMap;
+
+/// windows installer whose updater bundle (`.msi.zip` / `.nsis.zip`) goes into the manifest
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, IntoEnumIterator)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallerKind {
+    Msi,
+    Nsis,
+}
+
+impl FromStr for InstallerKind {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        matched_variant!(Self, s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// the archive the tauri updater downloads, referenced by the manifest
+    UpdaterArchive,
+    /// minisign signature of the updater archive
+    Signature,
+    /// installers people download and run by hand, published under the downloads prefix
+    Installer,
+    /// distro packages, published under the packages prefix
+    Package,
+    /// debug symbols
+    Symbols,
+    /// other bundles, uploaded next to the updater archive
+    Bundle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Rule {
+    /// matched against the end of the file name, the longest matching suffix wins
+    pub suffix: String,
+    pub kind: ArtifactKind,
+    /// `windows`, `linux` or `macos`, empty for all of them
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// updater archives of a single windows installer kind
+    #[serde(default)]
+    pub installer: Option<InstallerKind>,
+}
+
+fn rule(suffix: &str, kind: ArtifactKind, platforms: &[&str], content_type: Option<&str>) -> Rule {
+    Rule {
+        suffix: suffix.to_string(),
+        kind,
+        platforms: platforms.iter().map(ToString::to_string).collect(),
+        content_type: content_type.map(ToString::to_string),
+        installer: None,
+    }
+}
+
+fn builtin_rules() -> Vec<Rule> {
+    use ArtifactKind::*;
+    vec![
+        // macos
+        rule(".app.tar.gz", UpdaterArchive, &["macos"], None),
+        rule(
+            ".dmg",
+            Installer,
+            &["macos"],
+            Some("application/x-apple-diskimage"),
+        ),
+        // linux
+        rule(".AppImage.tar.gz", UpdaterArchive, &["linux"], None),
+        rule(".AppImage", Bundle, &["linux"], None),
+        rule(
+            ".deb",
+            Package,
+            &["linux"],
+            Some("application/vnd.debian.binary-package"),
+        ),
+        rule(".rpm", Package, &["linux"], Some("application/x-rpm")),
+        rule(
+            ".flatpak",
+            Installer,
+            &["linux"],
+            Some("application/vnd.flatpak"),
+        ),
+        // windows
+        Rule {
+            installer: Some(InstallerKind::Msi),
+            ..rule(".msi.zip", UpdaterArchive, &["windows"], None)
+        },
+        Rule {
+            installer: Some(InstallerKind::Nsis),
+            ..rule(".nsis.zip", UpdaterArchive, &["windows"], None)
+        },
+        rule(".msi", Bundle, &["windows"], None),
+        rule(".msix", Installer, &["windows"], Some("application/msix")),
+        rule(
+            ".msixbundle",
+            Installer,
+            &["windows"],
+            Some("application/msixbundle"),
+        ),
+        rule(".appx", Installer, &["windows"], Some("application/appx")),
+        rule(
+            downloads::APPINSTALLER_SUFFIX,
+            Installer,
+            &["windows"],
+            Some("application/appinstaller"),
+        ),
+        rule(
+            "-setup.exe",
+            Installer,
+            &["windows"],
+            Some("application/vnd.microsoft.portable-executable"),
+        ),
+        // all
+        rule(".tar.gz", Bundle, &[], None),
+        rule(".zip", Bundle, &[], None),
+        rule(".sig", Signature, &[], None),
+    ]
+}
+
+/// s3 storage classes an object can be uploaded with
+const STORAGE_CLASSES: &[&str] = &[
+    "STANDARD",
+    "REDUCED_REDUNDANCY",
+    "STANDARD_IA",
+    "ONEZONE_IA",
+    "INTELLIGENT_TIERING",
+    "GLACIER",
+    "GLACIER_IR",
+    "DEEP_ARCHIVE",
+];
+
+/// objects in these classes have to be restored before they can be downloaded
+const ARCHIVE_STORAGE_CLASSES: &[&str] = &["GLACIER", "DEEP_ARCHIVE"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RulesConfig {
+    #[serde(default)]
+    rules: Vec<Rule>,
+    /// e.g. `symbols = "GLACIER_IR"`, kinds left out go to the default class of the bucket
+    #[serde(default)]
+    storage_classes: BTreeMap<String, String>,
+}
+
+impl RulesConfig {
+    fn storage_classes(&self) -> Result<BTreeMap<ArtifactKind, String>> {
+        let mut storage_classes = BTreeMap::new();
+        for (kind, class) in &self.storage_classes {
+            let kind: ArtifactKind =
+                serde_json::from_value(serde_json::Value::String(kind.clone()))
+                    .wrap_err_with(|| format!("unknown artifact kind {kind} in storage_classes"))?;
+            if !STORAGE_CLASSES.contains(&class.as_str()) {
+                bail!(
+                    "unknown storage class {class} for {kind:?}, known ones: {}",
+                    STORAGE_CLASSES.join(", ")
+                );
+            }
+            let downloaded = !matches!(kind, ArtifactKind::Symbols | ArtifactKind::Bundle);
+            if downloaded && ARCHIVE_STORAGE_CLASSES.contains(&class.as_str()) {
+                bail!("{kind:?} is downloaded by users, it can't be uploaded to the archive storage class {class}");
+            }
+            storage_classes.insert(kind, class.clone());
+        }
+        Ok(storage_classes)
+    }
+}
+
+/// decides what a file from the bundle dir is, files without a matching rule are not uploaded
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    rules: Vec<Rule>,
+    storage_classes: BTreeMap<ArtifactKind, String>,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Self {
+            rules: builtin_rules(),
+            storage_classes: Default::default(),
+        }
+    }
+}
+
+impl Classifier {
+    /// the built-in table, with the `[[rules]]` of the toml file (if any) taking precedence on equally long suffixes
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        let config: RulesConfig = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("reading artifact rules [{}]", path.display()))
+            .and_then(|content| toml::from_str(&content).wrap_err("parsing artifact rules"))?;
+        let storage_classes = config
+            .storage_classes()
+            .wrap_err("checking artifact rules")?;
+        Ok(Self {
+            rules: config.rules.into_iter().chain(builtin_rules()).collect(),
+            storage_classes,
+        })
+    }
+
+    pub fn classify<T: AsRef<Path>>(&self, target: &RustTarget, path: T) -> Option<&Rule> {
+        let name = path.as_ref().file_name()?.to_str()?;
+        let platform = target.platform_name();
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| {
+                rule.platforms.is_empty() || rule.platforms.iter().any(|p| p == platform)
+            })
+            .filter(|(_, rule)| name.ends_with(&rule.suffix))
+            .max_by_key(|(index, rule)| (rule.suffix.len(), std::cmp::Reverse(*index)))
+            .map(|(_, rule)| rule)
+    }
+
+    pub fn kind<T: AsRef<Path>>(&self, target: &RustTarget, path: T) -> Option<ArtifactKind> {
+        self.classify(target, path).map(|rule| rule.kind)
+    }
+
+    /// storage class the file is uploaded with, `None` leaves it to the bucket
+    pub fn storage_class<T: AsRef<Path>>(&self, target: &RustTarget, path: T) -> Option<&str> {
+        self.kind(target, path)
+            .and_then(|kind| self.kind_storage_class(kind))
+    }
+
+    /// storage class objects of the kind are uploaded with, `None` for the default class of the bucket
+    pub fn kind_storage_class(&self, kind: ArtifactKind) -> Option<&str> {
+        self.storage_classes.get(&kind).map(String::as_str)
+    }
+
+    /// suffixes of the updater archives the updater may be pointed at
+    pub fn updater_suffixes(&self, target: &RustTarget, installer: InstallerKind) -> Vec<&str> {
+        let platform = target.platform_name();
+        self.rules
+            .iter()
+            .filter(|rule| rule.kind == ArtifactKind::UpdaterArchive)
+            .filter(|rule| {
+                rule.platforms.is_empty() || rule.platforms.iter().any(|p| p == platform)
+            })
+            .filter(|rule| rule.installer.map(|kind| kind == installer).unwrap_or(true))
+            .map(|rule| rule.suffix.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_classification() {
+        let classifier = Classifier::default();
+        let kind = |target, path| classifier.kind(&target, path);
+        assert_eq!(
+            kind(RustTarget::MacOsX86_64, "bundle/dmg/App_1.7.9_x64.dmg"),
+            Some(ArtifactKind::Installer)
+        );
+        assert_eq!(
+            kind(RustTarget::MacOsX86_64, "bundle/macos/App.app.tar.gz"),
+            Some(ArtifactKind::UpdaterArchive)
+        );
+        assert_eq!(
+            kind(RustTarget::Linux64, "bundle/rpm/app-1.7.9-1.x86_64.rpm"),
+            Some(ArtifactKind::Package)
+        );
+        assert_eq!(
+            kind(
+                RustTarget::Linux64,
+                "bundle/appimage/app.AppImage.tar.gz.sig"
+            ),
+            Some(ArtifactKind::Signature)
+        );
+        assert_eq!(
+            kind(RustTarget::Win64, "bundle/msi/app_1.7.9_x64_en-US.msi.zip"),
+            Some(ArtifactKind::UpdaterArchive)
+        );
+        assert_eq!(kind(RustTarget::Win64, "bundle/msi/app.pdb"), None);
+        assert_eq!(
+            classifier
+                .classify(&RustTarget::Linux64, "flatpak/random-app.flatpak")
+                .and_then(|rule| rule.content_type.as_deref()),
+            Some("application/vnd.flatpak")
+        );
+    }
+
+    #[test]
+    fn test_configured_rules_extend_the_table() -> Result<()> {
+        let config: RulesConfig = toml::from_str(
+            "[[rules]]\nsuffix = \".pdb\"\nkind = \"symbols\"\nplatforms = [\"windows\"]\n\n[[rules]]\nsuffix = \".msi\"\nkind = \"installer\"\ncontent_type = \"application/x-msi\"\n",
+        )?;
+        let classifier = Classifier {
+            storage_classes: config.storage_classes()?,
+            rules: config.rules.into_iter().chain(builtin_rules()).collect(),
+        };
+        assert_eq!(
+            classifier.kind(&RustTarget::Win64, "app.pdb"),
+            Some(ArtifactKind::Symbols)
+        );
+        assert_eq!(classifier.kind(&RustTarget::Linux64, "app.pdb"), None);
+        assert_eq!(
+            classifier.kind(&RustTarget::Win64, "app.msi"),
+            Some(ArtifactKind::Installer)
+        );
+        assert_eq!(
+            classifier.updater_suffixes(&RustTarget::Win64, InstallerKind::Nsis),
+            vec![".nsis.zip"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_storage_classes_by_kind() -> Result<()> {
+        let config: RulesConfig = toml::from_str(
+            "[storage_classes]\nsymbols = \"GLACIER_IR\"\ninstaller = \"STANDARD_IA\"\n",
+        )?;
+        let classifier = Classifier {
+            rules: builtin_rules(),
+            storage_classes: config.storage_classes()?,
+        };
+        assert_eq!(
+            classifier.storage_class(&RustTarget::MacOsX86_64, "App_1.7.9_x64.dmg"),
+            Some("STANDARD_IA")
+        );
+        assert_eq!(
+            classifier.storage_class(&RustTarget::MacOsX86_64, "App.app.tar.gz"),
+            None
+        );
+        let archived: RulesConfig =
+            toml::from_str("[storage_classes]\nupdater_archive = \"DEEP_ARCHIVE\"\n")?;
+        assert!(archived.storage_classes().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use super::*;
+
+/// signing key env pairs of the tauri cli - (tauri 1 name, tauri 2 name)
+const SIGNING_ENVS: &[(&str, &str)] = &[
+    ("TAURI_PRIVATE_KEY", "TAURI_SIGNING_PRIVATE_KEY"),
+    ("TAURI_KEY_PASSWORD", "TAURI_SIGNING_PRIVATE_KEY_PASSWORD"),
+];
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BuildArgs {
+    /// extra argument passed on to `tauri build` (e.g. `--build-arg=--bundles --build-arg=msi`), can be repeated
+    #[clap(long = "build-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub build_args: Vec<String>,
+    /// only upload the binaries and stage the release (like upload-artifacts), leaving publish to a later step
+    #[clap(long)]
+    pub stage_only: bool,
+}
+
+/// the signing env vars the tauri cli of the config's major version reads, filled in from the other version's names when only those are set
+///
+/// whatever is already set under the right name is inherited by the build as is
+fn signing_env(v2: bool, var: impl Fn(&str) -> Option<String>) -> Vec<(&'static str, String)> {
+    SIGNING_ENVS
+        .iter()
+        .filter_map(|&(v1_name, v2_name)| {
+            let (wanted, other) = if v2 {
+                (v2_name, v1_name)
+            } else {
+                (v1_name, v2_name)
+            };
+            match var(wanted) {
+                Some(_) => None,
+                None => var(other).map(|value| (wanted, value)),
+            }
+        })
+        .collect()
+}
+
+fn build_command_args(target: &RustTarget, args: &BuildArgs) -> Result<Vec<String>> {
+    let triple = serde_variant::to_variant_name(target).wrap_err("bad variant?")?;
+    Ok(["build", "--target", triple]
+        .into_iter()
+        .map(ToString::to_string)
+        .chain(args.build_args.iter().cloned())
+        .collect())
+}
+
+/// runs `{tauri_cli} build --target {target}` with the updater signing key passed through under the name the cli expects, output goes straight to the console
+pub async fn run(
+    tauri_conf_json: &TauriConfJson,
+    target: &RustTarget,
+    tauri_cli: &str,
+    args: &BuildArgs,
+) -> Result<()> {
+    let v2 = matches!(tauri_conf_json, TauriConfJson::V2(_));
+    let env = signing_env(v2, |name| std::env::var(name).ok());
+    for (name, _) in &env {
+        info!("passing the signing key env on as {name}");
+    }
+    let signing_key = if v2 {
+        SIGNING_ENVS[0].1
+    } else {
+        SIGNING_ENVS[0].0
+    };
+    if tauri_conf_json.updater_pubkey().is_some()
+        && std::env::var_os(signing_key).is_none()
+        && !env.iter().any(|(name, _)| *name == signing_key)
+    {
+        warn!("no {signing_key} set - the updater bundle won't be signed by the build");
+    }
+    let mut command = tauri_cli.split_whitespace();
+    let program = command
+        .next()
+        .ok_or_else(|| eyre::eyre!("--tauri-cli is empty"))?;
+    let build_args = build_command_args(target, args)?;
+    info!("building :: {tauri_cli} {}", build_args.join(" "));
+    let status = tokio::process::Command::new(program)
+        .args(command)
+        .args(&build_args)
+        .envs(env)
+        .status()
+        .await
+        .wrap_err_with(|| format!("running `{tauri_cli} build`"))?;
+    if !status.success() {
+        bail!("`{tauri_cli} build` failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_env() {
+        let env = |name: &str| match name {
+            "TAURI_PRIVATE_KEY" => Some("key".to_string()),
+            "TAURI_KEY_PASSWORD" => Some("password".to_string()),
+            _ => None,
+        };
+        assert_eq!(signing_env(false, env), vec![]);
+        assert_eq!(
+            signing_env(true, env),
+            vec![
+                ("TAURI_SIGNING_PRIVATE_KEY", "key".to_string()),
+                ("TAURI_SIGNING_PRIVATE_KEY_PASSWORD", "password".to_string()),
+            ]
+        );
+        assert_eq!(
+            build_command_args(
+                &RustTarget::Win64,
+                &BuildArgs {
+                    build_args: vec!["--bundles".to_string(), "msi".to_string()],
+                    stage_only: false,
+                }
+            )
+            .unwrap(),
+            vec![
+                "build",
+                "--target",
+                "x86_64-pc-windows-msvc",
+                "--bundles",
+                "msi"
+            ]
+        );
+    }
+}
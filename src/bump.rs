@@ -0,0 +1,227 @@
+use super::*;
+use semver::{
+    BuildMetadata,
+    Prerelease,
+    Version,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, IntoEnumIterator)]
+#[serde(rename_all = "snake_case")]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+    Pre,
+}
+
+impl FromStr for BumpLevel {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        matched_variant!(Self, s)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BumpArgs {
+    #[clap(long, value_name = "patch|minor|major|pre")]
+    pub level: BumpLevel,
+    /// prerelease identifier used when `--level pre` starts a new prerelease
+    #[clap(long, default_value = "rc", value_name = "ID")]
+    pub pre_id: String,
+    /// also bump `package.version` of the Cargo.toml next to the tauri config
+    #[clap(long)]
+    pub cargo_toml: bool,
+    /// also bump `version` of the package.json in the parent directory of the tauri config
+    #[clap(long)]
+    pub package_json: bool,
+}
+
+/// `pre` bumps the trailing number of an existing prerelease (`1.2.3-rc.1` -> `1.2.3-rc.2`) or starts one for the next patch (`1.2.3` -> `1.2.4-rc.0`), the other levels release a prerelease as is
+fn bumped(version: &Version, level: BumpLevel, pre_id: &str) -> Result<Version> {
+    let mut next = Version {
+        pre: Prerelease::EMPTY,
+        build: BuildMetadata::EMPTY,
+        ..version.clone()
+    };
+    match level {
+        BumpLevel::Major => {
+            next.major += 1;
+            next.minor = 0;
+            next.patch = 0;
+        }
+        BumpLevel::Minor => {
+            next.minor += 1;
+            next.patch = 0;
+        }
+        BumpLevel::Patch if !version.pre.is_empty() => {}
+        BumpLevel::Patch => next.patch += 1,
+        BumpLevel::Pre if version.pre.is_empty() => {
+            next.patch += 1;
+            next.pre = Prerelease::new(&format!("{pre_id}.0")).wrap_err("bad prerelease id")?;
+        }
+        BumpLevel::Pre => {
+            let pre = match version.pre.as_str().rsplit_once('.') {
+                Some((id, number)) => match number.parse::<u64>() {
+                    Ok(number) => format!("{id}.{}", number + 1),
+                    Err(_) => format!("{}.0", version.pre),
+                },
+                None => format!("{}.0", version.pre),
+            };
+            next.pre = Prerelease::new(&pre).wrap_err("bad prerelease")?;
+        }
+    }
+    Ok(next)
+}
+
+/// replaces the first `"version": "<old>"` in a json file, leaving the rest of the file (key order, formatting) as is
+fn replace_json_version(content: &str, old: &str, new: &str) -> Option<String> {
+    let key = content.find("\"version\"")?;
+    let value_start = key + content[key..].find(':')? + 1;
+    let quoted = format!("\"{old}\"");
+    let trimmed = content[value_start..].trim_start();
+    if !trimmed.starts_with(&quoted) {
+        return None;
+    }
+    let old_start = content.len() - trimmed.len();
+    Some(format!(
+        "{}\"{new}\"{}",
+        &content[..old_start],
+        &content[old_start + quoted.len()..]
+    ))
+}
+
+/// replaces `version = "<old>"` in the `[package]` section of a Cargo.toml, comments and formatting are kept
+fn replace_cargo_toml_version(content: &str, old: &str, new: &str) -> Option<String> {
+    let mut in_package = false;
+    let mut replaced = false;
+    let lines = content
+        .split_inclusive('\n')
+        .map(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                in_package = trimmed == "[package]";
+            } else if in_package && !replaced {
+                if let Some((key, value)) = trimmed.split_once('=') {
+                    if key.trim() == "version" && value.trim().starts_with(&format!("\"{old}\"")) {
+                        replaced = true;
+                        return line.replacen(&format!("\"{old}\""), &format!("\"{new}\""), 1);
+                    }
+                }
+            }
+            line.to_string()
+        })
+        .collect::<String>();
+    replaced.then_some(lines)
+}
+
+fn bump_file(path: &Path, old: &str, new: &str) -> Result<()> {
+    let content =
+        std::fs::read_to_string(path).wrap_err_with(|| format!("reading [{}]", path.display()))?;
+    let bumped = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => replace_cargo_toml_version(&content, old, new),
+        _ => replace_json_version(&content, old, new),
+    }
+    .ok_or_else(|| eyre::eyre!("version {old} not found in [{}]", path.display()))?;
+    files::write_atomic(path, bumped)?;
+    info!("{} :: {old} -> {new}", path.display());
+    Ok(())
+}
+
+/// bumps the version in the tauri config (or in the package.json it points to) and optionally in Cargo.toml / package.json, prints the new version as the last line
+pub fn run(config_path: &Path, args: &BumpArgs) -> Result<()> {
+    let format = tauri_conf_json::ConfigFormat::from_path(config_path);
+    let mut config = std::fs::read_to_string(config_path)
+        .wrap_err_with(|| format!("reading [{}]", config_path.display()))
+        .and_then(|content| format.parse_value(&content))?;
+    let tauri_conf_json: TauriConfJson =
+        serde_json::from_value(config.clone()).wrap_err("bad format for tauri.conf.json")?;
+    let current = tauri_conf_json.version().to_string();
+    let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+    // tauri allows the version to be a path to a package.json holding the actual version
+    let version_file = current
+        .ends_with(".json")
+        .then(|| config_dir.join(&current));
+    let current = match &version_file {
+        Some(version_file) => std::fs::read_to_string(version_file)
+            .wrap_err_with(|| format!("reading [{}]", version_file.display()))
+            .and_then(|content| {
+                serde_json::from_str::<serde_json::Value>(&content).wrap_err("parsing version file")
+            })?
+            .get("version")
+            .and_then(|version| version.as_str())
+            .map(ToString::to_string)
+            .ok_or_else(|| eyre::eyre!("no version in [{}]", version_file.display()))?,
+        None => current,
+    };
+    let version = Version::parse(&current)
+        .wrap_err_with(|| format!("current version [{current}] is not valid semver"))?;
+    let next = bumped(&version, args.level, &args.pre_id)?.to_string();
+
+    match &version_file {
+        Some(version_file) => bump_file(version_file, &current, &next)?,
+        None => {
+            let pointer = match tauri_conf_json {
+                TauriConfJson::V1(_) => "/package/version",
+                TauriConfJson::V2(_) => "/version",
+            };
+            if let Some(version) = config.pointer_mut(pointer) {
+                *version = serde_json::Value::String(next.clone());
+            }
+            files::write_atomic(config_path, format.render_value(&config)?)?;
+            info!("{} :: {current} -> {next}", config_path.display());
+        }
+    }
+    if args.cargo_toml {
+        bump_file(&config_dir.join("Cargo.toml"), &current, &next)?;
+    }
+    let package_json = config_dir.join("..").join("package.json");
+    if args.package_json && version_file.as_ref() != Some(&package_json) {
+        bump_file(&package_json, &current, &next)?;
+    }
+    println!("{next}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bump(version: &str, level: BumpLevel) -> String {
+        bumped(&Version::parse(version).unwrap(), level, "rc")
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_bump_levels() {
+        assert_eq!(bump("1.7.9", BumpLevel::Patch), "1.7.10");
+        assert_eq!(bump("1.7.9", BumpLevel::Minor), "1.8.0");
+        assert_eq!(bump("1.7.9", BumpLevel::Major), "2.0.0");
+        assert_eq!(bump("1.7.9", BumpLevel::Pre), "1.7.10-rc.0");
+        assert_eq!(bump("1.7.10-rc.0", BumpLevel::Pre), "1.7.10-rc.1");
+        assert_eq!(bump("1.7.10-rc.1", BumpLevel::Patch), "1.7.10");
+    }
+
+    #[test]
+    fn test_version_replaced_in_place() {
+        assert_eq!(
+            replace_json_version(
+                "{\n  \"name\": \"app\",\n  \"version\": \"1.7.9\",\n  \"b\": 1\n}",
+                "1.7.9",
+                "1.8.0"
+            )
+            .as_deref(),
+            Some("{\n  \"name\": \"app\",\n  \"version\": \"1.8.0\",\n  \"b\": 1\n}")
+        );
+        assert_eq!(
+            replace_cargo_toml_version(
+                "[package]\nname = \"app\"\nversion = \"1.7.9\" # bumped by CI\n\n[dependencies]\nserde = { version = \"1.7.9\" }\n",
+                "1.7.9",
+                "1.8.0"
+            )
+            .as_deref(),
+            Some("[package]\nname = \"app\"\nversion = \"1.8.0\" # bumped by CI\n\n[dependencies]\nserde = { version = \"1.7.9\" }\n")
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use super::*;
+use std::time::SystemTime;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheArgs {
+    /// directory downloaded artifacts are kept in and shared by every command and concurrent run [default: <temp dir>/tauri-static-deployer-cache]
+    #[clap(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// least recently used downloads are evicted once the cache grows past this many MiB
+    #[clap(long, default_value_t = 2048, value_name = "MIB")]
+    pub cache_max_size: u64,
+    /// always download, neither reading nor filling the cache
+    #[clap(long)]
+    pub no_cache: bool,
+}
+
+impl Default for CacheArgs {
+    fn default() -> Self {
+        Self {
+            cache_dir: None,
+            cache_max_size: 2048,
+            no_cache: false,
+        }
+    }
+}
+
+impl CacheArgs {
+    fn dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("tauri-static-deployer-cache"))
+    }
+}
+
+static CACHE: std::sync::OnceLock<CacheArgs> = std::sync::OnceLock::new();
+
+/// every download made afterwards goes through this cache, the defaults apply until it's set
+pub fn set_cache(args: CacheArgs) {
+    if CACHE.set(args).is_err() {
+        warn!("cache is set already, keeping the first one");
+    }
+}
+
+/// what is known about the object a url served when it was cached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    sha256: String,
+    etag: String,
+}
+
+/// locks the file, waiting for other processes on a blocking thread
+async fn lock(path: PathBuf) -> Result<files::FileLock> {
+    tokio::task::spawn_blocking(move || files::FileLock::acquire(path))
+        .await
+        .wrap_err("waiting for a cache lock")?
+}
+
+/// a cached object, kept from being evicted for as long as this is around
+struct Cached {
+    path: PathBuf,
+    sha256: String,
+    _object_lock: files::FileLock,
+}
+
+/// the etag the url serves right now, a cached copy is only used while it's unchanged
+async fn current_etag(url: &str) -> Option<String> {
+    let response = reqwest::Client::new()
+        .head(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| debug!("cache :: HEAD [{url}] failed :: {e}"))
+        .ok()?;
+    response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string)
+}
+
+/// oldest first, objects are evicted until the total fits, the one just added is always kept
+fn evictions(
+    mut objects: Vec<(PathBuf, u64, SystemTime)>,
+    max_size: u64,
+    keep: &Path,
+) -> Vec<PathBuf> {
+    let mut total: u64 = objects.iter().map(|(_, size, _)| size).sum();
+    objects.sort_by_key(|(_, _, used)| *used);
+    let mut evicted = vec![];
+    for (path, size, _) in objects {
+        if total <= max_size {
+            break;
+        }
+        if path != keep {
+            total -= size;
+            evicted.push(path);
+        }
+    }
+    evicted
+}
+
+fn evict(objects_dir: &Path, max_size: u64, keep: &Path) -> Result<()> {
+    let objects = std::fs::read_dir(objects_dir)
+        .wrap_err_with(|| format!("listing [{}]", objects_dir.display()))?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            let path = entry.path();
+            // downloads still in progress are `.partial`
+            if !metadata.is_file() || path.extension().is_some() {
+                return None;
+            }
+            Some((path, metadata.len(), metadata.modified().ok()?))
+        })
+        .collect();
+    for path in evictions(objects, max_size, keep) {
+        // objects other runs are reading right now stay, the cache shrinks further once they're done
+        match files::FileLock::try_acquire(&path)? {
+            Some(_lock) => {
+                debug!("cache :: evicting [{}]", path.display());
+                std::fs::remove_file(&path).ok();
+            }
+            None => debug!("cache :: [{}] is in use, not evicting it", path.display()),
+        }
+    }
+    Ok(())
+}
+
+/// marks the object as recently used, eviction goes by modification time
+fn touch(path: &Path) {
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+        .ok();
+}
+
+/// the cached copy of what the url serves, downloading it unless an unchanged copy is cached already
+async fn fetch(args: &CacheArgs, url: &str) -> Result<Cached> {
+    let dir = args.dir();
+    let objects_dir = dir.join("objects");
+    let urls_dir = dir.join("urls");
+    for dir in [&objects_dir, &urls_dir] {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .wrap_err_with(|| format!("creating [{}]", dir.display()))?;
+    }
+    let url_hash = checksum::sha256_bytes(url.as_bytes());
+    // only one process downloads a given url at a time
+    let _url_lock = lock(urls_dir.join(&url_hash)).await?;
+    let entry_path = urls_dir.join(format!("{url_hash}.json"));
+    let etag = current_etag(url).await;
+    let cached = std::fs::read(&entry_path)
+        .ok()
+        .and_then(|content| serde_json::from_slice::<Entry>(&content).ok())
+        .filter(|entry| Some(&entry.etag) == etag.as_ref());
+    if let Some(entry) = cached {
+        let path = objects_dir.join(&entry.sha256);
+        let object_lock = lock(path.clone()).await?;
+        // evicted before it could be locked
+        if path.is_file() {
+            debug!("cache :: hit [{url}]");
+            touch(&path);
+            return Ok(Cached {
+                path,
+                sha256: entry.sha256,
+                _object_lock: object_lock,
+            });
+        }
+    }
+    let partial = objects_dir.join(format!("{}.partial", uuid::Uuid::new_v4()));
+    let sha256 = match checksum::download_to(url, &partial).await {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            tokio::fs::remove_file(&partial).await.ok();
+            return Err(e);
+        }
+    };
+    let path = objects_dir.join(&sha256);
+    let object_lock = lock(path.clone()).await?;
+    tokio::fs::rename(&partial, &path)
+        .await
+        .wrap_err_with(|| format!("moving download to [{}]", path.display()))?;
+    match etag {
+        Some(etag) => {
+            let entry = Entry {
+                url: url.to_string(),
+                sha256: sha256.clone(),
+                etag,
+            };
+            let content = serde_json::to_vec_pretty(&entry).wrap_err("serializing cache entry")?;
+            files::write_atomic(&entry_path, &content).wrap_err("writing cache entry")?;
+        }
+        None => debug!("cache :: [{url}] has no etag, it's downloaded again next time"),
+    }
+    evict(&objects_dir, args.cache_max_size * 1024 * 1024, &path)?;
+    Ok(Cached {
+        path,
+        sha256,
+        _object_lock: object_lock,
+    })
+}
+
+/// sha256 of what the url serves
+pub async fn sha256(url: &str) -> Result<String> {
+    let args = CACHE.get_or_init(Default::default);
+    if args.no_cache {
+        return checksum::sha256_url(url).await;
+    }
+    fetch(args, url).await.map(|cached| cached.sha256)
+}
+
+/// copies what the url serves into the file, returns its sha256
+pub async fn download_to<T: AsRef<Path>>(url: &str, path: T) -> Result<String> {
+    let path = path.as_ref();
+    let args = CACHE.get_or_init(Default::default);
+    if args.no_cache {
+        return checksum::download_to(url, path).await;
+    }
+    // the object stays locked until it's copied, eviction by a concurrent run leaves it alone
+    let cached = fetch(args, url).await?;
+    tokio::fs::copy(&cached.path, path)
+        .await
+        .wrap_err_with(|| {
+            format!(
+                "copying [{}] to [{}]",
+                cached.path.display(),
+                path.display()
+            )
+        })?;
+    Ok(cached.sha256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_evictions_go_least_recently_used_first() {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let objects = vec![
+            (PathBuf::from("new"), 40, at(3)),
+            (PathBuf::from("old"), 40, at(1)),
+            (PathBuf::from("added"), 40, at(0)),
+            (PathBuf::from("middle"), 40, at(2)),
+        ];
+        assert_eq!(
+            evictions(objects, 100, Path::new("added")),
+            vec![PathBuf::from("old"), PathBuf::from("middle")]
+        );
+    }
+
+    #[test]
+    fn test_objects_in_use_are_not_evicted() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("cache-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        for name in ["in-use", "unused", "added"] {
+            std::fs::write(dir.join(name), "content")?;
+        }
+        let lock = files::FileLock::acquire(dir.join("in-use"))?;
+        evict(&dir, 0, &dir.join("added"))?;
+        assert!(dir.join("in-use").is_file());
+        assert!(!dir.join("unused").exists());
+        assert!(dir.join("added").is_file());
+        drop(lock);
+        evict(&dir, 0, &dir.join("added"))?;
+        assert!(!dir.join("in-use").exists());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
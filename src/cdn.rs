@@ -0,0 +1,565 @@
+use super::*;
+use crate::deploy::Deployment;
+use std::collections::BTreeMap;
+
+/// distribution in front of the bucket, `--cloudfront-distribution-id` takes precedence
+pub const CLOUDFRONT_DISTRIBUTION_ENV: &str = "DEPLOY_CLOUDFRONT_DISTRIBUTION_ID";
+
+/// zone the bucket is served through, for `--purge cloudflare`
+pub const CLOUDFLARE_ZONE_ENV: &str = "CLOUDFLARE_ZONE_ID";
+
+/// needs the `Zone.Cache Purge` permission
+pub const CLOUDFLARE_TOKEN_ENV: &str = "CLOUDFLARE_API_TOKEN";
+
+/// cloudflare refuses purge requests with more urls than this
+const CLOUDFLARE_URLS_PER_REQUEST: usize = 30;
+
+const CLOUDFRONT_HOST: &str = "cloudfront.amazonaws.com";
+
+/// cloudfront is a global service, its api is always signed for this region
+const CLOUDFRONT_REGION: &str = "us-east-1";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, IntoEnumIterator)]
+#[serde(rename_all = "snake_case")]
+pub enum Cdn {
+    Cloudfront,
+    Cloudflare,
+}
+
+impl FromStr for Cdn {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        matched_variant!(Self, s)
+    }
+}
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct CdnArgs {
+    /// invalidate the published manifests and downloads pages in this cloudfront distribution right after publishing [default: DEPLOY_CLOUDFRONT_DISTRIBUTION_ID]
+    #[clap(long, value_name = "ID")]
+    pub cloudfront_distribution_id: Option<String>,
+    /// purge the published manifests and downloads pages from this cdn right after publishing, repeatable (cloudfront, cloudflare)
+    #[clap(long = "purge", value_name = "CDN")]
+    pub purge: Vec<Cdn>,
+    /// toml file with http calls made after publishing, for cdns without a built-in purge
+    #[clap(long, value_name = "FILE")]
+    pub purge_hooks: Option<PathBuf>,
+}
+
+impl CdnArgs {
+    fn cloudfront_distribution_id(&self) -> Option<String> {
+        self.cloudfront_distribution_id
+            .clone()
+            .or_else(|| std::env::var(CLOUDFRONT_DISTRIBUTION_ENV).ok())
+            .filter(|id| !id.trim().is_empty())
+    }
+
+    fn purges(&self, cdn: Cdn) -> bool {
+        self.purge.contains(&cdn)
+    }
+}
+
+/// a request to an aws api, signed with signature version 4
+struct AwsRequest<'a> {
+    method: &'a str,
+    host: &'a str,
+    path: &'a str,
+    region: &'a str,
+    service: &'a str,
+    /// lowercase names, `host` and `x-amz-date` are added when signing
+    headers: Vec<(&'a str, String)>,
+    payload: &'a [u8],
+}
+
+impl AwsRequest<'_> {
+    /// every header to send, `authorization` included
+    fn signed_headers(
+        &self,
+        access_key: &str,
+        secret_key: &str,
+        at: time::OffsetDateTime,
+    ) -> Result<Vec<(String, String)>> {
+        let at = at.to_offset(time::UtcOffset::UTC);
+        let amz_date = format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            at.year(),
+            at.month() as u8,
+            at.day(),
+            at.hour(),
+            at.minute(),
+            at.second()
+        );
+        let date = &amz_date[..8];
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+            .chain([
+                ("host".to_string(), self.host.to_string()),
+                ("x-amz-date".to_string(), amz_date.clone()),
+            ])
+            .sorted()
+            .collect_vec();
+        let signed_names = headers.iter().map(|(name, _)| name.as_str()).join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n\n{signed_names}\n{}",
+            self.method,
+            self.path,
+            headers
+                .iter()
+                .map(|(name, value)| format!("{name}:{value}"))
+                .join("\n"),
+            checksum::sha256_bytes(self.payload)
+        );
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            checksum::sha256_bytes(canonical_request.as_bytes())
+        );
+        let signing_key = [self.region, self.service, "aws4_request"]
+            .into_iter()
+            .fold(
+                checksum::hmac_sha256(format!("AWS4{secret_key}").as_bytes(), date.as_bytes()),
+                |key, part| checksum::hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature = data_encoding::HEXLOWER.encode(&checksum::hmac_sha256(
+            &signing_key,
+            string_to_sign.as_bytes(),
+        ));
+        Ok(headers
+            .into_iter()
+            .chain([(
+                "authorization".to_string(),
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/{scope}, SignedHeaders={signed_names}, Signature={signature}"
+                ),
+            )])
+            .collect())
+    }
+}
+
+struct AwsCredentials {
+    access_key: String,
+    secret_key: String,
+    /// temporary credentials (an assumed role, sso) only work together with their session token
+    session_token: Option<String>,
+}
+
+/// the aws credentials if set, otherwise the bucket credentials - often the same account
+fn aws_credentials() -> Result<AwsCredentials> {
+    let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    if let (Some(access_key), Some(secret_key)) =
+        (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY"))
+    {
+        return Ok(AwsCredentials {
+            access_key,
+            secret_key,
+            session_token: var("AWS_SESSION_TOKEN"),
+        });
+    }
+    let var = |aws: &str, s3: &str| {
+        std::env::var(aws)
+            .or_else(|_| std::env::var(s3))
+            .wrap_err_with(|| format!("neither {aws} nor {s3} is set"))
+    };
+    Ok(AwsCredentials {
+        access_key: var("AWS_ACCESS_KEY_ID", "S3_ACCESS_KEY")?,
+        secret_key: var("AWS_SECRET_ACCESS_KEY", "S3_SECRET_KEY")?,
+        session_token: None,
+    })
+}
+
+fn invalidation_batch(paths: &[String], caller_reference: &str) -> String {
+    let items = paths
+        .iter()
+        .map(|path| format!("<Path>{}</Path>", downloads::escape_html(path)))
+        .join("");
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<InvalidationBatch xmlns=\"http://cloudfront.amazonaws.com/doc/2020-05-31/\"><Paths><Quantity>{}</Quantity><Items>{items}</Items></Paths><CallerReference>{caller_reference}</CallerReference></InvalidationBatch>",
+        paths.len()
+    )
+}
+
+async fn invalidate_cloudfront(distribution_id: &str, paths: &[String]) -> Result<()> {
+    let credentials = aws_credentials()?;
+    let path = format!("/2020-05-31/distribution/{distribution_id}/invalidation");
+    let body = invalidation_batch(paths, &uuid::Uuid::new_v4().to_string());
+    // the same caller reference on every retry, so a retried request doesn't create a second invalidation
+    storage::retry(&format!("invalidating {distribution_id}"), || async {
+        let request = AwsRequest {
+            method: "POST",
+            host: CLOUDFRONT_HOST,
+            path: &path,
+            region: CLOUDFRONT_REGION,
+            service: "cloudfront",
+            headers: std::iter::once(("content-type", "text/xml".to_string()))
+                .chain(
+                    credentials
+                        .session_token
+                        .clone()
+                        .map(|token| ("x-amz-security-token", token)),
+                )
+                .collect(),
+            payload: body.as_bytes(),
+        };
+        let headers = request.signed_headers(
+            &credentials.access_key,
+            &credentials.secret_key,
+            time::OffsetDateTime::now_utc(),
+        )?;
+        let response = headers
+            .iter()
+            .filter(|(name, _)| name != "host")
+            .fold(
+                reqwest::Client::new().post(format!("https://{CLOUDFRONT_HOST}{path}")),
+                |request, (name, value)| request.header(name, value),
+            )
+            .body(body.clone())
+            .send()
+            .await
+            .wrap_err("requesting cloudfront invalidation")?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            bail!(
+                "cloudfront invalidation failed, Got HTTP {} :: {message}",
+                status.as_u16()
+            );
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// substituted in urls, headers and bodies with the url of each published document, the hook is called once per url
+const MANIFEST_URL_PLACEHOLDER: &str = "{{manifest_url}}";
+
+/// `{{env.NAME}}` is substituted with the environment variable, so tokens stay out of the hooks file
+const ENV_PLACEHOLDER_PREFIX: &str = "{{env.";
+
+fn default_hook_method() -> String {
+    "POST".to_string()
+}
+
+/// a single http call, e.g. `PURGE {{manifest_url}}` for fastly
+#[derive(Debug, Clone, Deserialize)]
+struct PurgeHook {
+    #[serde(default = "default_hook_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PurgeHooksConfig {
+    #[serde(default)]
+    hooks: Vec<PurgeHook>,
+}
+
+/// substitutes the placeholders, unknown or unset ones are an error rather than an empty string in a url
+fn render(template: &str, manifest_url: Option<&str>) -> Result<String> {
+    let mut rendered = match manifest_url {
+        Some(url) => template.replace(MANIFEST_URL_PLACEHOLDER, url),
+        None => template.to_string(),
+    };
+    while let Some(start) = rendered.find(ENV_PLACEHOLDER_PREFIX) {
+        let name_start = start + ENV_PLACEHOLDER_PREFIX.len();
+        let end = rendered[name_start..]
+            .find("}}")
+            .map(|end| name_start + end)
+            .ok_or_else(|| eyre::eyre!("unterminated placeholder in [{template}]"))?;
+        let value = required_env(&rendered[name_start..end])?;
+        rendered.replace_range(start..end + 2, &value);
+    }
+    if let Some(start) = rendered.find("{{") {
+        bail!(
+            "unknown placeholder [{}] in [{template}]",
+            rendered[start..].split("}}").next().unwrap_or_default()
+        );
+    }
+    Ok(rendered)
+}
+
+impl PurgeHook {
+    fn uses_manifest_url(&self) -> bool {
+        std::iter::once(&self.url)
+            .chain(self.headers.values())
+            .chain(self.body.as_ref())
+            .any(|template| template.contains(MANIFEST_URL_PLACEHOLDER))
+    }
+
+    /// what the retries log, `{{env.*}}` stays unsubstituted so tokens in the url don't end up in the logs
+    fn label(&self, method: &reqwest::Method, manifest_url: Option<&str>) -> String {
+        let url = match manifest_url {
+            Some(manifest_url) => self.url.replace(MANIFEST_URL_PLACEHOLDER, manifest_url),
+            None => self.url.clone(),
+        };
+        format!("{method} {url}")
+    }
+
+    async fn call(&self, manifest_url: Option<&str>) -> Result<()> {
+        let method = reqwest::Method::from_bytes(self.method.to_uppercase().as_bytes())
+            .wrap_err_with(|| format!("bad method [{}]", self.method))?;
+        let url = render(&self.url, manifest_url)?;
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), render(value, manifest_url)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let body = self
+            .body
+            .as_deref()
+            .map(|body| render(body, manifest_url))
+            .transpose()?;
+        storage::retry(&self.label(&method, manifest_url), || async {
+            let request = headers.iter().fold(
+                reqwest::Client::new().request(method.clone(), &url),
+                |request, (name, value)| request.header(name, value),
+            );
+            let request = match &body {
+                Some(body) => request.body(body.clone()),
+                None => request,
+            };
+            // the error would name the rendered url
+            let response = request
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)
+                .wrap_err("calling purge hook")?;
+            let status = response.status();
+            if !status.is_success() {
+                let message = response.text().await.unwrap_or_default();
+                bail!(
+                    "purge hook failed, Got HTTP {} :: {message}",
+                    status.as_u16()
+                );
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+async fn run_purge_hooks(path: &Path, urls: &[String]) -> Result<usize> {
+    let config: PurgeHooksConfig = tokio::fs::read_to_string(path)
+        .await
+        .wrap_err_with(|| format!("reading purge hooks [{}]", path.display()))
+        .and_then(|content| toml::from_str(&content).wrap_err("parsing purge hooks"))?;
+    let mut calls = 0;
+    for hook in &config.hooks {
+        if hook.uses_manifest_url() {
+            for url in urls {
+                hook.call(Some(url)).await?;
+                calls += 1;
+            }
+        } else {
+            hook.call(None).await?;
+            calls += 1;
+        }
+    }
+    Ok(calls)
+}
+
+fn required_env(name: &str) -> Result<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| eyre::eyre!("{name} is not set"))
+}
+
+fn cloudflare_purge_body(urls: &[String]) -> serde_json::Value {
+    serde_json::json!({ "files": urls })
+}
+
+async fn purge_cloudflare(urls: &[String]) -> Result<()> {
+    let zone_id = required_env(CLOUDFLARE_ZONE_ENV)?;
+    let token = required_env(CLOUDFLARE_TOKEN_ENV)?;
+    let endpoint = format!("https://api.cloudflare.com/client/v4/zones/{zone_id}/purge_cache");
+    for chunk in urls.chunks(CLOUDFLARE_URLS_PER_REQUEST) {
+        let body = cloudflare_purge_body(chunk);
+        storage::retry(&format!("purging cloudflare zone {zone_id}"), || async {
+            let response = reqwest::Client::new()
+                .post(&endpoint)
+                .bearer_auth(&token)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await
+                .wrap_err("requesting cloudflare purge")?;
+            let status = response.status();
+            if !status.is_success() {
+                let message = response.text().await.unwrap_or_default();
+                bail!(
+                    "cloudflare purge failed, Got HTTP {} :: {message}",
+                    status.as_u16()
+                );
+            }
+            Ok(())
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+/// makes the cdn in front of the bucket drop its copies of what was just published, `extra_keys` on top of the documents every publish writes
+pub async fn purge(
+    deployment: &Deployment<'_>,
+    extra_keys: &[String],
+    args: &CdnArgs,
+) -> Result<()> {
+    let keys = deployment
+        .published_document_keys()
+        .into_iter()
+        .chain(extra_keys.iter().cloned())
+        .collect_vec();
+    purge_keys(deployment.s3_config, &keys, args).await
+}
+
+/// purges just the given keys
+pub async fn purge_keys(s3_config: &S3Config, keys: &[String], args: &CdnArgs) -> Result<()> {
+    let keys = keys.iter().unique().collect_vec();
+    let cloudfront_distribution_id = args.cloudfront_distribution_id();
+    if args.purges(Cdn::Cloudfront) && cloudfront_distribution_id.is_none() {
+        bail!("--purge cloudfront needs --cloudfront-distribution-id or {CLOUDFRONT_DISTRIBUTION_ENV}");
+    }
+    if let Some(distribution_id) = cloudfront_distribution_id {
+        let paths = keys
+            .iter()
+            .map(|key| {
+                format!(
+                    "/{}",
+                    namespacing::encode_key(&handle_s3::s3_path_with_subdirectory(s3_config, key))
+                )
+            })
+            .collect_vec();
+        invalidate_cloudfront(&distribution_id, &paths)
+            .await
+            .wrap_err_with(|| format!("invalidating cloudfront distribution {distribution_id}"))?;
+        info!(
+            "cdn :: invalidated {} paths in cloudfront {distribution_id}",
+            paths.len()
+        );
+    }
+    let urls = keys
+        .iter()
+        .map(|key| namespacing::s3_key_url(s3_config, key))
+        .collect_vec();
+    if args.purges(Cdn::Cloudflare) {
+        purge_cloudflare(&urls)
+            .await
+            .wrap_err("purging cloudflare cache")?;
+        info!("cdn :: purged {} urls from cloudflare", urls.len());
+    }
+    if let Some(path) = &args.purge_hooks {
+        let calls = run_purge_hooks(path, &urls)
+            .await
+            .wrap_err_with(|| format!("running purge hooks [{}]", path.display()))?;
+        info!("cdn :: made {calls} purge hook calls");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_v4() -> Result<()> {
+        // get-vanilla from the aws signature version 4 test suite
+        let request = AwsRequest {
+            method: "GET",
+            host: "example.amazonaws.com",
+            path: "/",
+            region: "us-east-1",
+            service: "service",
+            headers: vec![],
+            payload: b"",
+        };
+        let at = time::OffsetDateTime::parse(
+            "2015-08-30T12:36:00Z",
+            &time::format_description::well_known::Rfc3339,
+        )?;
+        let headers = request.signed_headers(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            at,
+        )?;
+        assert_eq!(
+            headers.last().map(|(_, value)| value.as_str()),
+            Some("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31")
+        );
+
+        // temporary credentials, the session token is sent and signed
+        let request = AwsRequest {
+            headers: vec![("x-amz-security-token", "token".to_string())],
+            ..request
+        };
+        let headers = request.signed_headers(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            at,
+        )?;
+        assert!(headers.contains(&("x-amz-security-token".to_string(), "token".to_string())));
+        assert!(headers.last().is_some_and(
+            |(_, value)| value.contains("SignedHeaders=host;x-amz-date;x-amz-security-token,")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_parses_cdn_names() -> Result<()> {
+        assert_eq!("cloudflare".parse::<Cdn>()?, Cdn::Cloudflare);
+        assert_eq!("cloudfront".parse::<Cdn>()?, Cdn::Cloudfront);
+        assert!("fastly".parse::<Cdn>().is_err());
+        assert_eq!(
+            cloudflare_purge_body(&["https://cdn.example.com/main/release-notes.json".to_string()]),
+            serde_json::json!({ "files": ["https://cdn.example.com/main/release-notes.json"] })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_hook_templates() -> Result<()> {
+        let config: PurgeHooksConfig = toml::from_str(
+            r#"
+[[hooks]]
+method = "PURGE"
+url = "{{manifest_url}}"
+
+[[hooks]]
+url = "https://api.example.com/purge_all"
+headers = { Authorization = "Bearer {{env.CDN_TEST_PURGE_TOKEN}}" }
+"#,
+        )?;
+        assert!(config.hooks[0].uses_manifest_url());
+        assert!(!config.hooks[1].uses_manifest_url());
+        assert_eq!(config.hooks[1].method, "POST");
+        assert_eq!(
+            render(
+                "{{manifest_url}}",
+                Some("https://cdn.example.com/main/latest.json")
+            )?,
+            "https://cdn.example.com/main/latest.json"
+        );
+        std::env::set_var("CDN_TEST_PURGE_TOKEN", "secret");
+        assert_eq!(
+            render("Bearer {{env.CDN_TEST_PURGE_TOKEN}}", None)?,
+            "Bearer secret"
+        );
+        assert!(render("{{env.CDN_TEST_UNSET_TOKEN}}", None).is_err());
+        assert!(render("{{manifest_path}}", Some("url")).is_err());
+        let hook = PurgeHook {
+            url: "https://api.example.com/purge?url={{manifest_url}}&token={{env.CDN_TEST_PURGE_TOKEN}}"
+                .to_string(),
+            ..config.hooks[1].clone()
+        };
+        assert_eq!(
+            hook.label(&reqwest::Method::POST, Some("https://cdn.example.com/main/latest.json")),
+            "POST https://api.example.com/purge?url=https://cdn.example.com/main/latest.json&token={{env.CDN_TEST_PURGE_TOKEN}}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use super::*;
+use md5::Md5;
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tokio::io::AsyncReadExt;
+
+pub fn sha256_bytes(content: &[u8]) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(content))
+}
+
+pub fn hmac_sha256(key: &[u8], content: &[u8]) -> Vec<u8> {
+    use hmac::Mac;
+    let mut mac = hmac::Hmac::<Sha256>::new_from_slice(key).expect("hmac takes keys of any size");
+    mac.update(content);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// hashes the file in chunks so large bundles don't have to fit in memory
+pub async fn sha256_file<T: AsRef<Path>>(path: T) -> Result<String> {
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .wrap_err_with(|| format!("opening [{}] for hashing", path.as_ref().display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .await
+            .wrap_err_with(|| format!("reading [{}] for hashing", path.as_ref().display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(data_encoding::HEXLOWER.encode(&hasher.finalize()))
+}
+
+/// the etag s3 gives an uploaded object - md5 of the content, or for a multipart upload md5 of the md5s of the parts followed by their count
+fn s3_etag(part_digests: &[Vec<u8>], multipart: bool) -> String {
+    if !multipart {
+        return part_digests
+            .first()
+            .map(|digest| data_encoding::HEXLOWER.encode(digest))
+            .unwrap_or_default();
+    }
+    let combined = Md5::digest(part_digests.concat());
+    format!(
+        "{}-{}",
+        data_encoding::HEXLOWER.encode(&combined),
+        part_digests.len()
+    )
+}
+
+/// etag of the content uploaded in one go (`part_size` is `None`) or in parts of `part_size` bytes
+pub fn s3_etag_bytes(content: &[u8], part_size: Option<u64>) -> String {
+    let part_digests = match part_size {
+        Some(part_size) => content
+            .chunks(part_size.max(1) as usize)
+            .map(|part| Md5::digest(part).to_vec())
+            .collect_vec(),
+        None => vec![Md5::digest(content).to_vec()],
+    };
+    s3_etag(&part_digests, part_size.is_some())
+}
+
+/// like [`s3_etag_bytes`], reading the file in chunks
+pub async fn s3_etag_file<T: AsRef<Path>>(path: T, part_size: Option<u64>) -> Result<String> {
+    s3_etag_and_sha256_file(path, part_size)
+        .await
+        .map(|(etag, _)| etag)
+}
+
+/// like [`s3_etag_file`], also hashing the file with sha256 in the same pass
+pub async fn s3_etag_and_sha256_file<T: AsRef<Path>>(
+    path: T,
+    part_size: Option<u64>,
+) -> Result<(String, String)> {
+    let path = path.as_ref();
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .wrap_err_with(|| format!("opening [{}] for hashing", path.display()))?;
+    let size = file
+        .metadata()
+        .await
+        .wrap_err_with(|| format!("reading size of [{}]", path.display()))?
+        .len();
+    let multipart = part_size.is_some();
+    let part_size = part_size.unwrap_or(size).max(1);
+    let mut buffer = vec![0; 1024 * 1024];
+    let mut part_digests = vec![];
+    let mut sha256 = Sha256::new();
+    let mut remaining = size;
+    while remaining > 0 || part_digests.is_empty() {
+        let mut hasher = Md5::new();
+        let mut left = part_size.min(remaining);
+        remaining -= left;
+        while left > 0 {
+            let read = (buffer.len() as u64).min(left) as usize;
+            file.read_exact(&mut buffer[..read])
+                .await
+                .wrap_err_with(|| format!("reading [{}] for hashing", path.display()))?;
+            hasher.update(&buffer[..read]);
+            sha256.update(&buffer[..read]);
+            left -= read as u64;
+        }
+        part_digests.push(hasher.finalize().to_vec());
+    }
+    Ok((
+        s3_etag(&part_digests, multipart),
+        data_encoding::HEXLOWER.encode(&sha256.finalize()),
+    ))
+}
+
+/// downloads the url and hashes the response body as it arrives
+pub async fn sha256_url(url: &str) -> Result<String> {
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .wrap_err_with(|| format!("downloading [{url}]"))?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .wrap_err_with(|| format!("downloading [{url}]"))?
+    {
+        hasher.update(&chunk);
+    }
+    Ok(data_encoding::HEXLOWER.encode(&hasher.finalize()))
+}
+
+/// downloads the url into the file, hashing the body as it's written
+pub async fn download_to<T: AsRef<Path>>(url: &str, path: T) -> Result<String> {
+    use tokio::io::AsyncWriteExt;
+    let path = path.as_ref();
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .wrap_err_with(|| format!("downloading [{url}]"))?;
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .wrap_err_with(|| format!("creating [{}]", path.display()))?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .wrap_err_with(|| format!("downloading [{url}]"))?
+    {
+        hasher.update(&chunk);
+        file.write_all(&chunk)
+            .await
+            .wrap_err_with(|| format!("writing [{}]", path.display()))?;
+    }
+    file.flush()
+        .await
+        .wrap_err_with(|| format!("writing [{}]", path.display()))?;
+    Ok(data_encoding::HEXLOWER.encode(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_s3_etag_bytes() {
+        assert_eq!(
+            s3_etag_bytes(b"abc", None),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            s3_etag_bytes(b"abc", Some(2)),
+            "d833159094d1d7ad96ffcc78414e3682-2"
+        );
+    }
+
+    #[test]
+    fn test_sha256_bytes() {
+        assert_eq!(
+            sha256_bytes(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_s3_etag_and_sha256_file() {
+        let path = std::env::temp_dir().join(format!("checksum-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"abc").unwrap();
+        let digests = s3_etag_and_sha256_file(&path, Some(2)).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            digests,
+            (s3_etag_bytes(b"abc", Some(2)), sha256_bytes(b"abc"))
+        );
+    }
+}
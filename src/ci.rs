@@ -0,0 +1,226 @@
+use super::*;
+
+/// CI system the deployer runs in - its env tells the branch and commit even when the checkout is shallow or on a detached HEAD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    GithubActions,
+    Gitlab,
+    Buildkite,
+    CircleCi,
+    AzurePipelines,
+}
+
+impl CiProvider {
+    pub const ALL: &'static [CiProvider] = &[
+        CiProvider::GithubActions,
+        CiProvider::Gitlab,
+        CiProvider::Buildkite,
+        CiProvider::CircleCi,
+        CiProvider::AzurePipelines,
+    ];
+
+    /// env var every job of the provider has set
+    pub fn marker(self) -> &'static str {
+        match self {
+            CiProvider::GithubActions => "GITHUB_ACTIONS",
+            CiProvider::Gitlab => "GITLAB_CI",
+            CiProvider::Buildkite => "BUILDKITE",
+            CiProvider::CircleCi => "CIRCLECI",
+            CiProvider::AzurePipelines => "TF_BUILD",
+        }
+    }
+
+    pub fn detect(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|provider| var(provider.marker()).is_some_and(|value| !value.is_empty()))
+    }
+
+    /// branch being built, the source branch for pull/merge requests - `None` for tag builds
+    pub fn branch(self, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        match self {
+            CiProvider::GithubActions => var("GITHUB_HEAD_REF").or_else(|| {
+                (var("GITHUB_REF_TYPE").as_deref() == Some("branch"))
+                    .then(|| var("GITHUB_REF_NAME"))
+                    .flatten()
+            }),
+            CiProvider::Gitlab => {
+                var("CI_COMMIT_BRANCH").or_else(|| var("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME"))
+            }
+            CiProvider::Buildkite => var("BUILDKITE_BRANCH"),
+            CiProvider::CircleCi => var("CIRCLE_BRANCH"),
+            CiProvider::AzurePipelines => var("SYSTEM_PULLREQUEST_SOURCEBRANCH")
+                .or_else(|| var("BUILD_SOURCEBRANCH"))
+                .and_then(|branch| branch.strip_prefix("refs/heads/").map(ToString::to_string)),
+        }
+    }
+
+    /// full sha of the commit being built - for pull requests the head of the source branch, not the merge commit the
+    /// provider builds, `None` when the provider doesn't tell it
+    pub fn commit(self, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let sha = match self {
+            CiProvider::GithubActions if var("GITHUB_HEAD_REF").is_some() => {
+                var("GITHUB_EVENT_PATH")
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+                    .and_then(|event| pull_request_head_sha(&event))
+            }
+            CiProvider::GithubActions => var("GITHUB_SHA"),
+            CiProvider::Gitlab => {
+                var("CI_MERGE_REQUEST_SOURCE_BRANCH_SHA").or_else(|| var("CI_COMMIT_SHA"))
+            }
+            CiProvider::Buildkite => var("BUILDKITE_COMMIT"),
+            CiProvider::CircleCi => var("CIRCLE_SHA1"),
+            CiProvider::AzurePipelines if var("SYSTEM_PULLREQUEST_PULLREQUESTID").is_some() => {
+                var("SYSTEM_PULLREQUEST_SOURCECOMMITID")
+            }
+            CiProvider::AzurePipelines => var("BUILD_SOURCEVERSION"),
+        };
+        sha.filter(|sha| sha.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+}
+
+/// `.pull_request.head.sha` of a github actions event payload
+fn pull_request_head_sha(event: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(event).ok()?["pull_request"]["head"]["sha"]
+        .as_str()
+        .map(ToString::to_string)
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// branch from the env of the CI provider, when running in one
+pub fn branch() -> Option<String> {
+    let provider = CiProvider::detect(env_var)?;
+    let branch = provider.branch(env_var)?;
+    debug!("{provider:?} :: branch [{branch}]");
+    Some(branch)
+}
+
+/// commit from the env of the CI provider, when running in one
+pub fn commit() -> Option<String> {
+    let provider = CiProvider::detect(env_var)?;
+    let commit = provider.commit(env_var)?;
+    debug!("{provider:?} :: commit [{commit}]");
+    Some(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_providers() {
+        let github_push = env(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF_TYPE", "branch"),
+            ("GITHUB_REF_NAME", "release"),
+            ("GITHUB_HEAD_REF", ""),
+            ("GITHUB_SHA", "0123456789abcdef0123456789abcdef01234567"),
+        ]);
+        let provider = CiProvider::detect(&github_push);
+        assert_eq!(provider, Some(CiProvider::GithubActions));
+        assert_eq!(
+            CiProvider::GithubActions.branch(&github_push).as_deref(),
+            Some("release")
+        );
+        assert_eq!(
+            CiProvider::GithubActions.commit(&github_push).as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        let github_pull_request = env(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF_TYPE", "branch"),
+            ("GITHUB_REF_NAME", "12/merge"),
+            ("GITHUB_HEAD_REF", "feature/login"),
+        ]);
+        assert_eq!(
+            CiProvider::GithubActions
+                .branch(&github_pull_request)
+                .as_deref(),
+            Some("feature/login")
+        );
+        // GITHUB_SHA of a pull request build is the merge commit github made, not a commit of the branch
+        let event_path = std::env::temp_dir().join(format!("event-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &event_path,
+            r#"{"pull_request": {"head": {"sha": "fedcba9876543210fedcba9876543210fedcba98"}}}"#,
+        )
+        .unwrap();
+        let event_path = event_path.to_string_lossy().to_string();
+        let vars = [
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_HEAD_REF", "feature/login"),
+            ("GITHUB_SHA", "0123456789abcdef0123456789abcdef01234567"),
+            ("GITHUB_EVENT_PATH", &event_path),
+        ];
+        let github_pull_request = env(&vars);
+        let commit = CiProvider::GithubActions.commit(&github_pull_request);
+        std::fs::remove_file(&event_path).ok();
+        assert_eq!(
+            commit.as_deref(),
+            Some("fedcba9876543210fedcba9876543210fedcba98")
+        );
+        let github_pull_request_without_event = env(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_HEAD_REF", "feature/login"),
+            ("GITHUB_SHA", "0123456789abcdef0123456789abcdef01234567"),
+        ]);
+        assert_eq!(
+            CiProvider::GithubActions.commit(&github_pull_request_without_event),
+            None
+        );
+        let github_tag = env(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF_TYPE", "tag"),
+            ("GITHUB_REF_NAME", "v1.0.0"),
+        ]);
+        assert_eq!(CiProvider::GithubActions.branch(&github_tag), None);
+        let gitlab = env(&[("GITLAB_CI", "true"), ("CI_COMMIT_BRANCH", "develop")]);
+        assert_eq!(CiProvider::detect(&gitlab), Some(CiProvider::Gitlab));
+        assert_eq!(
+            CiProvider::Gitlab.branch(&gitlab).as_deref(),
+            Some("develop")
+        );
+        let azure = env(&[
+            ("TF_BUILD", "True"),
+            ("BUILD_SOURCEBRANCH", "refs/heads/main"),
+        ]);
+        assert_eq!(CiProvider::detect(&azure), Some(CiProvider::AzurePipelines));
+        assert_eq!(
+            CiProvider::AzurePipelines.branch(&azure).as_deref(),
+            Some("main")
+        );
+        let azure_pull_request = env(&[
+            ("TF_BUILD", "True"),
+            ("SYSTEM_PULLREQUEST_PULLREQUESTID", "17"),
+            (
+                "SYSTEM_PULLREQUEST_SOURCECOMMITID",
+                "fedcba9876543210fedcba9876543210fedcba98",
+            ),
+            (
+                "BUILD_SOURCEVERSION",
+                "0123456789abcdef0123456789abcdef01234567",
+            ),
+        ]);
+        assert_eq!(
+            CiProvider::AzurePipelines
+                .commit(&azure_pull_request)
+                .as_deref(),
+            Some("fedcba9876543210fedcba9876543210fedcba98")
+        );
+        assert_eq!(CiProvider::detect(env(&[])), None);
+    }
+}
@@ -0,0 +1,58 @@
+/// content types worth compressing, everything else (images, fonts, archives) is compressed already
+const COMPRESSIBLE_CONTENT_TYPES: &[&str] = &[
+    "application/json",
+    "application/xml",
+    "application/manifest+json",
+    "application/wasm",
+    "image/svg+xml",
+];
+
+pub fn is_compressible(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("text/") || COMPRESSIBLE_CONTENT_TYPES.contains(&essence)
+}
+
+/// crc-32 (ieee) of the content, the gzip trailer carries it
+fn crc32(content: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in content {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// gzip member of the content - no file name and no mtime, so the same content always compresses to the same bytes (and etag)
+pub fn gzip(content: &[u8]) -> Vec<u8> {
+    const HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let mut gzipped = HEADER.to_vec();
+    gzipped.extend(miniz_oxide::deflate::compress_to_vec(content, 9));
+    gzipped.extend(crc32(content).to_le_bytes());
+    gzipped.extend((content.len() as u32).to_le_bytes());
+    gzipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn test_gzip() -> Result<()> {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let content = "console.log('hello');\n".repeat(100);
+        let gzipped = gzip(content.as_bytes());
+        assert_eq!(gzipped, gzip(content.as_bytes()));
+        assert!(gzipped.len() < content.len());
+        let deflated = &gzipped[10..gzipped.len() - 8];
+        let inflated =
+            miniz_oxide::inflate::decompress_to_vec(deflated).map_err(|e| eyre::eyre!("{e:?}"))?;
+        assert_eq!(inflated, content.as_bytes());
+        assert!(is_compressible("text/javascript; charset=utf-8"));
+        assert!(is_compressible("image/svg+xml"));
+        assert!(!is_compressible("image/png"));
+        Ok(())
+    }
+}
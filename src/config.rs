@@ -0,0 +1,815 @@
+use super::*;
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+
+/// unknown variables this close to a known name are reported as probable typos
+const MAX_TYPO_DISTANCE: usize = 3;
+
+/// prefixes of the variables the deployer owns, only unknown names with these prefixes are reported - everything else
+/// in the environment belongs to the CI, the cloud SDKs or the shell
+const OWNED_PREFIXES: &[&str] = &["S3_", "DEPLOY_"];
+
+/// subcommands the credentials of this environment are meant for, unset allows all of them
+pub const ALLOWED_COMMANDS_ENV: &str = "DEPLOY_ALLOWED_COMMANDS";
+
+/// a command that only prints what it reads, allowed regardless of the profile so it can be inspected
+const ALWAYS_ALLOWED_COMMAND: &str = "env-help";
+
+/// an environment variable the deployer reads
+#[derive(Debug, Clone, Copy)]
+pub struct EnvVar {
+    pub name: &'static str,
+    pub required: bool,
+    pub description: &'static str,
+    /// value is never printed
+    pub secret: bool,
+    /// set by the CI, where an empty value means it doesn't apply (e.g. GITHUB_HEAD_REF outside of pull requests)
+    pub set_by_ci: bool,
+    /// checks the value when it's set
+    pub check: Option<fn(&str) -> Result<()>>,
+}
+
+pub const ENV_VARS: &[EnvVar] = &[
+    EnvVar {
+        name: "S3_ACCESS_KEY",
+        required: true,
+        description: "access key of the bucket",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "S3_SECRET_KEY",
+        required: true,
+        description: "secret key of the bucket",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "S3_BUCKET",
+        required: true,
+        description: "bucket name, usually the name of the app",
+        secret: false,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "S3_REGION",
+        required: true,
+        description: "region of the bucket, e.g. fra1",
+        secret: false,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: storage::FALLBACK_ENDPOINTS_ENV,
+        required: false,
+        description: "comma separated endpoint hosts serving the same bucket, tried in order when the primary one can't be reached",
+        secret: false,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: storage::METADATA_ENV,
+        required: false,
+        description: "comma separated KEY=VALUE pairs every uploaded object gets as `x-amz-meta-KEY` headers, e.g. the CI run id",
+        secret: false,
+        set_by_ci: false,
+        check: Some(|val| storage::metadata_headers(val.split(',').filter(|entry| !entry.trim().is_empty())).map(|_| ())),
+    },
+    EnvVar {
+        name: cdn::CLOUDFRONT_DISTRIBUTION_ENV,
+        required: false,
+        description: "cloudfront distribution in front of the bucket, invalidated after publishing",
+        secret: false,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: notify::WEBHOOK_SECRET_ENV,
+        required: false,
+        description: "key the --webhook payloads are signed with (x-deployer-signature-256)",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: notify::TEAMS_WEBHOOK_ENV,
+        required: false,
+        description: "teams incoming webhook the published releases are announced in",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: notify::TELEGRAM_TOKEN_ENV,
+        required: false,
+        description: "telegram bot announcing published and failed releases",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: notify::TELEGRAM_CHAT_ENV,
+        required: false,
+        description: "telegram chat the bot posts to, --telegram-chat-id takes precedence",
+        secret: false,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: github::TOKEN_ENV,
+        required: false,
+        description: "github api token for --github-deployment, --mirror github-release and --pr-notes",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: github::PACKAGES_TOKEN_ENV,
+        required: false,
+        description: "github token pushing to the package repository fork and opening the pull request of `package-manifests --pull-request`, GITHUB_TOKEN is used when unset",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: packaging::CHOCOLATEY_API_KEY_ENV,
+        required: false,
+        description: "api key of the feed `package-manifests chocolatey --push` pushes to",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: github::REPOSITORY_ENV,
+        required: false,
+        description: "owner/repo the github deployments are created in, set by github actions",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_API_URL",
+        required: false,
+        description: "github api the deployments and releases go to, api.github.com when unset - set by github actions (enterprise too)",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_SERVER_URL",
+        required: false,
+        description: "github the workflow run linked from the deployment status lives on, set by github actions",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_RUN_ID",
+        required: false,
+        description: "workflow run linked from the deployment status, set by github actions",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: github::OUTPUT_ENV,
+        required: false,
+        description: "file the step outputs (version, manifest urls) are appended to, set by github actions",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: github::STEP_SUMMARY_ENV,
+        required: false,
+        description: "file the deploy summary is appended to, set by github actions",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: cdn::CLOUDFLARE_ZONE_ENV,
+        required: false,
+        description: "cloudflare zone the bucket is served through, for --purge cloudflare",
+        secret: false,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: cdn::CLOUDFLARE_TOKEN_ENV,
+        required: false,
+        description: "cloudflare api token with the cache purge permission, for --purge cloudflare",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "AWS_ACCESS_KEY_ID",
+        required: false,
+        description: "access key for the cdn apis, S3_ACCESS_KEY is used when unset",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "AWS_SECRET_ACCESS_KEY",
+        required: false,
+        description: "secret key for the cdn apis, S3_SECRET_KEY is used when unset",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "AWS_SESSION_TOKEN",
+        required: false,
+        description: "session token of temporary aws credentials (an assumed role, sso), sent along with AWS_ACCESS_KEY_ID",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: receipt::SIGNING_KEY_ENV,
+        required: false,
+        description: "base64 ed25519 seed signing deploy receipts and sign-offs",
+        secret: true,
+        set_by_ci: false,
+        check: Some(|val| receipt::decode_key(val).map(|_| ())),
+    },
+    EnvVar {
+        name: receipt::PUBLIC_KEY_ENV,
+        required: false,
+        description: "base64 ed25519 public key checking receipts and sign-offs",
+        secret: false,
+        set_by_ci: false,
+        check: Some(|val| receipt::decode_key(val).map(|_| ())),
+    },
+    EnvVar {
+        name: minisign::MANIFEST_SIGNING_KEY_ENV,
+        required: false,
+        description: "base64 ed25519 seed signing the updater manifests, the signature is uploaded next to them as `.sig`",
+        secret: true,
+        set_by_ci: false,
+        check: Some(|val| receipt::decode_key(val).map(|_| ())),
+    },
+    EnvVar {
+        name: appcast::SPARKLE_KEY_ENV,
+        required: false,
+        description: "base64 ed25519 seed (sparkle's `generate_keys -x`) signing the `--appcast` enclosure",
+        secret: true,
+        set_by_ci: false,
+        check: Some(|val| receipt::decode_key(val).map(|_| ())),
+    },
+    EnvVar {
+        name: minisign::MANIFEST_PUBLIC_KEY_ENV,
+        required: false,
+        description: "minisign public key (`RW...`) `verify` checks the manifest signature against",
+        secret: false,
+        set_by_ci: false,
+        check: Some(|val| {
+            minisign_verify::PublicKey::from_base64(val.trim())
+                .map(|_| ())
+                .map_err(|e| eyre::eyre!("{e}"))
+        }),
+    },
+    EnvVar {
+        name: ALLOWED_COMMANDS_ENV,
+        required: false,
+        description: "comma separated subcommands this environment may run (e.g. `upload,patch` for the CI deploy key), any other one is refused",
+        secret: false,
+        set_by_ci: false,
+        check: Some(|val| allowed_commands(val).map(|_| ())),
+    },
+    EnvVar {
+        name: "TAURI_PRIVATE_KEY",
+        required: false,
+        description: "tauri 1 updater private key, an unsigned updater bundle gets signed with it by the tauri signer",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "TAURI_KEY_PASSWORD",
+        required: false,
+        description: "password of TAURI_PRIVATE_KEY",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "TAURI_SIGNING_PRIVATE_KEY",
+        required: false,
+        description: "tauri 2 updater private key, an unsigned updater bundle gets signed with it by the tauri signer",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "TAURI_SIGNING_PRIVATE_KEY_PASSWORD",
+        required: false,
+        description: "password of TAURI_SIGNING_PRIVATE_KEY",
+        secret: true,
+        set_by_ci: false,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_ACTIONS",
+        required: false,
+        description: "marks a github actions job, the branch and commit are then read from its env",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_HEAD_REF",
+        required: false,
+        description: "github actions: source branch of a pull request build",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_REF_TYPE",
+        required: false,
+        description: "github actions: `branch` or `tag`, only branch builds take GITHUB_REF_NAME as the branch",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_REF_NAME",
+        required: false,
+        description: "github actions: branch (or tag) being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_SHA",
+        required: false,
+        description: "github actions: commit being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITHUB_EVENT_PATH",
+        required: false,
+        description: "github actions: event payload, the commit of a pull request build is its `pull_request.head.sha`",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "GITLAB_CI",
+        required: false,
+        description: "marks a gitlab ci job, the branch and commit are then read from its env",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CI_COMMIT_BRANCH",
+        required: false,
+        description: "gitlab ci: branch being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CI_MERGE_REQUEST_SOURCE_BRANCH_NAME",
+        required: false,
+        description: "gitlab ci: source branch of a merge request pipeline",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CI_MERGE_REQUEST_SOURCE_BRANCH_SHA",
+        required: false,
+        description: "gitlab ci: head of the source branch of a merge request pipeline",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CI_COMMIT_SHA",
+        required: false,
+        description: "gitlab ci: commit being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "BUILDKITE",
+        required: false,
+        description: "marks a buildkite job, the branch and commit are then read from its env",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "BUILDKITE_BRANCH",
+        required: false,
+        description: "buildkite: branch being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "BUILDKITE_COMMIT",
+        required: false,
+        description: "buildkite: commit being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CIRCLECI",
+        required: false,
+        description: "marks a circleci job, the branch and commit are then read from its env",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CIRCLE_BRANCH",
+        required: false,
+        description: "circleci: branch being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "CIRCLE_SHA1",
+        required: false,
+        description: "circleci: commit being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "TF_BUILD",
+        required: false,
+        description: "marks an azure pipelines job, the branch and commit are then read from its env",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "SYSTEM_PULLREQUEST_PULLREQUESTID",
+        required: false,
+        description: "azure pipelines: set for pull request builds",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "SYSTEM_PULLREQUEST_SOURCEBRANCH",
+        required: false,
+        description: "azure pipelines: source branch of a pull request build",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "SYSTEM_PULLREQUEST_SOURCECOMMITID",
+        required: false,
+        description: "azure pipelines: head of the source branch of a pull request build",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "BUILD_SOURCEBRANCH",
+        required: false,
+        description: "azure pipelines: ref being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+    EnvVar {
+        name: "BUILD_SOURCEVERSION",
+        required: false,
+        description: "azure pipelines: commit being built",
+        secret: false,
+        set_by_ci: true,
+        check: None,
+    },
+];
+
+fn closest_known(name: &str) -> Option<&'static str> {
+    ENV_VARS
+        .iter()
+        .map(|var| (strsim::levenshtein(name, var.name), var.name))
+        .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// everything wrong with the given environment, not just the first problem
+#[derive(Debug, Default, PartialEq)]
+struct Problems {
+    /// missing or invalid values of known variables, the deployer refuses to run with these
+    errors: Vec<String>,
+    /// unknown variables with an owned prefix, probably typos but harmless on their own
+    warnings: Vec<String>,
+}
+
+fn problems(env: &BTreeMap<String, String>) -> Problems {
+    let unknown = env
+        .keys()
+        .filter(|name| OWNED_PREFIXES.iter().any(|prefix| name.starts_with(prefix)))
+        .filter(|name| ENV_VARS.iter().all(|var| var.name != name.as_str()))
+        .map(|name| (name.as_str(), closest_known(name)))
+        .collect_vec();
+    let mut problems = Problems::default();
+    for var in ENV_VARS {
+        match env.get(var.name) {
+            Some(val) if val.trim().is_empty() && !var.set_by_ci => problems.errors.push(format!(
+                "{} is set but empty - {}",
+                var.name, var.description
+            )),
+            Some(val) => {
+                if let Some(Err(e)) = var.check.map(|check| check(val)) {
+                    problems
+                        .errors
+                        .push(format!("{} is invalid: {e:#}", var.name));
+                }
+            }
+            None if var.required => {
+                let hint = unknown
+                    .iter()
+                    .find(|(_, suggestion)| *suggestion == Some(var.name))
+                    .map(|(name, _)| format!(" ({name} is set - a typo?)"))
+                    .unwrap_or_default();
+                problems.errors.push(format!(
+                    "{} is not set - {}{hint}",
+                    var.name, var.description
+                ));
+            }
+            None => {}
+        }
+    }
+    for (name, suggestion) in unknown {
+        problems.warnings.push(match suggestion {
+            Some(known) if env.contains_key(known) => {
+                format!("{name} is not used by the deployer, {known} is set already - remove it")
+            }
+            Some(known) => format!("{name} is not used by the deployer - did you mean {known}?"),
+            None => format!("{name} is not used by the deployer"),
+        });
+    }
+    problems
+}
+
+/// checks the environment (including `.env`) before anything talks to the bucket, reporting all problems at once
+pub fn validate_env() -> Result<()> {
+    let env = std::env::vars_os()
+        .filter_map(|(name, val)| Some((name.into_string().ok()?, val.into_string().ok()?)))
+        .collect();
+    let Problems { errors, warnings } = problems(&env);
+    for warning in &warnings {
+        warn!("config :: {warning}");
+    }
+    if errors.is_empty() {
+        return Ok(());
+    }
+    for error in &errors {
+        error!("config :: {error}");
+    }
+    bail!(
+        "{} configuration problem(s):\n - {}",
+        errors.len(),
+        errors.join("\n - ")
+    )
+}
+
+fn command_names() -> Vec<String> {
+    use clap::CommandFactory;
+    crate::Args::command()
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .collect()
+}
+
+fn allowed_commands(val: &str) -> Result<Vec<String>> {
+    let known = command_names();
+    let allowed = val
+        .split(',')
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+        .map(str::to_string)
+        .collect_vec();
+    let unknown = allowed
+        .iter()
+        .filter(|command| !known.contains(command))
+        .collect_vec();
+    if !unknown.is_empty() {
+        bail!(
+            "unknown command(s) {}, known ones: {}",
+            unknown.iter().join(", "),
+            known.join(", ")
+        );
+    }
+    Ok(allowed)
+}
+
+/// a guard rail against running a destructive command in the wrong pipeline, enforced when DEPLOY_ALLOWED_COMMANDS is set
+pub fn ensure_command_allowed(command: &str) -> Result<()> {
+    let Ok(val) = std::env::var(ALLOWED_COMMANDS_ENV) else {
+        return Ok(());
+    };
+    let allowed =
+        allowed_commands(&val).wrap_err_with(|| format!("{ALLOWED_COMMANDS_ENV} is invalid"))?;
+    if command != ALWAYS_ALLOWED_COMMAND && !allowed.iter().any(|allowed| allowed == command) {
+        bail!(
+            "`{command}` is not allowed with these credentials, {ALLOWED_COMMANDS_ENV} permits only: {}",
+            allowed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// where the value of a variable the deployer reads comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// set in the environment of the process, `.env` never overrides it
+    Env,
+    /// loaded from `.env`
+    DotEnv,
+    Unset,
+}
+
+impl Source {
+    fn of(is_set: bool, in_process_env: bool) -> Self {
+        match (is_set, in_process_env) {
+            (false, _) => Self::Unset,
+            (true, true) => Self::Env,
+            (true, false) => Self::DotEnv,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Env => "set (env)",
+            Self::DotEnv => "set (.env)",
+            Self::Unset => "not set",
+        }
+    }
+}
+
+/// names in the environment of the process, taken before `.env` is loaded
+pub fn process_env_names() -> BTreeSet<String> {
+    std::env::vars_os()
+        .map(|(name, _)| name.to_string_lossy().to_string())
+        .collect()
+}
+
+/// prints every variable the deployer reads, whether it's set and where the value comes from
+pub fn env_help(process_env: &BTreeSet<String>) -> Result<()> {
+    for var in ENV_VARS {
+        let current = std::env::var(var.name).ok();
+        let source = Source::of(current.is_some(), process_env.contains(var.name));
+        let value = match (&current, var.secret) {
+            (None, _) => String::new(),
+            (Some(_), true) => " = ***".to_string(),
+            (Some(value), false) => format!(" = {value}"),
+        };
+        println!(
+            "{} [{}] {}{value}\n    {}",
+            var.name,
+            if var.required { "required" } else { "optional" },
+            source.describe(),
+            var.description
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_problems_are_reported_with_suggestions() {
+        let env: BTreeMap<String, String> = [
+            ("S3_ACESS_KEY", "key"),
+            ("S3_SECRET_KEY", "secret"),
+            ("S3_BUCKET", " "),
+            ("S3_REGION", "fra1"),
+            ("S3_FALLBACK_ENDPOINT", "ams3.digitaloceanspaces.com"),
+            (receipt::PUBLIC_KEY_ENV, "AAAA"),
+            ("PATH", "/usr/bin"),
+        ]
+        .into_iter()
+        .map(|(name, val)| (name.to_string(), val.to_string()))
+        .collect();
+        let problems = problems(&env);
+        assert_eq!(
+            problems.errors,
+            vec![
+                "S3_ACCESS_KEY is not set - access key of the bucket (S3_ACESS_KEY is set - a typo?)",
+                "S3_BUCKET is set but empty - bucket name, usually the name of the app",
+                "DEPLOY_RECEIPT_PUBLIC_KEY is invalid: key must be 32 bytes long, got 3",
+            ]
+        );
+        assert_eq!(
+            problems.warnings,
+            vec![
+                "S3_ACESS_KEY is not used by the deployer - did you mean S3_ACCESS_KEY?",
+                "S3_FALLBACK_ENDPOINT is not used by the deployer - did you mean S3_FALLBACK_ENDPOINTS?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_foreign_variables_are_not_problems() {
+        let env: BTreeMap<String, String> = [
+            ("S3_ACCESS_KEY", "key"),
+            ("S3_SECRET_KEY", "secret"),
+            ("S3_BUCKET", "app"),
+            ("S3_REGION", "fra1"),
+            ("GITHUB_REPOSITORY_ID", "123456"),
+            ("AWS_REGION", "eu-central-1"),
+        ]
+        .into_iter()
+        .map(|(name, val)| (name.to_string(), val.to_string()))
+        .collect();
+        assert_eq!(problems(&env), Problems::default());
+    }
+
+    #[test]
+    fn test_empty_ci_variables_are_not_problems() {
+        // a github actions push build, GITHUB_HEAD_REF is only filled in for pull requests
+        let env: BTreeMap<String, String> = [
+            ("S3_ACCESS_KEY", "key"),
+            ("S3_SECRET_KEY", "secret"),
+            ("S3_BUCKET", "app"),
+            ("S3_REGION", "fra1"),
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF_TYPE", "branch"),
+            ("GITHUB_REF_NAME", "release"),
+            ("GITHUB_HEAD_REF", ""),
+            ("GITHUB_SHA", "0123456789abcdef0123456789abcdef01234567"),
+        ]
+        .into_iter()
+        .map(|(name, val)| (name.to_string(), val.to_string()))
+        .collect();
+        assert_eq!(problems(&env), Problems::default());
+    }
+
+    #[test]
+    fn test_allowed_commands_must_exist() {
+        assert_eq!(
+            allowed_commands(" upload, patch ,").unwrap(),
+            vec!["upload".to_string(), "patch".to_string()]
+        );
+        assert!(allowed_commands("upload,delete").is_err());
+    }
+
+    #[test]
+    fn test_source_of_value() {
+        assert_eq!(Source::of(true, true), Source::Env);
+        assert_eq!(Source::of(true, false), Source::DotEnv);
+        assert_eq!(Source::of(false, true), Source::Unset);
+    }
+
+    /// names the code reads literally - `var("NAME")`, `var_os("NAME")`, `*_ENV` and `*_ENVS` constants - outside of tests
+    fn env_vars_read_in(source: &str) -> Vec<String> {
+        let source = source.split("#[cfg(test)]").next().unwrap_or_default();
+        let literal = |rest: &str| rest.split('"').next().unwrap_or_default().to_string();
+        let mut names = vec![];
+        for pattern in ["var(\"", "var_os(\"", "_ENV: &str = \""] {
+            names.extend(source.split(pattern).skip(1).map(literal));
+        }
+        for list in source.split("_ENVS: &").skip(1) {
+            let list = list.split("];").next().unwrap_or_default();
+            names.extend(list.split('"').skip(1).step_by(2).map(ToString::to_string));
+        }
+        names
+    }
+
+    #[test]
+    fn test_every_variable_read_is_known() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut read = ci::CiProvider::ALL
+            .iter()
+            .map(|provider| provider.marker().to_string())
+            .collect::<BTreeSet<_>>();
+        for entry in std::fs::read_dir(src).unwrap() {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            read.extend(env_vars_read_in(&source));
+        }
+        assert!(read.contains("GITHUB_RUN_ID"), "{read:?}");
+        let unknown = read
+            .iter()
+            .filter(|name| !ENV_VARS.iter().any(|var| var.name == name.as_str()))
+            .collect::<Vec<_>>();
+        assert!(unknown.is_empty(), "missing from ENV_VARS: {unknown:?}");
+    }
+}
@@ -0,0 +1,1673 @@
+use super::*;
+use crate::release_notes_file::{
+    Contents,
+    FileChecksum,
+    ManifestFormat,
+    ReleaseNotes,
+    StagedArtifact,
+    StagedRelease,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ArtifactsArgs {
+    #[clap(short, long, value_name = "DIR")]
+    pub release_dir: Option<PathBuf>,
+    /// this stage also cleans up release artifacts after uploading them - by default rust-cache action saves them all which makes the cache grow out of control
+    #[clap(short, long)]
+    pub cleanup: bool,
+    /// don't upload the `.deb` / `.rpm` packages from the bundle dir
+    #[clap(long)]
+    pub skip_packages: bool,
+    /// on windows tauri may build both an msi and an nsis (`-setup.exe`) installer, this one feeds the updater
+    #[clap(long, default_value = "msi", value_name = "msi|nsis")]
+    pub installer: artifacts::InstallerKind,
+    /// only upload files matching one of these globs (relative to the release dir, e.g. `**/*.msi.zip*`), can be repeated
+    #[clap(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// never upload files matching one of these globs (e.g. `*.pdb`), wins over --include, can be repeated
+    #[clap(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// fail when the updater bundle can't be verified - missing or empty `.sig`, no updater pubkey in tauri.conf.json. defaults to `true` on release branches (`main`, `master`, `release`, `release/*`)
+    #[clap(long, value_name = "true|false")]
+    pub strict_signatures: Option<bool>,
+    /// tauri cli used to sign an updater bundle built without a `.sig` when a tauri private key is in the environment
+    #[clap(long, default_value = "npx tauri", value_name = "COMMAND")]
+    pub tauri_cli: String,
+    #[clap(flatten)]
+    pub multipart: storage::MultipartArgs,
+    /// what to do with symlinks in the release dir - `follow` fails on loops
+    #[clap(long, default_value = "skip", value_name = "skip|follow|fail")]
+    pub symlinks: SymlinkPolicy,
+    /// serve installers with `Content-Disposition: attachment` and their file name, so browsers save them under it rather than the key leaf
+    #[clap(long)]
+    pub content_disposition: bool,
+    /// how many files are uploaded at the same time
+    #[clap(long, default_value_t = 4, value_name = "N")]
+    pub concurrency: usize,
+    /// upload a `.zsync` control file next to every AppImage for delta updates with AppImageUpdate
+    #[clap(long)]
+    pub zsync: bool,
+    /// zsync tool used for `--zsync`
+    #[clap(long, default_value = "zsyncmake", value_name = "COMMAND")]
+    pub zsyncmake: String,
+    #[clap(flatten)]
+    pub symbols: symbols::SymbolsArgs,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, IntoEnumIterator)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    Skip,
+    Follow,
+    Fail,
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        matched_variant!(Self, s)
+    }
+}
+
+impl ArtifactsArgs {
+    /// whether the file (relative to the release dir) passes `--include` / `--exclude`
+    fn path_filter(&self) -> Result<impl Fn(&Path) -> bool> {
+        let glob_set = |patterns: &[String]| {
+            patterns
+                .iter()
+                .try_fold(globset::GlobSetBuilder::new(), |mut builder, pattern| {
+                    builder.add(
+                        globset::Glob::new(pattern)
+                            .wrap_err_with(|| format!("bad glob [{pattern}]"))?,
+                    );
+                    Ok::<_, eyre::Report>(builder)
+                })?
+                .build()
+                .wrap_err("building glob set")
+        };
+        let include = glob_set(&self.include)?;
+        let exclude = glob_set(&self.exclude)?;
+        let include_all = self.include.is_empty();
+        Ok(move |path: &Path| (include_all || include.is_match(path)) && !exclude.is_match(path))
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PublishArgs {
+    #[clap(flatten)]
+    pub queue: QueueArgs,
+    #[clap(flatten)]
+    pub lock: lock::LockArgs,
+    #[clap(flatten)]
+    pub notes: notes::NotesArgs,
+    #[clap(flatten)]
+    pub report: report::ReportArgs,
+    #[clap(flatten)]
+    pub links: links::LinkCheckArgs,
+    #[clap(flatten)]
+    pub cdn: cdn::CdnArgs,
+    #[clap(flatten)]
+    pub notify: notify::NotifyArgs,
+    #[clap(flatten)]
+    pub github: github::GithubArgs,
+    #[clap(flatten)]
+    pub tag: tag::TagArgs,
+    #[clap(flatten)]
+    pub policy: policy::PolicyArgs,
+    /// copy the manifests to `latest/{target}/...` as well, a url that always serves the newest release - give it on the publishes of one channel only
+    #[clap(long)]
+    pub latest_alias: bool,
+    /// publish a sparkle `appcast.xml` next to the manifest, for a non-tauri macos build fed from the same release
+    #[clap(long)]
+    pub appcast: bool,
+    /// add the release (version, date and notes) to an appstream `metainfo.xml` next to the linux manifest, for flatpak packaging
+    #[clap(long)]
+    pub metainfo: bool,
+    /// upload a gzipped `.gz` copy (`Content-Encoding: gzip`) next to each manifest, for a cdn or edge function picking it by `Accept-Encoding`
+    #[clap(long)]
+    pub gzip_manifests: bool,
+    #[clap(flatten)]
+    pub scoop: packaging::ScoopArgs,
+    /// refuse to publish unless the version was signed off by this role (`sign-off --role`), can be repeated
+    #[clap(long, value_name = "ROLE")]
+    pub require_sign_off: Vec<String>,
+}
+
+/// exit code used when the deploy is aborted by `--deadline`, same as coreutils `timeout`
+pub const DEADLINE_EXCEEDED_EXIT_CODE: i32 = 124;
+
+/// how many times the shared release file merge is redone when another deploy changed it in the meantime
+const MANIFEST_MERGE_ATTEMPTS: usize = 5;
+
+/// branches (and the stable channel) whose updates reach customers, signatures are checked strictly on them by default
+fn is_release_branch(branch: &str) -> bool {
+    matches!(branch, "main" | "master" | "release" | "stable") || branch.starts_with("release/")
+}
+
+/// the bundle the updater downloads for the target and its `.sig` (if any) - newest file name wins when several versions are lying around in the bundle dir
+fn updater_bundle(
+    classifier: &artifacts::Classifier,
+    target: &RustTarget,
+    installer: artifacts::InstallerKind,
+    files: &[PathBuf],
+) -> Result<(PathBuf, Option<PathBuf>)> {
+    let suffixes = classifier.updater_suffixes(target, installer);
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let bundle = files
+        .iter()
+        .filter(|path| {
+            classifier.kind(target, path) == Some(artifacts::ArtifactKind::UpdaterArchive)
+                && suffixes.iter().any(|suffix| file_name(path).ends_with(suffix))
+        })
+        .max_by_key(|path| file_name(path))
+        .ok_or_else(|| {
+            eyre::eyre!(
+                "no updater bundle ({}) found for {target:?} - is the updater enabled in tauri.conf.json?",
+                suffixes.iter().map(|suffix| format!("*{suffix}")).join(", ")
+            )
+        })?;
+    let signature_name = format!("{}.sig", file_name(bundle));
+    let signature = files.iter().find(|path| file_name(path) == signature_name);
+    Ok((bundle.clone(), signature.cloned()))
+}
+
+/// objects a deploy has put in the bucket so far, removed when the deploy is aborted
+#[derive(Debug, Default)]
+pub struct StagedObjects {
+    inner: std::sync::Mutex<StagedObjectsInner>,
+}
+
+#[derive(Debug, Default)]
+struct StagedObjectsInner {
+    /// binaries and staged release files, useless once the deploy is aborted
+    artifacts: Vec<String>,
+    /// queue tickets and the like, always released on abort
+    leases: Vec<String>,
+    /// release file locks held right now, released on abort unless another job took them over
+    locks: Vec<lock::ReleaseFileLock>,
+    /// once the manifest is being written the artifacts may already be referenced by it
+    published: bool,
+}
+
+impl StagedObjects {
+    fn lock(&self) -> std::sync::MutexGuard<'_, StagedObjectsInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// an artifact about to be uploaded, removed on abort only when the bucket didn't hold it before - a rerun of a
+    /// published version uploads to the very keys the live manifest and downloads page point to
+    pub fn artifact(&self, key: &str, existing: bool) {
+        if !existing {
+            self.lock().artifacts.push(key.to_string());
+        }
+    }
+
+    pub fn lease(&self, key: &str) {
+        self.lock().leases.push(key.to_string());
+    }
+
+    pub fn hold(&self, lock: &lock::ReleaseFileLock) {
+        self.lock().locks.push(lock.clone());
+    }
+
+    pub fn unhold(&self, key: &str) {
+        self.lock().locks.retain(|lock| lock.key() != key);
+    }
+
+    pub fn mark_published(&self) {
+        self.lock().published = true;
+    }
+
+    /// what an abort deletes - the artifacts only as long as no release file may reference them
+    fn keys_to_remove(&self) -> Vec<String> {
+        let inner = self.lock();
+        match inner.published {
+            true => inner.leases.clone(),
+            false => inner
+                .artifacts
+                .iter()
+                .chain(&inner.leases)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    async fn remove_all(&self, s3_config: &S3Config) {
+        let (locks, published) = {
+            let inner = self.lock();
+            (inner.locks.clone(), inner.published)
+        };
+        for lock in locks {
+            match lock.delete(s3_config).await {
+                Ok(true) => info!("released [{}]", lock.key()),
+                Ok(false) => warn!(
+                    "[{}] was taken over by another job, leaving it in place",
+                    lock.key()
+                ),
+                Err(e) => error!("failed to release [{}] :: {e:?}", lock.key()),
+            }
+        }
+        if published {
+            warn!("release file may already reference the artifacts, leaving them in place");
+        }
+        for key in self.keys_to_remove() {
+            match storage::delete(s3_config, &key).await {
+                Ok(()) => info!("removed [{key}]"),
+                Err(e) => error!("failed to remove [{key}] :: {e:?}"),
+            }
+        }
+    }
+}
+
+/// [`Deployment::with_deadline`] of a run processing several targets, what every one of them staged is cleaned up
+pub async fn with_targets_deadline<T, F: std::future::Future<Output = Result<T>>>(
+    deployments: &[Deployment<'_>],
+    deadline: Option<std::time::Duration>,
+    deploy: F,
+) -> Result<T> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return deploy.await,
+    };
+    match tokio::time::timeout(deadline, deploy).await {
+        Ok(deployed) => deployed,
+        Err(_) => {
+            error!(
+                "DEADLINE EXCEEDED! deploy did not finish within {}, aborting",
+                humantime::format_duration(deadline)
+            );
+            for deployment in deployments {
+                deployment
+                    .staged_objects
+                    .remove_all(deployment.s3_config)
+                    .await;
+            }
+            std::process::exit(DEADLINE_EXCEEDED_EXIT_CODE)
+        }
+    }
+}
+
+/// stages the release of every target (or with no `artifacts` fetches the staged ones), then publishes them one after another into the shared manifest
+///
+/// the live manifest is only touched once the binaries of all targets have landed
+pub async fn deploy_targets(
+    deployments: &[&Deployment<'_>],
+    metadata: &[(String, String)],
+    artifacts: Option<&ArtifactsArgs>,
+    publish: Option<&PublishArgs>,
+) -> Result<()> {
+    let mut staged = Vec::with_capacity(deployments.len());
+    for deployment in deployments {
+        info!("target :: {:?}", deployment.target);
+        deployment.set_default_headers(metadata);
+        staged.push(match artifacts {
+            Some(artifacts) => deployment.upload_artifacts(artifacts).await?,
+            None => deployment.fetch_staged().await?,
+        });
+    }
+    if let Some(publish) = publish {
+        for (deployment, staged) in deployments.iter().zip(&staged) {
+            info!("publishing :: {:?}", deployment.target);
+            deployment.set_default_headers(metadata);
+            deployment.publish(staged, publish).await?;
+        }
+    }
+    Ok(())
+}
+
+/// everything a single deployment of a single target needs to know
+pub struct Deployment<'a> {
+    pub tauri_conf_json: &'a TauriConfJson,
+    pub target: &'a RustTarget,
+    pub branch: &'a str,
+    pub git_hash: &'a str,
+    pub s3_config: &'a S3Config,
+    pub fallback_endpoints: &'a [String],
+    pub merge_manifest: bool,
+    pub manifest_format: ManifestFormat,
+    pub train: Option<&'a str>,
+    /// `--force`, lets publish go back to an older version and uploads overwrite binaries
+    pub force: bool,
+    pub classifier: artifacts::Classifier,
+    pub staged_objects: StagedObjects,
+}
+
+impl<'a> Deployment<'a> {
+    /// the same deployment for another target of the branch
+    pub fn for_target(&self, target: &'a RustTarget) -> Self {
+        Deployment {
+            target,
+            classifier: self.classifier.clone(),
+            staged_objects: Default::default(),
+            ..*self
+        }
+    }
+}
+
+impl Deployment<'_> {
+    fn version(&self) -> &str {
+        self.tauri_conf_json.version()
+    }
+
+    /// aborts the deploy once the deadline passes, cleaning up whatever it managed to stage and exiting with [`DEADLINE_EXCEEDED_EXIT_CODE`]
+    pub async fn with_deadline<T, F: std::future::Future<Output = Result<T>>>(
+        &self,
+        deadline: Option<std::time::Duration>,
+        deploy: F,
+    ) -> Result<T> {
+        with_targets_deadline(std::slice::from_ref(self), deadline, deploy).await
+    }
+
+    /// tags every object uploaded from now on with the branch, version, target and commit of this deployment, on top of the `metadata` headers
+    /// registers the key for removal on abort, unless the bucket holds it already
+    pub async fn stage_artifact(&self, key: &str) -> Result<()> {
+        let existing = storage::head(self.s3_config, key)
+            .await
+            .wrap_err_with(|| format!("checking whether [{key}] exists"))?
+            .is_some();
+        self.staged_objects.artifact(key, existing);
+        Ok(())
+    }
+
+    pub fn set_default_headers(&self, metadata: &[(String, String)]) {
+        storage::set_default_headers(
+            [(
+                "x-amz-tagging".to_string(),
+                storage::tagging(&[
+                    ("branch", self.branch),
+                    ("version", self.tauri_conf_json.version()),
+                    (
+                        "target",
+                        serde_variant::to_variant_name(self.target)
+                            .expect("this will always serialize"),
+                    ),
+                    ("commit", self.git_hash),
+                ]),
+            )]
+            .into_iter()
+            .chain(metadata.iter().cloned())
+            .collect(),
+        );
+    }
+
+    /// formats whose manifest gets written, the one the updater is pointed at goes last
+    fn manifest_formats(&self) -> Vec<ManifestFormat> {
+        match self.endpoint_format() {
+            ManifestFormat::V2 if self.manifest_format == ManifestFormat::Both => {
+                vec![ManifestFormat::V1, ManifestFormat::V2]
+            }
+            ManifestFormat::V1 if self.manifest_format == ManifestFormat::Both => {
+                vec![ManifestFormat::V2, ManifestFormat::V1]
+            }
+            format => vec![format],
+        }
+    }
+
+    /// with both formats tauri 2 apps are pointed at latest.json, older ones at release-notes.json
+    fn endpoint_format(&self) -> ManifestFormat {
+        match (self.manifest_format, self.tauri_conf_json) {
+            (ManifestFormat::Both, TauriConfJson::V2(_)) => ManifestFormat::V2,
+            (ManifestFormat::Both, TauriConfJson::V1(_)) => ManifestFormat::V1,
+            (format, _) => format,
+        }
+    }
+
+    fn manifest_key(&self, format: ManifestFormat) -> String {
+        match (format, self.merge_manifest) {
+            (ManifestFormat::V2, true) => {
+                namespacing::derive_shared_latest_json_s3_key(self.branch)
+            }
+            (ManifestFormat::V2, false) => {
+                namespacing::derive_latest_json_s3_key(self.branch, self.target)
+            }
+            (_, true) => namespacing::derive_shared_release_file_s3_key(self.branch),
+            (_, false) => derive_release_file_s3_key(self.branch, self.target),
+        }
+    }
+
+    pub fn release_file_key(&self) -> String {
+        self.manifest_key(self.endpoint_format())
+    }
+
+    /// the url the updater has to be pointed at
+    pub fn release_file_url(&self) -> String {
+        namespacing::s3_key_url(self.s3_config, &self.release_file_key())
+    }
+
+    /// everything the deployer writes urls into on the branch: the updater manifests, the downloads manifest and page
+    pub fn published_document_keys(&self) -> Vec<String> {
+        self.manifest_formats()
+            .into_iter()
+            .map(|format| self.manifest_key(format))
+            .chain([
+                namespacing::derive_downloads_manifest_s3_key(self.branch),
+                namespacing::derive_downloads_page_s3_key(self.branch),
+            ])
+            .collect()
+    }
+
+    /// the localized copies of the manifests
+    pub fn localized_document_keys<'a>(
+        &self,
+        locales: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<String> {
+        locales
+            .into_iter()
+            .flat_map(|locale| {
+                self.manifest_formats().into_iter().map(move |format| {
+                    namespacing::derive_localized_s3_key(&self.manifest_key(format), locale)
+                })
+            })
+            .collect()
+    }
+
+    /// locales the manifest the updater is pointed at has copies in
+    async fn published_locales(&self) -> Result<Vec<String>> {
+        let key = self.release_file_key();
+        let prefix = key.strip_suffix("json").unwrap_or(&key);
+        Ok(storage::list_keys(self.s3_config, prefix)
+            .await?
+            .iter()
+            .filter_map(|candidate| namespacing::localized_s3_key_locale(&key, candidate))
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn staged_release_key(&self) -> String {
+        derive_staged_release_file_s3_key(self.branch, self.target, self.version())
+    }
+
+    /// uploads the binaries and stages the release, the live release-notes.json is not touched
+    pub async fn upload_artifacts(&self, args: &ArtifactsArgs) -> Result<StagedRelease> {
+        let release_platforms = self
+            .target
+            .to_release_platform()
+            .wrap_err("getting release platform from target")?;
+        let release_dir = match &args.release_dir {
+            Some(r) => r.clone(),
+            None => release_assets_path(self.target).wrap_err("failed to derive a release path")?,
+        };
+        let release_dir = files::long_path(&release_dir)?;
+        let path_filter = args.path_filter()?;
+        let entries = walkdir::WalkDir::new(&release_dir)
+            .follow_links(args.symlinks == SymlinkPolicy::Follow)
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                // the .app bundle itself ships as .app.tar.gz, its contents are not artifacts
+                let app_bundle = e.file_type().is_dir() && name.ends_with(".app");
+                !(app_bundle || files::is_junk_file(&name))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| match e.loop_ancestor() {
+                Some(ancestor) => eyre::eyre!(
+                    "symlink loop - {:?} points back to {ancestor:?}",
+                    e.path().unwrap_or(ancestor)
+                ),
+                None => e.into(),
+            })
+            .wrap_err("reading release dir entries")?;
+        let symlinks = entries
+            .iter()
+            .filter(|e| e.path_is_symlink())
+            .map(|e| e.path())
+            .collect_vec();
+        match args.symlinks {
+            SymlinkPolicy::Fail if !symlinks.is_empty() => {
+                bail!("symlinks in the release dir (--symlinks fail): {symlinks:#?}")
+            }
+            SymlinkPolicy::Skip if !symlinks.is_empty() => {
+                info!("skipping symlinks (--symlinks skip): {symlinks:#?}")
+            }
+            _ => {}
+        }
+        let mut files = entries
+            .into_iter()
+            .filter(|e| args.symlinks == SymlinkPolicy::Follow || !e.path_is_symlink())
+            .filter(|e| match self.classifier.kind(self.target, e.path()) {
+                Some(artifacts::ArtifactKind::Package) => !args.skip_packages,
+                Some(_) => e.file_type().is_file(),
+                None => false,
+            })
+            .filter(
+                |e| match files::windows_name_problem(&e.file_name().to_string_lossy()) {
+                    Some(problem) => {
+                        warn!(
+                            "skipping {:?} - {problem}, it can't be downloaded on windows",
+                            e.path()
+                        );
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .filter(|e| {
+                let included = path_filter(e.path().strip_prefix(&release_dir).unwrap_or(e.path()));
+                if !included {
+                    debug!("skipping {:?} (--include / --exclude)", e.path());
+                }
+                included
+            })
+            .map(|entry| entry.into_path())
+            .collect_vec();
+        let (bundle, signature_file) =
+            updater_bundle(&self.classifier, self.target, args.installer, &files)?;
+        let strict_signatures = args
+            .strict_signatures
+            .unwrap_or_else(|| is_release_branch(self.branch));
+        let signature_file = match signature_file {
+            Some(signature_file) => Some(signature_file),
+            None => minisign::sign_with_tauri(&args.tauri_cli, &bundle)
+                .await?
+                .inspect(|signature_file| files.push(signature_file.clone())),
+        };
+        let signature = match signature_file {
+            Some(signature_file) => tokio::fs::read_to_string(&signature_file)
+                .await
+                .wrap_err("reading signature from found file")?,
+            None => String::new(),
+        };
+        if signature.trim().is_empty() {
+            if strict_signatures {
+                bail!(
+                    "no signature for the updater bundle [{}] (missing or empty .sig) - clients would reject this update, is TAURI_PRIVATE_KEY set for the build? (--strict-signatures)",
+                    bundle.display()
+                );
+            }
+            error!(
+                "no signature for the updater bundle [{}], the updater will reject this update",
+                bundle.display()
+            );
+        } else {
+            minisign::check_bundle(self.tauri_conf_json, &bundle, &signature, strict_signatures)
+                .await?;
+        }
+        let with_keys = files
+            .iter()
+            .map(|binary_file_path| {
+                let rule = self.classifier.classify(self.target, binary_file_path);
+                let installer = rule
+                    .filter(|rule| {
+                        matches!(
+                            rule.kind,
+                            artifacts::ArtifactKind::Installer | artifacts::ArtifactKind::Package
+                        )
+                    })
+                    .map(|rule| {
+                        rule.content_type.as_deref().unwrap_or_else(|| {
+                            storage::content_type_for(&binary_file_path.to_string_lossy())
+                        })
+                    });
+                let file_name = || {
+                    binary_file_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .ok_or_else(|| eyre::eyre!("this is a directory"))
+                };
+                let key = match installer {
+                    Some(_)
+                        if rule.map(|rule| rule.kind) == Some(artifacts::ArtifactKind::Package) =>
+                    {
+                        file_name().map(|file_name| {
+                            namespacing::derive_package_s3_key(
+                                self.branch,
+                                self.target,
+                                self.version(),
+                                &file_name,
+                            )
+                        })
+                    }
+                    Some(_) => file_name().map(|file_name| {
+                        namespacing::derive_download_s3_key(
+                            self.branch,
+                            self.target,
+                            self.version(),
+                            &file_name,
+                        )
+                    }),
+                    None => derive_binary_file_s3_key(
+                        self.tauri_conf_json,
+                        self.target,
+                        self.branch,
+                        binary_file_path.clone(),
+                        self.git_hash,
+                    ),
+                };
+                key.map(|key| (binary_file_path, key, installer))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("extracting s3 keys")?;
+        info!("uploading:\n{:#?}", with_keys);
+        self.ensure_immutable(
+            with_keys
+                .iter()
+                .filter(|(_, _, installer)| installer.is_none())
+                .map(|(path, key, _)| (path.as_path(), key.as_str())),
+            &args.multipart,
+        )
+        .await?;
+        let installer_urls = with_keys
+            .iter()
+            .filter(|(_, _, installer)| installer.is_some())
+            .filter_map(|(path, key, _)| {
+                Some((
+                    path.file_name()?.to_string_lossy().to_string(),
+                    namespacing::s3_key_url(self.s3_config, key),
+                ))
+            })
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let installer_urls = &installer_urls;
+        let staged_key = self.staged_release_key();
+        let contents_key = namespacing::derive_contents_s3_key(
+            self.branch,
+            self.target,
+            self.version(),
+            self.git_hash,
+        );
+        futures::future::try_join_all(
+            with_keys
+                .iter()
+                .map(|(_, key, _)| key)
+                .chain([&contents_key, &staged_key])
+                .map(|key| self.stage_artifact(key)),
+        )
+        .await?;
+        use futures::{
+            StreamExt,
+            TryStreamExt,
+        };
+        let tasks = with_keys.iter().map(|(path, key, installer)| async move {
+            let kind = self.classifier.kind(self.target, path);
+            if let Some(content_type) = installer {
+                let mut download = downloads::upload_installer(
+                    self,
+                    path,
+                    key,
+                    content_type,
+                    installer_urls,
+                    &args.multipart,
+                    args.content_disposition,
+                )
+                .await?;
+                if args.zsync && zsync::is_appimage(path) {
+                    download.zsync_url =
+                        Some(zsync::upload(self, path, &download, &args.zsyncmake).await?);
+                }
+                let artifact = StagedArtifact {
+                    key: key.clone(),
+                    url: download.url.clone(),
+                    sha256: download.sha256.clone(),
+                    size: download.size,
+                    kind,
+                };
+                return Ok::<_, eyre::Report>((artifact, Some(download)));
+            }
+            let sha256 = checksum::sha256_file(path).await?;
+            let size = tokio::fs::metadata(path)
+                .await
+                .wrap_err("reading file size")?
+                .len();
+            let headers = self
+                .classifier
+                .storage_class(self.target, path)
+                .map(|class| vec![("x-amz-storage-class", class)])
+                .unwrap_or_default();
+            let url = storage::upload_large(
+                path,
+                self.s3_config,
+                self.fallback_endpoints,
+                key,
+                &args.multipart,
+                &headers,
+            )
+            .await?;
+            let artifact = StagedArtifact {
+                key: key.clone(),
+                url,
+                sha256,
+                size,
+                kind,
+            };
+            Ok((artifact, None))
+        });
+        let (mut artifacts, downloads): (Vec<_>, Vec<_>) = futures::stream::iter(tasks)
+            .buffered(args.concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await
+            .wrap_err("uploading all binary files")?
+            .into_iter()
+            .unzip();
+        let downloads = downloads.into_iter().flatten().collect_vec();
+        if args.symbols.symbols {
+            artifacts
+                .extend(symbols::upload(self, &release_dir, &args.symbols, &args.multipart).await?);
+        }
+        storage::upload_json(
+            &Contents {
+                version: self.version().to_string(),
+                commit: self.git_hash.to_string(),
+                target: self.target.clone(),
+                objects: artifacts.clone(),
+            },
+            self.s3_config,
+            self.fallback_endpoints,
+            &contents_key,
+        )
+        .await
+        .wrap_err("uploading contents.json")?;
+        let (packages, installers): (Vec<_>, Vec<_>) = downloads.iter().partition(|download| {
+            download
+                .key
+                .starts_with(&namespacing::derive_packages_prefix(self.branch))
+        });
+        info!(
+            " :: installers ::\n{}\n :: packages ::\n{}",
+            installers.iter().map(|download| &download.url).join("\n"),
+            packages.iter().map(|download| &download.url).join("\n")
+        );
+        let binary_url = with_keys
+            .iter()
+            .zip(&artifacts)
+            .find(|((path, _, _), _)| **path == bundle)
+            .map(|(_, artifact)| artifact.url.clone())
+            .ok_or_else(|| eyre::eyre!("updater bundle [{}] was not uploaded", bundle.display()))?;
+        info!(binary_url);
+
+        info!("all files uploaded");
+        if args.cleanup {
+            warn!("cleaning up to prevent cache from growing out of control");
+            std::fs::remove_dir_all(&release_dir).wrap_err("cleaning up cache failed")?;
+        }
+        let staged = StagedRelease {
+            version: self.version().to_string(),
+            commit: self.git_hash.to_string(),
+            train: self.train.map(ToString::to_string),
+            platforms: release_platforms
+                .into_iter()
+                .map(|release_platform| {
+                    (
+                        release_platform,
+                        RemoteRelease {
+                            url: binary_url.clone(),
+                            signature: signature.clone(),
+                        },
+                    )
+                })
+                .collect(),
+            artifacts,
+            downloads,
+        };
+        storage::upload_json(
+            &staged,
+            self.s3_config,
+            self.fallback_endpoints,
+            &staged_key,
+        )
+        .await
+        .wrap_err("uploading staged release file to s3")?;
+        info!(" ::: binaries staged under [{staged_key}], release is NOT live yet :::");
+        index::append(self, &staged).await?;
+        github::report_staged(self, &staged)?;
+        Ok(staged)
+    }
+
+    /// binary keys hold the version and commit, what's under them may have been downloaded already - different content is only uploaded over it with `--force`
+    async fn ensure_immutable(
+        &self,
+        binaries: impl Iterator<Item = (&Path, &str)>,
+        multipart: &storage::MultipartArgs,
+    ) -> Result<()> {
+        let mut overwrites = vec![];
+        for (path, key) in binaries {
+            let (remote_size, remote_etag) = match storage::head(self.s3_config, key).await? {
+                Some(remote) => remote,
+                None => continue,
+            };
+            let local_size = tokio::fs::metadata(path)
+                .await
+                .wrap_err_with(|| format!("reading size of [{}]", path.display()))?
+                .len();
+            let local_etag =
+                checksum::s3_etag_file(path, multipart.part_size_for(local_size)).await?;
+            if remote_size != local_size || remote_etag.as_deref() != Some(local_etag.as_str()) {
+                overwrites.push(policy::Overwrite {
+                    key: key.to_string(),
+                    remote_size,
+                    remote_etag: remote_etag.unwrap_or_default(),
+                    local_size,
+                    local_etag,
+                });
+            }
+        }
+        if overwrites.is_empty() {
+            return Ok(());
+        }
+        let report = policy::overwrite_report(&overwrites);
+        if self.force {
+            warn!(
+                "--force :: overwriting binaries of {} ::\n{report}",
+                self.version()
+            );
+            return Ok(());
+        }
+        bail!("{} binaries of {} @ {} differ from the uploaded ones, users may have downloaded those already - pass --force to replace them ::\n{report}", overwrites.len(), self.version(), self.git_hash)
+    }
+
+    /// records the branch (or channel) the key namespace belongs to on the first upload, and refuses to upload into one that belongs to another - two branch names sanitized to the same one
+    pub async fn claim_namespace(&self, owner: &str) -> Result<()> {
+        let key = namespacing::derive_namespace_owner_s3_key(self.branch);
+        let claim = serde_json::to_vec_pretty(&serde_json::json!({ "owner": owner }))
+            .wrap_err("serializing namespace owner")?;
+        if storage::put_if_none_match(self.s3_config, &key, &claim, "application/json").await? {
+            info!("namespace :: [{}] belongs to {owner}", self.branch);
+            return Ok(());
+        }
+        let current = storage::get_bytes(self.s3_config, &key)
+            .await
+            .wrap_err_with(|| format!("reading namespace owner [{key}]"))?
+            .and_then(|content| serde_json::from_slice::<serde_json::Value>(&content).ok())
+            .unwrap_or_default();
+        match current["owner"].as_str() {
+            Some(current) if current == owner => Ok(()),
+            Some(current) => bail!(
+                "[{}] belongs to {current}, not {owner} - their names are the same once sanitized, rename one of them or map it to a channel",
+                self.branch
+            ),
+            None => bail!("namespace owner [{key}] is not valid, remove it to claim [{}] again", self.branch),
+        }
+    }
+
+    /// reads back the release staged by [`Deployment::upload_artifacts`], possibly in another CI job
+    pub async fn fetch_staged(&self) -> Result<StagedRelease> {
+        self.fetch_staged_version(self.version()).await
+    }
+
+    pub async fn fetch_staged_version(&self, version: &str) -> Result<StagedRelease> {
+        let staged_key = derive_staged_release_file_s3_key(self.branch, self.target, version);
+        storage::fetch_json(self.s3_config, &staged_key)
+            .await
+            .wrap_err("fetching staged release")?
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "no release staged under [{staged_key}] - run `upload-artifacts` for version {version} first"
+                )
+            })
+    }
+
+    /// every version staged for the target, oldest first
+    pub async fn staged_versions(&self) -> Result<Vec<semver::Version>> {
+        let prefix = format!(
+            "{}/",
+            namespacing::derive_release_base_key(self.branch, self.target)
+        );
+        let keys = storage::list_keys(self.s3_config, &prefix)
+            .await
+            .wrap_err("listing staged releases")?;
+        Ok(keys
+            .iter()
+            .filter_map(|key| {
+                key.strip_prefix(&prefix)?
+                    .strip_suffix("/staged-release.json")
+            })
+            .filter_map(|version| semver::Version::parse(version).ok())
+            .sorted()
+            .collect())
+    }
+
+    /// version the updater of this target currently sees
+    pub async fn live_version(&self) -> Result<Option<String>> {
+        self.manifest_version(&self.release_file_key()).await
+    }
+
+    /// commit of the release the updater is pointed at, from its receipt - `None` when nothing is live or it has no receipt
+    pub async fn live_commit(&self) -> Result<Option<String>> {
+        let Some(version) = self.live_version().await? else {
+            return Ok(None);
+        };
+        Ok(receipt::latest_of_version(self, &version)
+            .await?
+            .map(|receipt| receipt.commit))
+    }
+
+    async fn manifest_version(&self, key: &str) -> Result<Option<String>> {
+        Ok(storage::get_bytes(self.s3_config, key)
+            .await
+            .wrap_err_with(|| format!("reading live manifest [{key}]"))?
+            .and_then(|content| serde_json::from_slice::<serde_json::Value>(&content).ok())
+            .and_then(|manifest| manifest.get("version")?.as_str().map(ToString::to_string)))
+    }
+
+    /// flips the live release-notes.json to the staged release, in merge mode the read-modify-write is guarded by a lock in the bucket
+    pub async fn publish(&self, staged: &StagedRelease, args: &PublishArgs) -> Result<()> {
+        let variables = [
+            ("app", self.tauri_conf_json.product_name().to_string()),
+            ("version", staged.version.clone()),
+            ("branch", self.branch.to_string()),
+            ("commit", staged.commit.clone()),
+            ("train", self.train(staged).unwrap_or_default()),
+            ("date", time::OffsetDateTime::now_utc().date().to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let live_commit = match args.notes.reads_commits() {
+            true => self
+                .live_commit()
+                .await
+                .wrap_err("looking up the commit of the live release")?,
+            false => None,
+        };
+        let notes = args
+            .notes
+            .resolve(
+                &staged.version,
+                live_commit.as_deref(),
+                &notes::default_notes(self.branch, &staged.version),
+                variables,
+            )
+            .await?;
+        let published = notify::Published::new(
+            self,
+            staged,
+            &notes.public_or_default(self.branch, &staged.version),
+        );
+        let github_deployment = github::GithubDeployment::start(self, staged, &args.github).await?;
+        if let Err(e) = self.go_live(staged, &notes, args).await {
+            notify::notify_failure(&args.notify, &published, &e).await;
+            if let Some(github_deployment) = &github_deployment {
+                if let Err(status_error) = github_deployment.set_status("failure").await {
+                    error!("github :: {status_error:?}");
+                }
+            }
+            return Err(e);
+        }
+        if let Some(github_deployment) = &github_deployment {
+            // the release is live already, a hiccup of the github api mustn't skip what follows
+            if let Err(e) = github_deployment.set_status("success").await {
+                error!("github :: {e:?}");
+            }
+        }
+        tag::tag_and_push(self.tauri_conf_json.product_name(), staged, &args.tag)?;
+        github::mirror(self, staged, &published.notes, &args.github).await?;
+        github::report_published(self, staged)?;
+        notify::notify(&args.notify, &published).await?;
+        if args.links.check_links {
+            links::check(self, &args.report).await?;
+        }
+        Ok(())
+    }
+
+    /// checks, writes the live manifests and purges the cdn
+    async fn go_live(
+        &self,
+        staged: &StagedRelease,
+        notes: &notes::Notes,
+        args: &PublishArgs,
+    ) -> Result<()> {
+        history::require_sign_offs(self, &staged.version, &args.require_sign_off).await?;
+        history::ensure_not_yanked(self, &staged.version).await?;
+        args.policy.check(&staged.version)?;
+        self.ensure_newer_than_live(&staged.version).await?;
+        if let Some(rules) = args.notes.lint_rules(self.branch).await? {
+            notes::check(
+                &notes.public_or_default(self.branch, &staged.version),
+                &rules,
+                &args.report,
+            )?;
+        }
+        if let Some(internal) = &notes.internal {
+            info!(" :: internal notes (not published to the manifest) ::\n{internal}\n\n");
+        }
+        self.write_release_file_locked(staged, notes, &args.policy, &args.lock)
+            .await?;
+        let mut purged = self.localized_document_keys(notes.localized.keys());
+        if args.latest_alias {
+            purged.extend(self.write_latest_alias().await?);
+        }
+        if args.appcast {
+            let notes = notes.public_or_default(self.branch, &staged.version);
+            purged.push(appcast::publish(self, staged, &notes, &args.policy).await?);
+        }
+        if args.gzip_manifests {
+            purged.extend(self.write_gzip_manifests().await?);
+        }
+        if args.metainfo {
+            let notes = notes.public_or_default(self.branch, &staged.version);
+            purged.push(metainfo::publish(self, &staged.version, &notes).await?);
+        }
+        cdn::purge(self, &purged, &args.cdn).await?;
+        if args.scoop.scoop_bucket.is_some() {
+            packaging::scoop(self, &staged.version, &args.scoop).await?;
+        }
+        Ok(())
+    }
+
+    /// refuses to publish a version not newer than the live one unless forced - in merge mode the other targets publish the same version, so only older ones are refused
+    async fn ensure_newer_than_live(&self, version: &str) -> Result<()> {
+        let live = self.live_version().await?;
+        match policy::check_monotonic(live.as_deref(), version, self.merge_manifest) {
+            Err(e) if self.force => {
+                warn!("--force :: {e}");
+                Ok(())
+            }
+            checked => checked,
+        }
+    }
+
+    /// puts an earlier staged release back live, e.g. when the live one got yanked - with the notes, `minimum_version` and
+    /// `mandatory` it went live with, as archived in its last receipt. the localized manifests too, with the same notes.
+    /// returns their keys
+    pub async fn republish(
+        &self,
+        staged: &StagedRelease,
+        lock_args: &lock::LockArgs,
+    ) -> Result<Vec<String>> {
+        let aliased = self.is_latest_alias_live().await?;
+        let archived = match receipt::latest_of_version(self, &staged.version).await? {
+            Some(receipt) => archived_notes_and_policy(&receipt)?,
+            None => None,
+        };
+        let (mut notes, policy) = archived.unwrap_or_else(|| {
+            warn!(
+                "no archived manifest of {} [{}], republishing it with the default notes and no policy",
+                staged.version, self.branch
+            );
+            Default::default()
+        });
+        let public = notes.public_or_default(self.branch, &staged.version);
+        notes.localized = self
+            .published_locales()
+            .await?
+            .into_iter()
+            .map(|locale| (locale, public.clone()))
+            .collect();
+        self.write_release_file_locked(staged, &notes, &policy, lock_args)
+            .await?;
+        let mut purged = self.localized_document_keys(notes.localized.keys());
+        if aliased {
+            purged.extend(self.write_latest_alias().await?);
+        }
+        let gzipped = format!("{}.gz", self.release_file_key());
+        if storage::head(self.s3_config, &gzipped).await?.is_some() {
+            purged.extend(self.write_gzip_manifests().await?);
+        }
+        Ok(purged)
+    }
+
+    /// gzipped copies of the written manifests, returns their keys
+    async fn write_gzip_manifests(&self) -> Result<Vec<String>> {
+        let mut written = vec![];
+        for format in self.manifest_formats() {
+            let key = self.manifest_key(format);
+            let content = storage::get_bytes(self.s3_config, &key)
+                .await?
+                .ok_or_else(|| eyre::eyre!("[{key}] was not written"))?;
+            let gzip_key = format!("{key}.gz");
+            storage::sync_bytes(
+                self.s3_config,
+                &gzip_key,
+                &compression::gzip(&content),
+                storage::content_type_for(&key),
+                false,
+                &[("Content-Encoding", "gzip")],
+            )
+            .await
+            .wrap_err_with(|| format!("uploading [{gzip_key}]"))?;
+            written.push(gzip_key);
+        }
+        Ok(written)
+    }
+
+    /// manifest keys and the keys of their latest aliases
+    fn latest_alias_keys(&self) -> Vec<(String, String)> {
+        self.manifest_formats()
+            .into_iter()
+            .map(|format| {
+                let key = self.manifest_key(format);
+                let alias = namespacing::derive_latest_alias_s3_key(self.branch, &key);
+                (key, alias)
+            })
+            .collect()
+    }
+
+    /// whether the latest alias serves the live version of this namespace, and so has to follow it when it's rolled back
+    async fn is_latest_alias_live(&self) -> Result<bool> {
+        let alias = namespacing::derive_latest_alias_s3_key(self.branch, &self.release_file_key());
+        let aliased = self.manifest_version(&alias).await?;
+        Ok(aliased.is_some() && aliased == self.live_version().await?)
+    }
+
+    /// copies the manifests (and their signatures) to their latest aliases, returns the alias keys
+    async fn write_latest_alias(&self) -> Result<Vec<String>> {
+        let signed = minisign::manifest_signing_key_from_env()?.is_some();
+        let mut aliases = vec![];
+        for (key, alias) in self.latest_alias_keys() {
+            let url = storage::copy(self.s3_config, &key, &alias, &[])
+                .await
+                .wrap_err("copying manifest to its latest alias")?;
+            if signed {
+                storage::copy(
+                    self.s3_config,
+                    &format!("{key}.sig"),
+                    &format!("{alias}.sig"),
+                    &[],
+                )
+                .await
+                .wrap_err("copying manifest signature to its latest alias")?;
+            }
+            info!(" :: latest alias :: {url}");
+            aliases.push(alias);
+        }
+        Ok(aliases)
+    }
+
+    /// removes the updater manifests, their localized copies, signatures, gzipped copies and checksums, so the updater finds
+    /// nothing to update to. records the deletion in a receipt, returns every removed key
+    pub async fn delete_manifests(&self, version: &str) -> Result<Vec<String>> {
+        if self.merge_manifest {
+            bail!("the manifest is shared by every target of [{}], deleting it would unpublish all of them - roll it back with --to instead", self.branch);
+        }
+        let locales = self.published_locales().await?;
+        let aliases = match self.is_latest_alias_live().await? {
+            true => self
+                .latest_alias_keys()
+                .into_iter()
+                .map(|(_, alias)| alias)
+                .collect_vec(),
+            false => vec![],
+        };
+        let keys = self
+            .manifest_formats()
+            .into_iter()
+            .map(|format| self.manifest_key(format))
+            .chain(self.localized_document_keys(&locales))
+            .chain(aliases)
+            .collect_vec();
+        let mut deleted = vec![];
+        for key in keys {
+            for key in [format!("{key}.sig"), format!("{key}.gz"), key] {
+                storage::delete(self.s3_config, &key).await?;
+                deleted.push(key);
+            }
+            info!(" :: deleted [{}] ::", deleted[deleted.len() - 1]);
+        }
+        let checksums = namespacing::derive_checksums_s3_key(&self.release_file_key());
+        storage::delete(self.s3_config, &checksums).await?;
+        deleted.push(checksums);
+        receipt::emit_deleted(self, version)
+            .await
+            .wrap_err("emitting deploy receipt")?;
+        Ok(deleted)
+    }
+
+    async fn write_release_file_locked(
+        &self,
+        staged: &StagedRelease,
+        notes: &notes::Notes,
+        policy: &policy::PolicyArgs,
+        lock_args: &lock::LockArgs,
+    ) -> Result<()> {
+        if !self.merge_manifest {
+            return self.write_release_file(staged, notes, policy).await;
+        }
+        let lock = lock::acquire(self, &self.release_file_key(), lock_args).await?;
+        let published = self.write_release_file(staged, notes, policy).await;
+        lock.release(self).await;
+        published
+    }
+
+    fn release_notes(
+        &self,
+        staged: &StagedRelease,
+        notes: &notes::Notes,
+        policy: &policy::PolicyArgs,
+    ) -> ReleaseNotes {
+        ReleaseNotes {
+            notes: notes.public_or_default(self.branch, &staged.version),
+            version: staged.version.clone(),
+            pub_date: time::OffsetDateTime::now_utc(),
+            train: self.train(staged),
+            minimum_version: policy.minimum_version.clone(),
+            mandatory: policy.mandatory,
+            platforms: staged.platforms.clone(),
+            files: staged
+                .artifacts
+                .iter()
+                .map(|artifact| {
+                    (
+                        artifact
+                            .key
+                            .rsplit('/')
+                            .next()
+                            .unwrap_or(&artifact.key)
+                            .to_string(),
+                        FileChecksum {
+                            url: artifact.url.clone(),
+                            sha256: artifact.sha256.clone(),
+                            size: artifact.size,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// `--train` given to `publish` wins over the one the release was staged with
+    pub fn train(&self, staged: &StagedRelease) -> Option<String> {
+        self.train
+            .map(ToString::to_string)
+            .or_else(|| staged.train.clone())
+    }
+
+    fn formatted(release: ReleaseNotes, format: ManifestFormat) -> ReleaseNotes {
+        match format {
+            ManifestFormat::V2 => release.v2_only(),
+            _ => release,
+        }
+    }
+
+    /// read-merge-write of the shared release file, the write only goes through if nobody changed the file since it was read
+    async fn merge_release_file(
+        &self,
+        staged: &StagedRelease,
+        notes: &notes::Notes,
+        policy: &policy::PolicyArgs,
+        format: ManifestFormat,
+        release_key: &str,
+    ) -> Result<String> {
+        let content = storage::update_if_match(
+            self.s3_config,
+            self.fallback_endpoints,
+            release_key,
+            "application/json",
+            MANIFEST_MERGE_ATTEMPTS,
+            |current| {
+                let release = match current {
+                    Some(current) => {
+                        info!("merging into existing release file [{release_key}]");
+                        let existing = serde_json::from_slice::<ReleaseNotes>(current)
+                            .wrap_err("parsing current release file")?;
+                        self.release_notes(staged, notes, policy)
+                            .merged_with(existing)
+                    }
+                    None => self.release_notes(staged, notes, policy),
+                };
+                self.staged_objects.mark_published();
+                serde_json::to_vec_pretty(&Self::formatted(release, format))
+                    .wrap_err("serializing release file")
+            },
+        )
+        .await
+        .wrap_err("uploading release file to s3")?;
+        String::from_utf8(content).wrap_err("release file is not utf-8")
+    }
+
+    /// uploads the minisign signature of the manifest as `{key}.sig` when a signing key is configured
+    async fn sign_manifest(&self, key: &str, content: &str) -> Result<()> {
+        let signing_key = match minisign::manifest_signing_key_from_env()? {
+            Some(signing_key) => signing_key,
+            None => return Ok(()),
+        };
+        let file_name = key.rsplit('/').next().unwrap_or(key);
+        let signature = minisign::sign(
+            content.as_bytes(),
+            file_name,
+            &signing_key,
+            time::OffsetDateTime::now_utc().unix_timestamp(),
+        );
+        let url = storage::put_bytes(
+            self.s3_config,
+            &format!("{key}.sig"),
+            signature.as_bytes(),
+            "text/plain; charset=utf-8",
+        )
+        .await
+        .wrap_err("uploading manifest signature")?;
+        info!(
+            "manifest signed :: {url} (public key {})",
+            minisign::encode_public_key(&signing_key)
+        );
+        Ok(())
+    }
+
+    /// writes a single updater manifest, returns its content and url
+    async fn write_manifest(
+        &self,
+        staged: &StagedRelease,
+        notes: &notes::Notes,
+        policy: &policy::PolicyArgs,
+        format: ManifestFormat,
+    ) -> Result<(String, String)> {
+        let key = self.manifest_key(format);
+        let (content, url) = if self.merge_manifest {
+            let content = self
+                .merge_release_file(staged, notes, policy, format, &key)
+                .await?;
+            (content, namespacing::s3_key_url(self.s3_config, &key))
+        } else {
+            let release = Self::formatted(self.release_notes(staged, notes, policy), format);
+            let content =
+                serde_json::to_string_pretty(&release).wrap_err("serializing release file")?;
+            self.staged_objects.mark_published();
+            let url = storage::upload_bytes(
+                content.as_bytes(),
+                self.s3_config,
+                self.fallback_endpoints,
+                &key,
+            )
+            .await
+            .wrap_err("uploading release file to s3")?;
+            (content, url)
+        };
+        info!(" :: uploaded release [{key}] ::\n{content}\n\n");
+        self.sign_manifest(&key, &content).await?;
+        self.write_localized_manifests(&key, &content, notes)
+            .await?;
+        Ok((content, url))
+    }
+
+    /// copies of the manifest with the notes replaced, one per locale
+    async fn write_localized_manifests(
+        &self,
+        key: &str,
+        content: &str,
+        notes: &notes::Notes,
+    ) -> Result<()> {
+        for (locale, localized_notes) in &notes.localized {
+            let mut manifest: serde_json::Value =
+                serde_json::from_str(content).wrap_err("parsing written manifest")?;
+            manifest["notes"] = localized_notes.clone().into();
+            let localized = serde_json::to_string_pretty(&manifest)
+                .wrap_err("serializing localized manifest")?;
+            let localized_key = namespacing::derive_localized_s3_key(key, locale);
+            storage::upload_bytes(
+                localized.as_bytes(),
+                self.s3_config,
+                self.fallback_endpoints,
+                &localized_key,
+            )
+            .await
+            .wrap_err_with(|| format!("uploading {locale} manifest"))?;
+            self.sign_manifest(&localized_key, &localized).await?;
+            info!(" :: uploaded {locale} release [{localized_key}] ::");
+        }
+        Ok(())
+    }
+
+    /// `SHA256SUMS` of the files listed in the written manifest, next to it
+    async fn write_checksums(&self, release_key: &str, content: &str) -> Result<()> {
+        let release: ReleaseNotes =
+            serde_json::from_str(content).wrap_err("parsing written manifest")?;
+        if release.files.is_empty() {
+            return Ok(());
+        }
+        let url = storage::put_bytes(
+            self.s3_config,
+            &namespacing::derive_checksums_s3_key(release_key),
+            release.sha256sums().as_bytes(),
+            "text/plain; charset=utf-8",
+        )
+        .await
+        .wrap_err("uploading SHA256SUMS")?;
+        info!("checksums :: {url}");
+        Ok(())
+    }
+
+    async fn write_release_file(
+        &self,
+        staged: &StagedRelease,
+        notes: &notes::Notes,
+        policy: &policy::PolicyArgs,
+    ) -> Result<()> {
+        let release_key = self.release_file_key();
+        info!("binaries upload successfully, generating release_file");
+        let mut written = vec![];
+        for format in self.manifest_formats() {
+            written.push(self.write_manifest(staged, notes, policy, format).await?);
+        }
+        // the manifest the updater is pointed at is written last
+        let (release_content, release_file_url) = written
+            .pop()
+            .ok_or_else(|| eyre::eyre!("no manifest format selected"))?;
+
+        info!(" :: validating ::");
+        if !self
+            .tauri_conf_json
+            .update_endpoints()
+            .iter()
+            .any(|url| url == &release_file_url)
+        {
+            error!("CRITICAL ERROR! UPDATE WILL NOT BE TRIGGERED!");
+            bail!("configuration error - release file url is '{release_file_url}', but no such endpoint was found in tauri.conf.json file. entries found: {:?}", self.tauri_conf_json.update_endpoints())
+        }
+
+        info!(" ::: uploaded to [{release_key}], update is LIVE :::");
+        self.write_checksums(&release_key, &release_content).await?;
+        downloads::publish(self, &staged.downloads)
+            .await
+            .wrap_err("publishing downloads")?;
+        receipt::emit(
+            self,
+            staged,
+            notes,
+            &release_key,
+            &release_file_url,
+            release_content.as_bytes(),
+        )
+        .await
+        .wrap_err("emitting deploy receipt")?;
+        Ok(())
+    }
+}
+
+/// the notes and policy a release went live with, read back from the manifest archived in its receipt - `None` for
+/// receipts that predate manifest archiving
+fn archived_notes_and_policy(
+    receipt: &receipt::Receipt,
+) -> Result<Option<(notes::Notes, policy::PolicyArgs)>> {
+    let manifest = match &receipt.manifest {
+        Some(manifest) => manifest,
+        None => return Ok(None),
+    };
+    let archived = serde_json::from_str::<ReleaseNotes>(manifest).wrap_err_with(|| {
+        format!(
+            "parsing the manifest archived in receipt {}",
+            receipt.deploy_id
+        )
+    })?;
+    Ok(Some((
+        notes::Notes {
+            public: Some(archived.notes),
+            internal: receipt.internal_notes.clone(),
+            localized: Default::default(),
+        },
+        policy::PolicyArgs {
+            minimum_version: archived.minimum_version,
+            mandatory: archived.mandatory,
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abort_leaves_existing_objects_alone() {
+        let staged = StagedObjects::default();
+        // a rerun uploading the binary of a published version again, and a new installer
+        staged.artifact(
+            "release/x86_64-pc-windows-msvc/1.0.0/deadbeef/app.msi.zip",
+            true,
+        );
+        staged.artifact(
+            "release/downloads/x86_64-pc-windows-msvc/1.0.0/app.exe",
+            false,
+        );
+        staged.lease("release/deploy-queue/1-a.json");
+        assert_eq!(
+            staged.keys_to_remove(),
+            vec![
+                "release/downloads/x86_64-pc-windows-msvc/1.0.0/app.exe".to_string(),
+                "release/deploy-queue/1-a.json".to_string(),
+            ]
+        );
+        staged.mark_published();
+        assert_eq!(
+            staged.keys_to_remove(),
+            vec!["release/deploy-queue/1-a.json".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_updater_bundle_is_paired_with_its_signature() -> Result<()> {
+        let files = [
+            "bundle/deb/app_1.7.9_amd64.deb",
+            "bundle/appimage/app_1.7.9_amd64.AppImage",
+            "bundle/appimage/app_1.7.9_amd64.AppImage.tar.gz",
+            "bundle/appimage/app_1.7.9_amd64.AppImage.tar.gz.sig",
+            "bundle/macos/app.app.tar.gz.sig",
+            "bundle/dmg/app_1.7.9_x64.dmg",
+            "bundle/macos/app.app.tar.gz",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(
+            updater_bundle(
+                &artifacts::Classifier::default(),
+                &RustTarget::Linux64,
+                artifacts::InstallerKind::Msi,
+                &files
+            )?,
+            (files[2].clone(), Some(files[3].clone()))
+        );
+        assert_eq!(
+            updater_bundle(
+                &artifacts::Classifier::default(),
+                &RustTarget::MacOsX86_64,
+                artifacts::InstallerKind::Msi,
+                &files
+            )?,
+            (files[6].clone(), Some(files[4].clone()))
+        );
+        assert!(updater_bundle(
+            &artifacts::Classifier::default(),
+            &RustTarget::Win64,
+            artifacts::InstallerKind::Msi,
+            &files
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_yanked_release_is_republished_with_its_notes_and_policy() -> Result<()> {
+        let archived = ReleaseNotes {
+            version: "1.7.8".to_string(),
+            notes: "fixes the crash on startup".to_string(),
+            pub_date: time::OffsetDateTime::UNIX_EPOCH,
+            train: None,
+            minimum_version: Some("1.6.0".to_string()),
+            mandatory: true,
+            platforms: Default::default(),
+            files: Default::default(),
+        };
+        let mut receipt = serde_json::from_value::<receipt::Receipt>(serde_json::json!({
+            "deploy_id": "a",
+            "branch": "release",
+            "target": "x86_64-pc-windows-msvc",
+            "version": "1.7.8",
+            "commit": "deadbeef",
+            "published_at": "2024-03-01T12:00:00Z",
+            "manifest_key": "release/x86_64-pc-windows-msvc/release-notes.json",
+            "manifest_url": "https://example.com/release/x86_64-pc-windows-msvc/release-notes.json",
+            "manifest_sha256": "",
+            "artifacts": {},
+            "internal_notes": "JIRA-123",
+        }))?;
+        assert!(archived_notes_and_policy(&receipt)?.is_none());
+
+        receipt.manifest = Some(serde_json::to_string_pretty(&archived)?);
+        let (notes, policy) = archived_notes_and_policy(&receipt)?.unwrap();
+        assert_eq!(notes.public.as_deref(), Some("fixes the crash on startup"));
+        assert_eq!(notes.internal.as_deref(), Some("JIRA-123"));
+        assert_eq!(policy.minimum_version.as_deref(), Some("1.6.0"));
+        assert!(policy.mandatory);
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_branches() {
+        assert!(is_release_branch("master"));
+        assert!(is_release_branch("release/2024.06"));
+        assert!(is_release_branch("stable"));
+        assert!(!is_release_branch("feature/release-notes"));
+        assert!(!is_release_branch("releases"));
+    }
+
+    #[test]
+    fn test_include_exclude_globs() -> Result<()> {
+        let args = ArtifactsArgs {
+            release_dir: None,
+            cleanup: false,
+            skip_packages: false,
+            installer: artifacts::InstallerKind::Msi,
+            include: vec!["msi/*".to_string(), "nsis/*".to_string()],
+            exclude: vec!["*.pdb".to_string(), "*_pl-PL.*".to_string()],
+            strict_signatures: None,
+            tauri_cli: "npx tauri".to_string(),
+            multipart: storage::MultipartArgs {
+                multipart_threshold: 64,
+                part_size: 16,
+                parallel_parts: 4,
+            },
+            symlinks: SymlinkPolicy::Skip,
+            content_disposition: false,
+            concurrency: 4,
+            zsync: false,
+            zsyncmake: "zsyncmake".to_string(),
+            symbols: Default::default(),
+        };
+        let path_filter = args.path_filter()?;
+        assert!(path_filter(Path::new("msi/app_1.7.9_x64_en-US.msi.zip")));
+        assert!(!path_filter(Path::new("msi/app_1.7.9_x64_pl-PL.msi")));
+        assert!(!path_filter(Path::new("nsis/app.pdb")));
+        assert!(!path_filter(Path::new("dmg/app.dmg")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_installer_kind_picks_the_bundle() -> Result<()> {
+        let files = [
+            "bundle/msi/app_1.7.9_x64_en-US.msi",
+            "bundle/msi/app_1.7.9_x64_en-US.msi.zip",
+            "bundle/msi/app_1.7.9_x64_en-US.msi.zip.sig",
+            "bundle/nsis/app_1.7.9_x64-setup.exe",
+            "bundle/nsis/app_1.7.9_x64-setup.nsis.zip",
+            "bundle/nsis/app_1.7.9_x64-setup.nsis.zip.sig",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(
+            updater_bundle(
+                &artifacts::Classifier::default(),
+                &RustTarget::Win64,
+                artifacts::InstallerKind::Msi,
+                &files
+            )?,
+            (files[1].clone(), Some(files[2].clone()))
+        );
+        assert_eq!(
+            updater_bundle(
+                &artifacts::Classifier::default(),
+                &RustTarget::Win64,
+                artifacts::InstallerKind::Nsis,
+                &files
+            )?,
+            (files[4].clone(), Some(files[5].clone()))
+        );
+        Ok(())
+    }
+}
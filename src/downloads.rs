@@ -0,0 +1,477 @@
+use super::*;
+use crate::deploy::Deployment;
+use std::collections::BTreeMap;
+
+/// App Installer file pointing windows at the msix package and at itself for update checks (e.g. for Intune deployments)
+pub const APPINSTALLER_SUFFIX: &str = ".appinstaller";
+
+/// detached gpg signature of an installer, picked up from next to it when present
+const GPG_SIGNATURE_SUFFIX: &str = ".asc";
+
+/// how many times the downloads manifest update is redone when another deploy changed it in the meantime
+const MANIFEST_UPDATE_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Download {
+    pub key: String,
+    pub file_name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub train: Option<String>,
+    pub url: String,
+    pub content_type: String,
+    pub sha256: String,
+    pub size: u64,
+    /// detached gpg signature, e.g. of a `.flatpak` bundle
+    #[serde(default)]
+    pub signature_url: Option<String>,
+    /// stable url of the latest installer of this kind, set once published
+    #[serde(default)]
+    pub permalink: Option<String>,
+    /// header the installer was uploaded with, its permalink gets the same one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_disposition: Option<String>,
+    /// zsync control file of an AppImage, uploaded with `--zsync`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zsync_url: Option<String>,
+}
+
+/// latest installers of every target of a branch
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DownloadsManifest {
+    pub downloads: BTreeMap<RustTarget, Vec<Download>>,
+}
+
+impl DownloadsManifest {
+    /// replaces the installers of a single target, leaving the others untouched
+    fn with_target(mut self, target: &RustTarget, downloads: Vec<Download>) -> Self {
+        self.downloads.insert(target.clone(), downloads);
+        self
+    }
+
+    /// `labels` are the annotations of the listed versions, shown next to them
+    fn to_html(&self, branch: &str, labels: &BTreeMap<String, Vec<String>>) -> String {
+        let sections = self
+            .downloads
+            .iter()
+            .map(|(target, downloads)| {
+                let items = downloads
+                    .iter()
+                    .map(|download| {
+                        let permalink = download
+                            .permalink
+                            .as_ref()
+                            .map(|url| format!(" - <a href=\"{}\">permalink</a>", escape_html(url)))
+                            .unwrap_or_default();
+                        let signature = download
+                            .signature_url
+                            .as_ref()
+                            .map(|url| format!(" - <a href=\"{}\">gpg signature</a>", escape_html(url)))
+                            .unwrap_or_default();
+                        let labels = labels
+                            .get(&download.version)
+                            .map(|labels| {
+                                labels
+                                    .iter()
+                                    .map(|label| format!(" <mark>{}</mark>", escape_html(label)))
+                                    .join("")
+                            })
+                            .unwrap_or_default();
+                        format!(
+                            "      <li><a href=\"{}\">{}</a> ({}, {:.1} MiB){labels}{permalink}{signature}<br><code>sha256: {}</code></li>\n",
+                            escape_html(&download.url),
+                            escape_html(&download.file_name),
+                            escape_html(&download.version),
+                            download.size as f64 / (1024.0 * 1024.0),
+                            download.sha256
+                        )
+                    })
+                    .join("");
+                format!(
+                    "    <h2>{}</h2>\n    <ul>\n{items}    </ul>\n",
+                    serde_variant::to_variant_name(target).expect("this will always serialize")
+                )
+            })
+            .join("");
+        let title = format!("{} downloads", escape_html(branch));
+        format!(
+            "<!DOCTYPE html>\n<html>\n  <head>\n    <meta charset=\"utf-8\">\n    <title>{title}</title>\n  </head>\n  <body>\n    <h1>{title}</h1>\n{sections}  </body>\n</html>\n"
+        )
+    }
+}
+
+/// points every `Uri` of the App Installer file at where things end up in the bucket - packages by file name, the file itself at its permalink
+fn rewrite_appinstaller(
+    content: &str,
+    own_url: &str,
+    installer_urls: &BTreeMap<String, String>,
+) -> String {
+    const ATTRIBUTE: &str = "Uri=\"";
+    let mut rewritten = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find(ATTRIBUTE) {
+        let (before, after) = rest.split_at(start + ATTRIBUTE.len());
+        rewritten.push_str(before);
+        let end = match after.find('"') {
+            Some(end) => end,
+            None => break,
+        };
+        let uri = &after[..end];
+        let file_name = uri.rsplit('/').next().unwrap_or(uri);
+        let new_uri = if file_name.ends_with(APPINSTALLER_SUFFIX) {
+            own_url
+        } else {
+            installer_urls
+                .get(file_name)
+                .map(String::as_str)
+                .unwrap_or(uri)
+        };
+        if new_uri == uri {
+            rewritten.push_str(uri);
+        } else {
+            info!("{APPINSTALLER_SUFFIX} :: {uri} -> {new_uri}");
+            rewritten.push_str(&escape_html(new_uri));
+        }
+        rest = &after[end..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+pub fn escape_html(val: &str) -> String {
+    val.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// characters left as they are in the utf-8 `filename*` of a Content-Disposition header
+const FILENAME_ATTR_CHAR: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_');
+
+/// makes browsers save the installer under its file name, with an ascii fallback for those that don't understand `filename*`
+pub fn content_disposition(file_name: &str) -> String {
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        namespacing::ascii_identifier(file_name),
+        percent_encoding::utf8_percent_encode(file_name, FILENAME_ATTR_CHAR)
+    )
+}
+
+/// uploads the installer under its versioned downloads key, it only shows up on the downloads page once published
+///
+/// `installer_urls` are the urls of all installers of this release by file name, App Installer files are rewritten to reference them
+pub async fn upload_installer(
+    deployment: &Deployment<'_>,
+    path: &Path,
+    key: &str,
+    content_type: &str,
+    installer_urls: &BTreeMap<String, String>,
+    multipart: &storage::MultipartArgs,
+    attachment: bool,
+) -> Result<Download> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let content_disposition = attachment.then(|| content_disposition(&file_name));
+    let headers = content_disposition
+        .as_deref()
+        .map(|value| ("Content-Disposition", value))
+        .into_iter()
+        .chain(
+            deployment
+                .classifier
+                .storage_class(deployment.target, path)
+                .map(|class| ("x-amz-storage-class", class)),
+        )
+        .collect_vec();
+    // only App Installer files are rewritten, every other installer streams from disk - NSIS bundles run to hundreds of MiB
+    let (url, sha256, size) = if key.ends_with(APPINSTALLER_SUFFIX) {
+        let own_url = namespacing::s3_key_url(
+            deployment.s3_config,
+            &namespacing::derive_download_permalink_s3_key(
+                deployment.branch,
+                deployment.target,
+                APPINSTALLER_SUFFIX,
+            ),
+        );
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("reading App Installer file [{}]", path.display()))?;
+        let content = rewrite_appinstaller(&content, &own_url, installer_urls).into_bytes();
+        let url = storage::put_bytes_large(
+            deployment.s3_config,
+            key,
+            &content,
+            content_type,
+            multipart,
+            &headers,
+        )
+        .await
+        .wrap_err("uploading installer")?;
+        (url, checksum::sha256_bytes(&content), content.len() as u64)
+    } else {
+        let size = tokio::fs::metadata(path)
+            .await
+            .wrap_err_with(|| format!("reading size of [{}]", path.display()))?
+            .len();
+        let (url, sha256) = storage::upload_large_as(
+            path,
+            deployment.s3_config,
+            deployment.fallback_endpoints,
+            key,
+            content_type,
+            multipart,
+            &headers,
+        )
+        .await
+        .wrap_err("uploading installer")?;
+        (url, sha256, size)
+    };
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(GPG_SIGNATURE_SUFFIX);
+    let signature_url = match tokio::fs::read(&signature_path).await {
+        Ok(signature) => {
+            let signature_key = format!("{key}{GPG_SIGNATURE_SUFFIX}");
+            deployment.stage_artifact(&signature_key).await?;
+            Some(
+                storage::put_bytes(
+                    deployment.s3_config,
+                    &signature_key,
+                    &signature,
+                    "application/pgp-signature",
+                )
+                .await
+                .wrap_err("uploading installer gpg signature")?,
+            )
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e)
+                .wrap_err_with(|| format!("reading [{}]", Path::new(&signature_path).display()))
+        }
+    };
+    Ok(Download {
+        key: key.to_string(),
+        file_name,
+        version: deployment.tauri_conf_json.version().to_string(),
+        train: deployment.train.map(ToString::to_string),
+        url,
+        content_type: content_type.to_string(),
+        sha256,
+        size,
+        signature_url,
+        permalink: None,
+        content_disposition,
+        zsync_url: None,
+    })
+}
+
+/// copies the installer to its `latest` key and puts a `sha256sum` compatible checksum file next to it
+async fn publish_permalink(deployment: &Deployment<'_>, download: &Download) -> Result<Download> {
+    let suffix = deployment
+        .classifier
+        .classify(deployment.target, &download.file_name)
+        .map(|rule| rule.suffix.as_str())
+        .ok_or_else(|| eyre::eyre!("[{}] is not an installer", download.file_name))?;
+    let permalink_key =
+        namespacing::derive_download_permalink_s3_key(deployment.branch, deployment.target, suffix);
+    let headers = download
+        .content_disposition
+        .as_deref()
+        .map(|value| vec![("Content-Disposition", value)])
+        .unwrap_or_default();
+    let permalink = storage::copy(
+        deployment.s3_config,
+        &download.key,
+        &permalink_key,
+        &headers,
+    )
+    .await
+    .wrap_err("updating installer permalink")?;
+    storage::put_bytes(
+        deployment.s3_config,
+        &format!("{permalink_key}.sha256"),
+        format!("{}  {}\n", download.sha256, download.file_name).as_bytes(),
+        "text/plain; charset=utf-8",
+    )
+    .await
+    .wrap_err("uploading installer checksum")?;
+    if download.signature_url.is_some() {
+        storage::copy(
+            deployment.s3_config,
+            &format!("{}{GPG_SIGNATURE_SUFFIX}", download.key),
+            &format!("{permalink_key}{GPG_SIGNATURE_SUFFIX}"),
+            &[],
+        )
+        .await
+        .wrap_err("updating installer gpg signature permalink")?;
+    }
+    if download.zsync_url.is_some() {
+        storage::copy(
+            deployment.s3_config,
+            &format!("{}{}", download.key, zsync::ZSYNC_SUFFIX),
+            &format!("{permalink_key}{}", zsync::ZSYNC_SUFFIX),
+            &[],
+        )
+        .await
+        .wrap_err("updating zsync control file permalink")?;
+    }
+    info!("permalink :: {permalink} -> {}", download.file_name);
+    Ok(Download {
+        permalink: Some(permalink),
+        ..download.clone()
+    })
+}
+
+/// points the downloads manifest and page of the branch at the installers of this release
+pub async fn publish(deployment: &Deployment<'_>, downloads: &[Download]) -> Result<()> {
+    if downloads.is_empty() {
+        return Ok(());
+    }
+    let downloads = futures::future::try_join_all(
+        downloads
+            .iter()
+            .map(|download| publish_permalink(deployment, download)),
+    )
+    .await?;
+    let manifest_key = namespacing::derive_downloads_manifest_s3_key(deployment.branch);
+    let content = storage::update_if_match(
+        deployment.s3_config,
+        deployment.fallback_endpoints,
+        &manifest_key,
+        "application/json",
+        MANIFEST_UPDATE_ATTEMPTS,
+        |current| {
+            let manifest = match current {
+                Some(current) => serde_json::from_slice::<DownloadsManifest>(current)
+                    .wrap_err("parsing current downloads manifest")?,
+                None => DownloadsManifest::default(),
+            };
+            serde_json::to_vec_pretty(&manifest.with_target(deployment.target, downloads.clone()))
+                .wrap_err("serializing downloads manifest")
+        },
+    )
+    .await
+    .wrap_err("updating downloads manifest")?;
+    let manifest: DownloadsManifest =
+        serde_json::from_slice(&content).wrap_err("parsing written downloads manifest")?;
+    upload_page(deployment, &manifest).await
+}
+
+async fn upload_page(deployment: &Deployment<'_>, manifest: &DownloadsManifest) -> Result<()> {
+    let labels = history::labels(
+        deployment,
+        manifest
+            .downloads
+            .values()
+            .flatten()
+            .map(|download| download.version.as_str()),
+    )
+    .await?;
+    let page_url = storage::put_bytes(
+        deployment.s3_config,
+        &namespacing::derive_downloads_page_s3_key(deployment.branch),
+        manifest.to_html(deployment.branch, &labels).as_bytes(),
+        "text/html; charset=utf-8",
+    )
+    .await
+    .wrap_err("uploading downloads page")?;
+    info!(" ::: downloads page updated: {page_url} :::");
+    Ok(())
+}
+
+/// re-renders the downloads page from the live downloads manifest, e.g. after the labels of a version changed
+pub async fn render_page(deployment: &Deployment<'_>) -> Result<()> {
+    let manifest_key = namespacing::derive_downloads_manifest_s3_key(deployment.branch);
+    match storage::get_bytes(deployment.s3_config, &manifest_key).await? {
+        Some(content) => {
+            let manifest: DownloadsManifest =
+                serde_json::from_slice(&content).wrap_err("parsing downloads manifest")?;
+            upload_page(deployment, &manifest).await
+        }
+        None => {
+            info!("no downloads published on [{}] yet", deployment.branch);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(file_name: &str) -> Download {
+        Download {
+            key: format!("release/downloads/x86_64-apple-darwin/1.7.9/{file_name}"),
+            file_name: file_name.to_string(),
+            version: "1.7.9".to_string(),
+            train: None,
+            url: format!("https://example.com/{file_name}"),
+            content_type: "application/octet-stream".to_string(),
+            sha256: checksum::sha256_bytes(file_name.as_bytes()),
+            size: 1024 * 1024,
+            signature_url: None,
+            permalink: None,
+            content_disposition: None,
+            zsync_url: None,
+        }
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("Zażółć App_1.0.0_x64.msi"),
+            "attachment; filename=\"Zazolc-App_1.0.0_x64.msi\"; filename*=UTF-8''Za%C5%BC%C3%B3%C5%82%C4%87%20App_1.0.0_x64.msi"
+        );
+    }
+
+    #[test]
+    fn test_appinstaller_uris_are_rewritten() {
+        let appinstaller = r#"<AppInstaller Uri="\\share\App.appinstaller" Version="1.7.9.0">
+  <MainPackage Name="App" Uri="https://old.example.com/App_1.7.9_x64.msix" />
+  <Dependencies><Package Uri="https://other.example.com/VCLibs.appx" /></Dependencies>
+</AppInstaller>"#;
+        let installer_urls = [(
+            "App_1.7.9_x64.msix".to_string(),
+            "https://bucket.example.com/release/downloads/x86_64-pc-windows-msvc/1.7.9/App_1.7.9_x64.msix".to_string(),
+        )]
+        .into_iter()
+        .collect();
+        let rewritten = rewrite_appinstaller(
+            appinstaller,
+            "https://bucket.example.com/latest.appinstaller",
+            &installer_urls,
+        );
+        assert!(rewritten
+            .contains(r#"<AppInstaller Uri="https://bucket.example.com/latest.appinstaller""#));
+        assert!(rewritten.contains(r#"Uri="https://bucket.example.com/release/downloads/x86_64-pc-windows-msvc/1.7.9/App_1.7.9_x64.msix""#));
+        assert!(rewritten.contains(r#"Uri="https://other.example.com/VCLibs.appx""#));
+    }
+
+    #[test]
+    fn test_publishing_replaces_only_own_target() {
+        let manifest = DownloadsManifest::default()
+            .with_target(&RustTarget::Win64, vec![download("App.msi")])
+            .with_target(&RustTarget::MacOsX86_64, vec![download("old.dmg")])
+            .with_target(&RustTarget::MacOsX86_64, vec![download("App<1>.dmg")]);
+        assert_eq!(manifest.downloads.len(), 2);
+        assert_eq!(
+            manifest.downloads[&RustTarget::MacOsX86_64],
+            vec![download("App<1>.dmg")]
+        );
+        let labels = [(
+            "1.7.9".to_string(),
+            vec!["known-issue: <HiDPI>".to_string()],
+        )]
+        .into_iter()
+        .collect();
+        let html = manifest.to_html("release", &labels);
+        assert!(html.contains("App&lt;1&gt;.dmg"));
+        assert!(html.contains("<mark>known-issue: &lt;HiDPI&gt;</mark>"));
+        assert!(!html.contains("old.dmg"));
+    }
+}
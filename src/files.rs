@@ -0,0 +1,196 @@
+use super::*;
+use std::fs::{
+    File,
+    OpenOptions,
+};
+use std::io::Write;
+
+/// advisory lock on a sidecar `.{file name}.lock` next to the file - the file itself gets replaced by `write_atomic`, so locking it directly would not exclude anybody
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    // the lock is released when the file is closed
+    _file: File,
+}
+
+fn sidecar_path(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| eyre::eyre!("[{}] is not a file", path.display()))?
+        .to_string_lossy();
+    Ok(path.with_file_name(format!(".{file_name}{suffix}")))
+}
+
+impl FileLock {
+    fn open(path: &Path) -> Result<(PathBuf, File)> {
+        let path = sidecar_path(path, ".lock")?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .wrap_err_with(|| format!("opening lock file [{}]", path.display()))?;
+        Ok((path, file))
+    }
+
+    /// blocks until no other process holds the lock for `path`
+    pub fn acquire<T: AsRef<Path>>(path: T) -> Result<Self> {
+        let (path, file) = Self::open(path.as_ref())?;
+        if file.try_lock().is_err() {
+            info!("waiting for [{}] held by another process", path.display());
+            file.lock()
+                .wrap_err_with(|| format!("locking [{}]", path.display()))?;
+        }
+        debug!("locked [{}]", path.display());
+        Ok(Self { path, _file: file })
+    }
+
+    /// the lock for `path`, `None` when another process (or another handle of this one) holds it right now
+    pub fn try_acquire<T: AsRef<Path>>(path: T) -> Result<Option<Self>> {
+        let (path, file) = Self::open(path.as_ref())?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { path, _file: file })),
+            Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+            Err(std::fs::TryLockError::Error(e)) => {
+                Err(e).wrap_err_with(|| format!("locking [{}]", path.display()))
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        debug!("unlocked [{}]", self.path.display());
+    }
+}
+
+/// writes to a temporary file in the same directory and renames it over `path`, readers see either the old or the new content, never a truncated file
+pub fn write_atomic<T: AsRef<Path>, C: AsRef<[u8]>>(path: T, content: C) -> Result<()> {
+    let path = path.as_ref();
+    let temp_path = sidecar_path(path, &format!(".{}.tmp", uuid::Uuid::new_v4()))?;
+    let written = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e).wrap_err_with(|| format!("writing [{}]", path.display()));
+    }
+    Ok(())
+}
+
+/// files editors and file managers leave behind
+const JUNK_FILES: &[&str] = &[".DS_Store", "Thumbs.db", "desktop.ini", ".directory"];
+
+/// OS and editor leftovers that never belong in the upload set
+pub fn is_junk_file(name: &str) -> bool {
+    JUNK_FILES.iter().any(|junk| junk.eq_ignore_ascii_case(name))
+        // macos resource forks, vim swap files, backups
+        || name.starts_with("._")
+        || (name.starts_with('.') && name.ends_with(".swp"))
+        || name.ends_with('~')
+}
+
+/// windows paths this long need the `\\?\` prefix
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// device names windows reserves in every directory, whatever the extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// why a file name that is fine on linux can't be created (or downloaded) on windows, if it can't
+pub fn windows_name_problem(name: &str) -> Option<&'static str> {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        return Some("reserved device name");
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some("trailing dot or space");
+    }
+    if name.contains(|c: char| "<>:\"|?*\\".contains(c) || c.is_control()) {
+        return Some("character not allowed on windows");
+    }
+    None
+}
+
+fn verbatim(path: &str) -> String {
+    match path.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{unc}"),
+        None => format!(r"\\?\{path}"),
+    }
+}
+
+/// absolute path without resolving symlinks, on windows paths past MAX_PATH get the `\\?\` prefix so deeply nested bundle dirs stay readable
+pub fn long_path<T: AsRef<Path>>(path: T) -> Result<PathBuf> {
+    let absolute = std::path::absolute(path.as_ref())
+        .wrap_err_with(|| format!("absolute path of [{}]", path.as_ref().display()))?;
+    let absolute_str = absolute.to_string_lossy();
+    if cfg!(windows) && absolute_str.len() >= WINDOWS_MAX_PATH && !absolute_str.starts_with(r"\\?\")
+    {
+        return Ok(PathBuf::from(verbatim(&absolute_str)));
+    }
+    Ok(absolute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_content() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("write-atomic-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("tauri.conf.json");
+        std::fs::write(&path, "{\"old\": true}")?;
+        {
+            let _lock = FileLock::acquire(&path)?;
+            write_atomic(&path, "{}")?;
+        }
+        assert_eq!(std::fs::read_to_string(&path)?, "{}");
+        // only the lock file is left behind, no temporary files
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 2);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_path_handling() {
+        assert_eq!(
+            windows_name_problem("nul.txt"),
+            Some("reserved device name")
+        );
+        assert_eq!(windows_name_problem("Com1"), Some("reserved device name"));
+        assert_eq!(windows_name_problem("app "), Some("trailing dot or space"));
+        assert_eq!(
+            windows_name_problem("app:1.7.9.msi"),
+            Some("character not allowed on windows")
+        );
+        assert_eq!(windows_name_problem("console.exe"), None);
+        assert_eq!(windows_name_problem("LICENSE"), None);
+        assert_eq!(
+            verbatim(r"C:\a\target\release\bundle\nsis\app-setup.exe"),
+            r"\\?\C:\a\target\release\bundle\nsis\app-setup.exe"
+        );
+        assert_eq!(
+            verbatim(r"\\runner\share\bundle"),
+            r"\\?\UNC\runner\share\bundle"
+        );
+    }
+
+    #[test]
+    fn test_junk_files() {
+        assert!(is_junk_file(".DS_Store"));
+        assert!(is_junk_file("thumbs.db"));
+        assert!(is_junk_file("._app_1.7.9_x64.dmg"));
+        assert!(is_junk_file(".release-notes.json.swp"));
+        assert!(!is_junk_file("app_1.7.9_x64.dmg"));
+        assert!(!is_junk_file(".app.tar.gz"));
+    }
+}
@@ -0,0 +1,723 @@
+use super::*;
+
+/// static hosts and most cdns serve this for a path that doesn't exist
+const NOT_FOUND_PAGE: &str = "404.html";
+
+/// dir under the prefix holding the uploaded versions and what's live, never part of the site itself
+const VERSIONS_DIR: &str = "_versions/";
+
+/// how many times the deploy list is reread when another deploy changed it in the meantime
+const DEPLOYS_UPDATE_ATTEMPTS: usize = 5;
+
+#[derive(clap::Args, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct DeployFrontendArgs {
+    #[clap(subcommand)]
+    pub action: Option<FrontendAction>,
+    /// the built web app, e.g. vite's `dist`
+    #[clap(long, default_value = "dist", value_name = "DIR")]
+    pub dist_dir: PathBuf,
+    /// key prefix the site is synced to, defaults to `{branch}/frontend/`
+    #[clap(long, value_name = "PREFIX")]
+    pub prefix: Option<String>,
+    /// files (relative to the dist dir) with a content hash in their name, they get the immutable Cache-Control and everything else is revalidated - can be repeated
+    #[clap(long, default_value = "assets/**", value_name = "GLOB")]
+    pub immutable: Vec<String>,
+    /// single page app - `index.html` is uploaded as `404.html` too (unless the dist dir has one), so client side routes load the app
+    #[clap(long)]
+    pub spa: bool,
+    /// gzip text assets (html, css, js, json, svg, wasm) and serve them with `Content-Encoding: gzip` under their own keys, for buckets and cdns that don't compress on the fly
+    #[clap(long)]
+    pub gzip: bool,
+    /// `.br` / `.gz` variants the build wrote next to the assets (e.g. vite-plugin-compression) get the content type of the asset and their `Content-Encoding`, for a cdn or edge function picking them by `Accept-Encoding`
+    #[clap(long)]
+    pub precompressed: bool,
+    /// upload into a content-addressed `_versions/{id}/` under the prefix and make it live from there, `deploy-frontend rollback` goes back to an earlier one
+    #[clap(long)]
+    pub versioned: bool,
+    #[clap(flatten)]
+    pub live: LiveArgs,
+    /// how many files are uploaded at the same time
+    #[clap(long, default_value_t = 8, value_name = "N")]
+    pub concurrency: usize,
+    #[clap(flatten)]
+    pub cdn: cdn::CdnArgs,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum FrontendAction {
+    /// makes the previous versioned deploy (or `--to`) live again
+    Rollback(RollbackArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RollbackArgs {
+    /// id of the version to make live, defaults to the one deployed before the live one
+    #[clap(long, value_name = "ID")]
+    pub to: Option<String>,
+    #[clap(long, value_name = "PREFIX")]
+    pub prefix: Option<String>,
+    #[clap(flatten)]
+    pub live: LiveArgs,
+    #[clap(flatten)]
+    pub cdn: cdn::CdnArgs,
+}
+
+#[derive(clap::Args, Debug, Default)]
+pub struct LiveArgs {
+    /// delete objects under the prefix that are not part of the site anymore
+    #[clap(long)]
+    pub delete: bool,
+    /// versioned deploys only flip `_versions/current.json` and leave the live prefix alone - for a cdn or edge function serving the version it points at
+    #[clap(long)]
+    pub pointer_only: bool,
+}
+
+/// where the site goes - unlike a tauri deployment it needs no tauri.conf.json or rust target
+pub struct Frontend<'a> {
+    pub s3_config: &'a S3Config,
+    pub fallback_endpoints: &'a [String],
+    pub branch: &'a str,
+    pub git_hash: &'a str,
+}
+
+impl Frontend<'_> {
+    /// tags every uploaded object with the branch and commit, plus the `--meta` / `DEPLOY_METADATA` metadata
+    pub fn set_default_headers(&self, metadata: &[(String, String)]) {
+        storage::set_default_headers(
+            [(
+                "x-amz-tagging".to_string(),
+                storage::tagging(&[("branch", self.branch), ("commit", self.git_hash)]),
+            )]
+            .into_iter()
+            .chain(metadata.iter().cloned())
+            .collect(),
+        );
+    }
+}
+
+/// how a file is served
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Encoding {
+    content_type: &'static str,
+    content_encoding: Option<&'static str>,
+    /// gzipped on upload, precompressed variants are uploaded as they are
+    compress: bool,
+}
+
+/// a file of the dist dir
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SiteFile {
+    path: PathBuf,
+    /// `/` separated, relative to the dist dir
+    relative: String,
+    immutable: bool,
+    encoding: Encoding,
+}
+
+/// a file of an uploaded version
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VersionFile {
+    pub path: String,
+    pub immutable: bool,
+    /// set when it differs from the one of the path, e.g. for a `.br` variant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+}
+
+/// `_versions/{id}.json` - what a versioned deploy uploaded
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FrontendVersion {
+    pub id: String,
+    pub commit: String,
+    pub files: Vec<VersionFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FrontendDeploy {
+    pub id: String,
+    pub commit: String,
+    pub deployed_at: String,
+}
+
+/// `_versions/deploys.json` - the versions made live, the last one is live. a rollback drops the last one
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FrontendDeploys {
+    pub deploys: Vec<FrontendDeploy>,
+}
+
+fn now() -> Result<String> {
+    time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .wrap_err("formatting current date")
+}
+
+fn is_html(key: &str) -> bool {
+    let key = key.to_lowercase();
+    key.ends_with(".html") || key.ends_with(".htm")
+}
+
+/// precompressed variants of a file of the site keep its content type, `--gzip` compresses the text ones
+fn encoding(
+    relative: &str,
+    args: &DeployFrontendArgs,
+    paths: &std::collections::HashSet<&str>,
+) -> Encoding {
+    if args.precompressed {
+        for (suffix, content_encoding) in [(".br", "br"), (".gz", "gzip")] {
+            if let Some(original) = relative
+                .strip_suffix(suffix)
+                .filter(|original| paths.contains(original))
+            {
+                return Encoding {
+                    content_type: storage::content_type_for(original),
+                    content_encoding: Some(content_encoding),
+                    compress: false,
+                };
+            }
+        }
+    }
+    let content_type = storage::content_type_for(relative);
+    let compress = args.gzip && compression::is_compressible(content_type);
+    Encoding {
+        content_type,
+        content_encoding: compress.then_some("gzip"),
+        compress,
+    }
+}
+
+fn live_prefix(branch: &str, prefix: Option<&str>) -> String {
+    match prefix {
+        Some(prefix) => match prefix.trim_matches('/') {
+            // the bucket root
+            "" => String::new(),
+            prefix => format!("{prefix}/"),
+        },
+        None => namespacing::derive_frontend_prefix(branch),
+    }
+}
+
+/// every file of the dist dir, the `--spa` fallback page included - pages last, so a new page never references assets that aren't there yet
+fn site_files(args: &DeployFrontendArgs) -> Result<Vec<SiteFile>> {
+    let immutable = args
+        .immutable
+        .iter()
+        .try_fold(globset::GlobSetBuilder::new(), |mut builder, pattern| {
+            builder.add(
+                globset::Glob::new(pattern).wrap_err_with(|| format!("bad glob [{pattern}]"))?,
+            );
+            Ok::<_, eyre::Report>(builder)
+        })?
+        .build()
+        .wrap_err("building --immutable globs")?;
+    let mut site = vec![];
+    for entry in walkdir::WalkDir::new(&args.dist_dir)
+        .into_iter()
+        .filter_entry(|e| !files::is_junk_file(&e.file_name().to_string_lossy()))
+    {
+        let entry = entry.wrap_err("reading dist dir entries")?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(&args.dist_dir)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .join("/");
+        if relative.starts_with(VERSIONS_DIR) {
+            bail!("[{relative}] - {VERSIONS_DIR} is where the deployer keeps the versions, the site can't have it");
+        }
+        site.push(SiteFile {
+            immutable: immutable.is_match(&relative),
+            relative,
+            path: entry.into_path(),
+            encoding: Encoding {
+                content_type: "",
+                content_encoding: None,
+                compress: false,
+            },
+        });
+    }
+    if site.is_empty() {
+        bail!(
+            "[{}] holds no files - was the frontend built?",
+            args.dist_dir.display()
+        );
+    }
+    if args.spa {
+        let index = site
+            .iter()
+            .find(|file| file.relative == "index.html")
+            .cloned()
+            .ok_or_else(|| {
+                eyre::eyre!("--spa needs an index.html in [{}]", args.dist_dir.display())
+            })?;
+        match site.iter().any(|file| file.relative == NOT_FOUND_PAGE) {
+            true => warn!("frontend :: the dist dir has its own {NOT_FOUND_PAGE}, keeping it as the --spa fallback"),
+            false => site.push(SiteFile {
+                relative: NOT_FOUND_PAGE.to_string(),
+                ..index
+            }),
+        }
+    }
+    let paths = site
+        .iter()
+        .map(|file| file.relative.clone())
+        .collect::<Vec<_>>();
+    let paths = paths.iter().map(String::as_str).collect();
+    for file in &mut site {
+        file.encoding = encoding(&file.relative, args, &paths);
+    }
+    site.sort_by_key(|file| (is_html(&file.relative), file.relative.clone()));
+    Ok(site)
+}
+
+/// the same site content always gets the same id
+async fn content_id(site: &[SiteFile]) -> Result<String> {
+    let mut listing = String::new();
+    for file in site {
+        listing.push_str(&format!(
+            "{} {}\n",
+            checksum::sha256_file(&file.path).await?,
+            file.relative
+        ));
+    }
+    Ok(checksum::sha256_bytes(listing.as_bytes())[..16].to_string())
+}
+
+/// uploads the files under the prefix, skipping the ones the bucket holds already - returns the keys that changed
+async fn sync(
+    frontend: &Frontend<'_>,
+    site: &[SiteFile],
+    prefix: &str,
+    all_immutable: bool,
+    concurrency: usize,
+) -> Result<Vec<(String, bool)>> {
+    use futures::{
+        StreamExt,
+        TryStreamExt,
+    };
+    let (pages, assets): (Vec<_>, Vec<_>) = site.iter().partition(|file| is_html(&file.relative));
+    let mut changed = vec![];
+    for batch in [assets, pages] {
+        let uploaded = futures::stream::iter(batch.into_iter().map(|file| async move {
+            let key = format!("{prefix}{}", file.relative);
+            let immutable = all_immutable || file.immutable;
+            let mut content = tokio::fs::read(&file.path)
+                .await
+                .wrap_err_with(|| format!("reading [{}]", file.path.display()))?;
+            if file.encoding.compress {
+                content = compression::gzip(&content);
+            }
+            let headers = file
+                .encoding
+                .content_encoding
+                .map(|content_encoding| vec![("Content-Encoding", content_encoding)])
+                .unwrap_or_default();
+            let uploaded = storage::sync_bytes(
+                frontend.s3_config,
+                &key,
+                &content,
+                file.encoding.content_type,
+                immutable,
+                &headers,
+            )
+            .await?;
+            Ok::<_, eyre::Report>(uploaded.then_some((key, immutable)))
+        }))
+        .buffer_unordered(concurrency.max(1))
+        .try_collect::<Vec<_>>()
+        .await
+        .wrap_err("uploading the frontend")?;
+        changed.extend(uploaded.into_iter().flatten());
+    }
+    info!(
+        "frontend :: {} of {} files uploaded to [{prefix}]",
+        changed.len(),
+        site.len()
+    );
+    Ok(changed)
+}
+
+/// files only a release namespace holds
+const RELEASE_FILE_NAMES: &[&str] = &["release-notes.json", "staged-release.json", "owner.json"];
+
+/// a key showing the prefix is (or holds) a release namespace, whose manifests, binaries and receipts aren't part of the site
+fn release_namespace_key(keys: &[String]) -> Option<&str> {
+    keys.iter().map(String::as_str).find(|key| {
+        key.rsplit('/')
+            .next()
+            .is_some_and(|name| RELEASE_FILE_NAMES.contains(&name))
+    })
+}
+
+/// deletes the objects under the prefix that aren't among the site paths, returns their keys - refuses to when the prefix holds releases
+async fn delete_stale<'a>(
+    frontend: &Frontend<'_>,
+    prefix: &str,
+    paths: impl Iterator<Item = &'a str>,
+) -> Result<Vec<String>> {
+    let keys = paths
+        .map(|path| format!("{prefix}{path}"))
+        .collect::<std::collections::HashSet<_>>();
+    let versions = format!("{prefix}{VERSIONS_DIR}");
+    let stale = storage::list_keys(frontend.s3_config, prefix)
+        .await?
+        .into_iter()
+        .filter(|key| !keys.contains(key) && !key.starts_with(&versions))
+        .collect_vec();
+    if let Some(key) = release_namespace_key(&stale) {
+        bail!("--delete would delete [{key}] - [{prefix}] holds the releases of the app, give the frontend a --prefix of its own");
+    }
+    for stale in &stale {
+        info!("frontend :: deleting [{stale}]");
+        storage::delete(frontend.s3_config, stale).await?;
+    }
+    Ok(stale)
+}
+
+fn version_prefix(prefix: &str, id: &str) -> String {
+    format!("{prefix}{VERSIONS_DIR}{id}/")
+}
+
+async fn fetch_version(frontend: &Frontend<'_>, prefix: &str, id: &str) -> Result<FrontendVersion> {
+    let key = format!("{prefix}{VERSIONS_DIR}{id}.json");
+    let content = storage::get_bytes(frontend.s3_config, &key)
+        .await?
+        .ok_or_else(|| eyre::eyre!("no frontend version {id} under [{prefix}]"))?;
+    serde_json::from_slice(&content).wrap_err_with(|| format!("parsing [{key}]"))
+}
+
+/// makes the uploaded version live - copies it over the live prefix (assets first, pages last) unless `--pointer-only`, then points `_versions/current.json` at it. returns the keys to purge
+async fn go_live(
+    frontend: &Frontend<'_>,
+    prefix: &str,
+    version: &FrontendVersion,
+    args: &LiveArgs,
+) -> Result<Vec<String>> {
+    let mut purged = vec![];
+    if !args.pointer_only {
+        let from = version_prefix(prefix, &version.id);
+        let immutable = storage::immutable_cache_control();
+        let (pages, assets): (Vec<_>, Vec<_>) =
+            version.files.iter().partition(|file| is_html(&file.path));
+        for file in assets.into_iter().chain(pages) {
+            let key = format!("{prefix}{}", file.path);
+            let content_type = file
+                .content_type
+                .as_deref()
+                .unwrap_or_else(|| storage::content_type_for(&file.path));
+            let headers = [("Content-Type", Some(content_type))]
+                .into_iter()
+                .chain([
+                    ("Content-Encoding", file.content_encoding.as_deref()),
+                    ("Cache-Control", immutable.filter(|_| file.immutable)),
+                ])
+                .filter_map(|(header, value)| Some((header, value?)))
+                .collect_vec();
+            storage::copy(
+                frontend.s3_config,
+                &format!("{from}{}", file.path),
+                &key,
+                &headers,
+            )
+            .await?;
+            if !file.immutable {
+                purged.push(key);
+            }
+        }
+        if args.delete {
+            purged.extend(
+                delete_stale(
+                    frontend,
+                    prefix,
+                    version.files.iter().map(|file| file.path.as_str()),
+                )
+                .await?,
+            );
+        }
+    }
+    let pointer = FrontendDeploy {
+        id: version.id.clone(),
+        commit: version.commit.clone(),
+        deployed_at: now()?,
+    };
+    let pointer_key = format!("{prefix}{VERSIONS_DIR}current.json");
+    storage::upload_json(
+        &pointer,
+        frontend.s3_config,
+        frontend.fallback_endpoints,
+        &pointer_key,
+    )
+    .await
+    .wrap_err("updating the frontend pointer")?;
+    purged.push(pointer_key);
+    info!(
+        "frontend :: {} (commit {}) is live on [{prefix}]",
+        version.id, version.commit
+    );
+    Ok(purged)
+}
+
+async fn update_deploys<F>(frontend: &Frontend<'_>, prefix: &str, mut modify: F) -> Result<()>
+where
+    F: FnMut(FrontendDeploys) -> Result<FrontendDeploys>,
+{
+    let key = format!("{prefix}{VERSIONS_DIR}deploys.json");
+    storage::update_if_match(
+        frontend.s3_config,
+        frontend.fallback_endpoints,
+        &key,
+        "application/json",
+        DEPLOYS_UPDATE_ATTEMPTS,
+        |current| {
+            let deploys = match current {
+                Some(current) => {
+                    serde_json::from_slice(current).wrap_err_with(|| format!("parsing [{key}]"))?
+                }
+                None => FrontendDeploys::default(),
+            };
+            serde_json::to_vec_pretty(&modify(deploys)?).wrap_err("serializing frontend deploys")
+        },
+    )
+    .await
+    .wrap_err("updating the frontend deploys")?;
+    Ok(())
+}
+
+/// syncs the dist dir of a web frontend to the prefix, skipping files the bucket holds already
+pub async fn deploy(frontend: &Frontend<'_>, args: &DeployFrontendArgs) -> Result<()> {
+    if let Some(FrontendAction::Rollback(rollback_args)) = &args.action {
+        return rollback(frontend, rollback_args).await;
+    }
+    let prefix = live_prefix(frontend.branch, args.prefix.as_deref());
+    let site = site_files(args)?;
+    if !args.versioned {
+        if args.live.pointer_only {
+            bail!("--pointer-only needs --versioned");
+        }
+        let changed = sync(frontend, &site, &prefix, false, args.concurrency).await?;
+        let mut purged = changed
+            .into_iter()
+            .filter(|(_, immutable)| !immutable)
+            .map(|(key, _)| key)
+            .collect_vec();
+        if args.live.delete {
+            purged.extend(
+                delete_stale(
+                    frontend,
+                    &prefix,
+                    site.iter().map(|file| file.relative.as_str()),
+                )
+                .await?,
+            );
+        }
+        return cdn::purge_keys(frontend.s3_config, &purged, &args.cdn).await;
+    }
+    let version = FrontendVersion {
+        id: content_id(&site).await?,
+        commit: frontend.git_hash.to_string(),
+        files: site
+            .iter()
+            .map(|file| VersionFile {
+                path: file.relative.clone(),
+                immutable: file.immutable,
+                content_type: (file.encoding.content_type
+                    != storage::content_type_for(&file.relative))
+                .then(|| file.encoding.content_type.to_string()),
+                content_encoding: file.encoding.content_encoding.map(str::to_string),
+            })
+            .collect(),
+    };
+    // a version never changes once uploaded, the id is its content
+    sync(
+        frontend,
+        &site,
+        &version_prefix(&prefix, &version.id),
+        true,
+        args.concurrency,
+    )
+    .await?;
+    storage::upload_json(
+        &version,
+        frontend.s3_config,
+        frontend.fallback_endpoints,
+        &format!("{prefix}{VERSIONS_DIR}{}.json", version.id),
+    )
+    .await
+    .wrap_err("uploading the frontend version")?;
+    let purged = go_live(frontend, &prefix, &version, &args.live).await?;
+    let pushed = FrontendDeploy {
+        id: version.id.clone(),
+        commit: version.commit.clone(),
+        deployed_at: now()?,
+    };
+    update_deploys(frontend, &prefix, |mut deploys| {
+        deploys.deploys.push(pushed.clone());
+        Ok(deploys)
+    })
+    .await?;
+    cdn::purge_keys(frontend.s3_config, &purged, &args.cdn).await
+}
+
+/// the version a rollback makes live - `--to` or the one deployed before the live one
+fn rollback_target(deploys: &FrontendDeploys, to: Option<&str>) -> Result<String> {
+    if let Some(to) = to {
+        return Ok(to.to_string());
+    }
+    match deploys.deploys.as_slice() {
+        [.., previous, _live] => Ok(previous.id.clone()),
+        _ => bail!("there is no earlier versioned frontend deploy to roll back to"),
+    }
+}
+
+/// makes an earlier versioned deploy live again
+pub async fn rollback(frontend: &Frontend<'_>, args: &RollbackArgs) -> Result<()> {
+    let prefix = live_prefix(frontend.branch, args.prefix.as_deref());
+    let key = format!("{prefix}{VERSIONS_DIR}deploys.json");
+    let deploys: FrontendDeploys = match storage::get_bytes(frontend.s3_config, &key).await? {
+        Some(content) => {
+            serde_json::from_slice(&content).wrap_err_with(|| format!("parsing [{key}]"))?
+        }
+        None => bail!("nothing was deployed with --versioned under [{prefix}]"),
+    };
+    let id = rollback_target(&deploys, args.to.as_deref())?;
+    let version = fetch_version(frontend, &prefix, &id).await?;
+    let purged = go_live(frontend, &prefix, &version, &args.live).await?;
+    let deployed_at = now()?;
+    update_deploys(frontend, &prefix, |mut deploys| {
+        match &args.to {
+            // repeated rollbacks keep going back
+            None => {
+                deploys.deploys.pop();
+            }
+            Some(_) => deploys.deploys.push(FrontendDeploy {
+                id: version.id.clone(),
+                commit: version.commit.clone(),
+                deployed_at: deployed_at.clone(),
+            }),
+        }
+        Ok(deploys)
+    })
+    .await?;
+    cdn::purge_keys(frontend.s3_config, &purged, &args.cdn).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_site_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("frontend-{}", uuid::Uuid::new_v4()));
+        for file in [
+            "index.html",
+            "about/index.html",
+            "assets/index-BkG3x9aZ.js",
+            "assets/index-BkG3x9aZ.js.br",
+            "favicon.ico",
+            ".DS_Store",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap_or(&dir))?;
+            std::fs::write(&path, file)?;
+        }
+        let args = DeployFrontendArgs {
+            action: None,
+            dist_dir: dir.clone(),
+            prefix: None,
+            immutable: vec!["assets/**".to_string()],
+            spa: true,
+            gzip: true,
+            precompressed: true,
+            versioned: false,
+            live: Default::default(),
+            concurrency: 8,
+            cdn: cdn::CdnArgs {
+                cloudfront_distribution_id: None,
+                purge: vec![],
+                purge_hooks: None,
+            },
+        };
+        let site = site_files(&args);
+        std::fs::remove_dir_all(&dir)?;
+        let site = site?
+            .into_iter()
+            .map(|file| {
+                (
+                    file.relative,
+                    file.immutable,
+                    file.encoding.content_encoding,
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            site,
+            [
+                ("assets/index-BkG3x9aZ.js", true, Some("gzip")),
+                ("assets/index-BkG3x9aZ.js.br", true, Some("br")),
+                ("favicon.ico", false, None),
+                ("404.html", false, Some("gzip")),
+                ("about/index.html", false, Some("gzip")),
+                ("index.html", false, Some("gzip")),
+            ]
+            .map(|(relative, immutable, content_encoding)| {
+                (relative.to_string(), immutable, content_encoding)
+            })
+        );
+        let paths = ["app.js"].into_iter().collect();
+        assert_eq!(
+            encoding("app.js.gz", &args, &paths).content_type,
+            "text/javascript; charset=utf-8"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_live_prefix() {
+        assert_eq!(live_prefix("main", None), "main/frontend/");
+        assert_eq!(live_prefix("main", Some("/site/")), "site/");
+        assert_eq!(live_prefix("main", Some("/")), "");
+        assert_eq!(live_prefix("main", Some("")), "");
+    }
+
+    #[test]
+    fn test_release_namespaces_are_never_deleted() {
+        let stale = |keys: &[&str]| keys.iter().map(ToString::to_string).collect_vec();
+        assert_eq!(
+            release_namespace_key(&stale(&["main/old.html", "main/owner.json"])),
+            Some("main/owner.json")
+        );
+        assert_eq!(
+            release_namespace_key(&stale(&["main/x86_64-pc-windows-msvc/release-notes.json"])),
+            Some("main/x86_64-pc-windows-msvc/release-notes.json")
+        );
+        assert_eq!(
+            release_namespace_key(&stale(&["site/old.html", "site/assets/old.js"])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rollback_target() -> Result<()> {
+        let deploy = |id: &str| FrontendDeploy {
+            id: id.to_string(),
+            commit: "abcd".to_string(),
+            deployed_at: "2024-06-01T12:00:00Z".to_string(),
+        };
+        let deploys = FrontendDeploys {
+            deploys: vec![deploy("a"), deploy("b"), deploy("c")],
+        };
+        assert_eq!(rollback_target(&deploys, None)?, "b");
+        assert_eq!(rollback_target(&deploys, Some("a"))?, "a");
+        let single = FrontendDeploys {
+            deploys: vec![deploy("a")],
+        };
+        assert!(rollback_target(&single, None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,713 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::release_notes_file::{
+    StagedArtifact,
+    StagedRelease,
+};
+
+/// both are set by github actions for every step
+pub const OUTPUT_ENV: &str = "GITHUB_OUTPUT";
+pub const STEP_SUMMARY_ENV: &str = "GITHUB_STEP_SUMMARY";
+
+/// needs the `deployments: write` permission for `--github-deployment`
+pub const TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// `owner/repo`, set by github actions
+pub const REPOSITORY_ENV: &str = "GITHUB_REPOSITORY";
+
+/// token allowed to push to the fork and open pull requests against the package repositories (winget-pkgs and the like), GITHUB_TOKEN is used when unset
+pub const PACKAGES_TOKEN_ENV: &str = "DEPLOY_PACKAGES_GITHUB_TOKEN";
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// somewhere else the published release is copied to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, IntoEnumIterator)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mirror {
+    /// a github release for the `v{version}` tag, with the artifacts attached
+    GithubRelease,
+}
+
+impl FromStr for Mirror {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        matched_variant!(Self, s)
+    }
+}
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct GithubArgs {
+    /// record the publish as a deployment of the commit to the branch's environment, shown in the repo's deployments tab (needs GITHUB_TOKEN and GITHUB_REPOSITORY)
+    #[clap(long)]
+    pub github_deployment: bool,
+    /// copy the release here once it's live, repeatable (github-release)
+    #[clap(long, value_name = "MIRROR")]
+    pub mirror: Vec<Mirror>,
+}
+
+/// the file the variable points to, `None` outside of github actions
+fn actions_file(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+fn append(path: &Path, content: &str) -> Result<()> {
+    use std::io::Write;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .wrap_err_with(|| format!("appending to [{}]", path.display()))
+}
+
+/// `name=value`, multiline values in a heredoc with a delimiter that can't appear in them
+fn output_lines(outputs: &[(&str, String)]) -> String {
+    outputs
+        .iter()
+        .map(|(name, value)| match value.contains('\n') {
+            false => format!("{name}={value}\n"),
+            true => {
+                let delimiter = format!("ghadelimiter_{}", uuid::Uuid::new_v4());
+                format!("{name}<<{delimiter}\n{value}\n{delimiter}\n")
+            }
+        })
+        .join("")
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes < 1024 * 1024 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        bytes => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn artifacts_table(artifacts: &[StagedArtifact]) -> String {
+    let rows = artifacts
+        .iter()
+        .map(|artifact| {
+            let file_name = artifact.key.rsplit('/').next().unwrap_or(&artifact.key);
+            let kind = artifact
+                .kind
+                .map(|kind| {
+                    serde_variant::to_variant_name(&kind).expect("this will always serialize")
+                })
+                .unwrap_or("-");
+            format!(
+                "| [{}]({}) | {kind} | {} | `{}` |\n",
+                file_name.replace('|', "\\|"),
+                artifact.url,
+                size(artifact.size),
+                artifact.sha256
+            )
+        })
+        .join("");
+    format!("| file | kind | size | sha256 |\n| --- | --- | --- | --- |\n{rows}")
+}
+
+/// `version` and `artifact_urls` (a json array) for later steps, the table of uploaded artifacts for the job summary
+pub fn report_staged(deployment: &Deployment<'_>, staged: &StagedRelease) -> Result<()> {
+    let urls = staged
+        .artifacts
+        .iter()
+        .map(|artifact| &artifact.url)
+        .collect_vec();
+    if let Some(path) = actions_file(OUTPUT_ENV) {
+        append(
+            &path,
+            &output_lines(&[
+                ("version", staged.version.clone()),
+                (
+                    "artifact_urls",
+                    serde_json::to_string(&urls).wrap_err("serializing artifact urls")?,
+                ),
+            ]),
+        )?;
+    }
+    if let Some(path) = actions_file(STEP_SUMMARY_ENV) {
+        append(
+            &path,
+            &format!(
+                "### {} {} ({}, {})\n\nstaged, not live yet\n\n{}\n",
+                deployment.tauri_conf_json.product_name(),
+                staged.version,
+                deployment.branch,
+                serde_variant::to_variant_name(deployment.target)
+                    .expect("this will always serialize"),
+                artifacts_table(&staged.artifacts)
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+/// `version` and `manifest_url` for later steps, a line saying it's live for the job summary
+pub fn report_published(deployment: &Deployment<'_>, staged: &StagedRelease) -> Result<()> {
+    let manifest_url = deployment.release_file_url();
+    if let Some(path) = actions_file(OUTPUT_ENV) {
+        append(
+            &path,
+            &output_lines(&[
+                ("version", staged.version.clone()),
+                ("manifest_url", manifest_url.clone()),
+            ]),
+        )?;
+    }
+    if let Some(path) = actions_file(STEP_SUMMARY_ENV) {
+        append(
+            &path,
+            &format!(
+                "**{} {} is live** on {}, manifest: {manifest_url}\n\n",
+                deployment.tauri_conf_json.product_name(),
+                staged.version,
+                deployment.branch
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+/// authenticated client for the rest api of the repository the workflow runs in
+struct Api {
+    base: String,
+    repository: String,
+    token: String,
+}
+
+impl Api {
+    fn from_env() -> Result<Self> {
+        Self::for_repository(&env_var(REPOSITORY_ENV)?, env_var(TOKEN_ENV)?)
+    }
+
+    fn for_repository(repository: &str, token: String) -> Result<Self> {
+        Ok(Self {
+            base: std::env::var("GITHUB_API_URL")
+                .unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            repository: repository.to_string(),
+            token,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/repos/{}{path}", self.base, self.repository)
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
+            .request(method, url)
+            .bearer_auth(&self.token)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header(reqwest::header::USER_AGENT, "tauri-static-deployer")
+            .header("x-github-api-version", "2022-11-28")
+    }
+
+    /// the parsed response, `None` for 404
+    async fn send(request: reqwest::RequestBuilder) -> Result<Option<serde_json::Value>> {
+        let response = request.send().await.wrap_err("calling the github api")?;
+        let status = response.status();
+        let content = response.text().await.unwrap_or_default();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            bail!(
+                "github api failed, Got HTTP {} :: {content}",
+                status.as_u16()
+            );
+        }
+        serde_json::from_str(&content)
+            .map(Some)
+            .wrap_err("parsing github api response")
+    }
+
+    /// the parsed response, `None` for 404
+    async fn call(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<(&str, &[u8])>,
+    ) -> Result<Option<serde_json::Value>> {
+        storage::retry(&format!("{method} {url}"), || async {
+            let request = self.request(method.clone(), url);
+            let request = match body {
+                Some((content_type, body)) => request
+                    .header(reqwest::header::CONTENT_TYPE, content_type)
+                    .body(body.to_vec()),
+                None => request,
+            };
+            Self::send(request).await
+        })
+        .await
+    }
+
+    /// streams the file as the request body, it's reopened for every attempt so a retry sends it from the start
+    async fn upload(
+        &self,
+        url: &str,
+        content_type: &str,
+        path: &Path,
+    ) -> Result<Option<serde_json::Value>> {
+        storage::retry(&format!("POST {url}"), || async {
+            let file = tokio::fs::File::open(path)
+                .await
+                .wrap_err_with(|| format!("opening [{}]", path.display()))?;
+            let size = file
+                .metadata()
+                .await
+                .wrap_err_with(|| format!("reading metadata of [{}]", path.display()))?
+                .len();
+            let request = self
+                .request(reqwest::Method::POST, url)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .header(reqwest::header::CONTENT_LENGTH, size)
+                .body(reqwest::Body::from(file));
+            Self::send(request).await
+        })
+        .await
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<serde_json::Value>> {
+        self.call(reqwest::Method::GET, &self.url(path), None).await
+    }
+
+    /// `path` is relative to the repository, e.g. `/deployments`
+    async fn post(&self, path: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        self.send_json(reqwest::Method::POST, path, body).await
+    }
+
+    async fn patch(&self, path: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        self.send_json(reqwest::Method::PATCH, path, body).await
+    }
+
+    async fn send_json(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let url = self.url(path);
+        self.call(
+            method,
+            &url,
+            Some(("application/json", body.to_string().as_bytes())),
+        )
+        .await?
+        .ok_or_else(|| eyre::eyre!("[{url}] not found"))
+    }
+}
+
+fn env_var(name: &str) -> Result<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| eyre::eyre!("{name} is not set"))
+}
+
+/// commits the files (path, content) to a branch of the fork - reset to the upstream head when it exists already - and opens a pull request of it against the upstream repository unless one is open, returns the pull request url
+pub async fn open_pull_request(
+    upstream: &str,
+    fork: &str,
+    branch: &str,
+    files: &[(String, String)],
+    title: &str,
+    body: &str,
+) -> Result<String> {
+    let token = env_var(PACKAGES_TOKEN_ENV).or_else(|_| env_var(TOKEN_ENV))?;
+    let upstream_api = Api::for_repository(upstream, token.clone())?;
+    let fork_api = Api::for_repository(fork, token)?;
+    let repository = upstream_api
+        .get("")
+        .await?
+        .ok_or_else(|| eyre::eyre!("no repository [{upstream}]"))?;
+    let base = repository["default_branch"]
+        .as_str()
+        .ok_or_else(|| eyre::eyre!("[{upstream}] has no default branch"))?;
+    let head = upstream_api
+        .get(&format!("/git/ref/heads/{base}"))
+        .await?
+        .and_then(|reference| reference["object"]["sha"].as_str().map(str::to_string))
+        .ok_or_else(|| eyre::eyre!("no [{base}] branch in [{upstream}]"))?;
+    // a rerun of a failed job finds the branch of the previous attempt, it starts over from the upstream head
+    match fork_api.get(&format!("/git/ref/heads/{branch}")).await? {
+        Some(_) => fork_api
+            .patch(
+                &format!("/git/refs/heads/{branch}"),
+                &serde_json::json!({ "sha": head, "force": true }),
+            )
+            .await
+            .wrap_err_with(|| format!("resetting branch [{branch}] in [{fork}]"))?,
+        None => fork_api
+            .post(
+                "/git/refs",
+                &serde_json::json!({ "ref": format!("refs/heads/{branch}"), "sha": head }),
+            )
+            .await
+            .wrap_err_with(|| format!("creating branch [{branch}] in [{fork}]"))?,
+    };
+    for (path, content) in files {
+        // replacing a file the branch has already needs the sha of its blob
+        let existing = fork_api
+            .get(&format!("/contents/{path}?ref={branch}"))
+            .await?
+            .and_then(|file| file["sha"].as_str().map(str::to_string));
+        let mut commit = serde_json::json!({
+            "message": title,
+            "content": data_encoding::BASE64.encode(content.as_bytes()),
+            "branch": branch,
+        });
+        if let Some(sha) = existing {
+            commit["sha"] = sha.into();
+        }
+        fork_api
+            .send_json(reqwest::Method::PUT, &format!("/contents/{path}"), &commit)
+            .await
+            .wrap_err_with(|| format!("committing [{path}] to [{fork}]"))?;
+    }
+    let fork_owner = fork.split('/').next().unwrap_or(fork);
+    let open = upstream_api
+        .get(&format!("/pulls?state=open&head={fork_owner}:{branch}"))
+        .await?
+        .unwrap_or_default();
+    if let Some(url) = open
+        .as_array()
+        .and_then(|pulls| pulls.first())
+        .and_then(|pull| pull["html_url"].as_str())
+    {
+        info!("github :: the pull request of [{branch}] is open already");
+        return Ok(url.to_string());
+    }
+    let pull_request = upstream_api
+        .post(
+            "/pulls",
+            &serde_json::json!({
+                "title": title,
+                "body": body,
+                "head": format!("{fork_owner}:{branch}"),
+                "base": base,
+            }),
+        )
+        .await
+        .wrap_err_with(|| format!("opening a pull request against [{upstream}]"))?;
+    Ok(pull_request["html_url"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// the full sha, github doesn't resolve the abbreviated one the deployment keys use
+fn commit_sha(deployment: &Deployment<'_>) -> Result<String> {
+    if let Some(sha) = ci::commit().filter(|sha| sha.starts_with(deployment.git_hash)) {
+        return Ok(sha);
+    }
+    metadata::git(&["rev-parse", &format!("{}^{{commit}}", deployment.git_hash)])
+        .wrap_err_with(|| format!("resolving the full sha of commit {}", deployment.git_hash))
+}
+
+/// link from the deployment status to the workflow run, when running in one
+fn run_url() -> Option<String> {
+    let var = |name| std::env::var(name).ok();
+    Some(format!(
+        "{}/{}/actions/runs/{}",
+        var("GITHUB_SERVER_URL")?,
+        var(REPOSITORY_ENV)?,
+        var("GITHUB_RUN_ID")?
+    ))
+}
+
+/// a deployment created for the publish, its status is set once the manifests are live (or not)
+pub struct GithubDeployment {
+    api: Api,
+    id: u64,
+    environment_url: String,
+}
+
+impl GithubDeployment {
+    /// `None` unless `--github-deployment` is given
+    pub async fn start(
+        deployment: &Deployment<'_>,
+        staged: &StagedRelease,
+        args: &GithubArgs,
+    ) -> Result<Option<Self>> {
+        if !args.github_deployment {
+            return Ok(None);
+        }
+        let api = Api::from_env().wrap_err("--github-deployment")?;
+        let created = api
+            .post(
+                "/deployments",
+                &serde_json::json!({
+                    "ref": commit_sha(deployment)?,
+                    "environment": deployment.branch,
+                    "description": format!("{} {}", deployment.tauri_conf_json.product_name(), staged.version),
+                    "auto_merge": false,
+                    "required_contexts": [],
+                    "payload": {
+                        "version": staged.version,
+                        "target": deployment.target,
+                    },
+                }),
+            )
+            .await
+            .wrap_err("creating github deployment")?;
+        let id = created["id"]
+            .as_u64()
+            .ok_or_else(|| eyre::eyre!("github deployment without an id: {created}"))?;
+        let environment_url = match staged.downloads.is_empty() {
+            true => deployment.release_file_url(),
+            false => namespacing::s3_key_url(
+                deployment.s3_config,
+                &namespacing::derive_downloads_page_s3_key(deployment.branch),
+            ),
+        };
+        let github_deployment = Self {
+            api,
+            id,
+            environment_url,
+        };
+        github_deployment.set_status("in_progress").await?;
+        info!(
+            "github :: deployment {id} to [{}] started",
+            deployment.branch
+        );
+        Ok(Some(github_deployment))
+    }
+
+    /// `success`, `failure`, `in_progress`, ...
+    pub async fn set_status(&self, state: &str) -> Result<()> {
+        let mut status = serde_json::json!({
+            "state": state,
+            "environment_url": self.environment_url,
+            "auto_inactive": true,
+        });
+        if let Some(log_url) = run_url() {
+            status["log_url"] = log_url.into();
+        }
+        self.api
+            .post(&format!("/deployments/{}/statuses", self.id), &status)
+            .await
+            .wrap_err_with(|| format!("setting github deployment {} to {state}", self.id))?;
+        Ok(())
+    }
+}
+
+/// at most this many commits are looked up, one request each
+const MAX_COMMITS_LOOKED_UP: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+}
+
+/// the merged pull requests the commits came from, oldest (lowest number) first
+pub async fn merged_pull_requests(shas: &[String]) -> Result<Vec<PullRequest>> {
+    let api = Api::from_env()?;
+    if shas.len() > MAX_COMMITS_LOOKED_UP {
+        warn!(
+            "github :: {} commits, only the pull requests of the newest {MAX_COMMITS_LOOKED_UP} are looked up",
+            shas.len()
+        );
+    }
+    let mut pull_requests = std::collections::BTreeMap::new();
+    for sha in shas.iter().take(MAX_COMMITS_LOOKED_UP) {
+        let pulls = api
+            .get(&format!("/commits/{sha}/pulls"))
+            .await
+            .wrap_err_with(|| format!("looking up the pull requests of {sha}"))?
+            .unwrap_or_default();
+        for pull in pulls.as_array().into_iter().flatten() {
+            if pull["merged_at"].is_null() {
+                continue;
+            }
+            let (Some(number), Some(title), Some(url)) = (
+                pull["number"].as_u64(),
+                pull["title"].as_str(),
+                pull["html_url"].as_str(),
+            ) else {
+                continue;
+            };
+            pull_requests.insert(
+                number,
+                PullRequest {
+                    number,
+                    title: title.trim().to_string(),
+                    url: url.to_string(),
+                },
+            );
+        }
+    }
+    Ok(pull_requests.into_values().collect())
+}
+
+/// `upload_url` of a release is a uri template, `https://uploads.github.com/.../assets{?name,label}`
+fn asset_upload_url(upload_url: &str, name: &str) -> String {
+    format!(
+        "{}?name={}",
+        upload_url.split('{').next().unwrap_or(upload_url),
+        percent_encoding::utf8_percent_encode(name, percent_encoding::NON_ALPHANUMERIC)
+    )
+}
+
+/// the github release of the `v{version}` tag, created or with its notes updated
+async fn upsert_release(
+    api: &Api,
+    deployment: &Deployment<'_>,
+    staged: &StagedRelease,
+    notes: &str,
+) -> Result<serde_json::Value> {
+    let tag = format!("v{}", staged.version);
+    let prerelease = semver::Version::parse(&staged.version)
+        .map(|version| !version.pre.is_empty())
+        .unwrap_or(false);
+    match api.get(&format!("/releases/tags/{tag}")).await? {
+        Some(release) => {
+            let id = release["id"]
+                .as_u64()
+                .ok_or_else(|| eyre::eyre!("github release without an id: {release}"))?;
+            api.patch(&format!("/releases/{id}"), &serde_json::json!({ "body": notes }))
+                .await
+        }
+        None => {
+            api.post(
+                "/releases",
+                &serde_json::json!({
+                    "tag_name": tag,
+                    "target_commitish": commit_sha(deployment)?,
+                    "name": format!("{} {}", deployment.tauri_conf_json.product_name(), staged.version),
+                    "body": notes,
+                    "prerelease": prerelease,
+                }),
+            )
+            .await
+        }
+    }
+}
+
+/// attaches the staged artifacts (downloaded through the cache) to the github release, assets it has already are kept
+async fn mirror_github_release(
+    deployment: &Deployment<'_>,
+    staged: &StagedRelease,
+    notes: &str,
+) -> Result<()> {
+    let api = Api::from_env()?;
+    let release = upsert_release(&api, deployment, staged, notes).await?;
+    let upload_url = release["upload_url"]
+        .as_str()
+        .ok_or_else(|| eyre::eyre!("github release without an upload url: {release}"))?;
+    let existing = release["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| Some((asset["name"].as_str()?.to_string(), asset["size"].as_u64()?)))
+        .collect::<std::collections::HashMap<_, _>>();
+    let mut attached = 0;
+    for artifact in &staged.artifacts {
+        let name = artifact.key.rsplit('/').next().unwrap_or(&artifact.key);
+        match existing.get(name) {
+            Some(&size) if size == artifact.size => {
+                debug!("github :: [{name}] is attached already");
+                continue;
+            }
+            Some(_) => {
+                warn!("github :: a different [{name}] is attached already, keeping it");
+                continue;
+            }
+            None => {}
+        }
+        let path = std::env::temp_dir().join(format!("mirror-{}", uuid::Uuid::new_v4()));
+        let uploaded = async {
+            cache::download_to(&artifact.url, &path).await?;
+            api.upload(
+                &asset_upload_url(upload_url, name),
+                storage::content_type_for(name),
+                &path,
+            )
+            .await
+        }
+        .await;
+        tokio::fs::remove_file(&path).await.ok();
+        uploaded.wrap_err_with(|| format!("attaching [{name}]"))?;
+        attached += 1;
+    }
+    info!(
+        "github :: attached {attached} artifacts to release v{}",
+        staged.version
+    );
+    Ok(())
+}
+
+/// copies the live release to the `--mirror`s
+pub async fn mirror(
+    deployment: &Deployment<'_>,
+    staged: &StagedRelease,
+    notes: &str,
+    args: &GithubArgs,
+) -> Result<()> {
+    for mirror in &args.mirror {
+        match mirror {
+            Mirror::GithubRelease => mirror_github_release(deployment, staged, notes)
+                .await
+                .wrap_err("mirroring to a github release")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_lines() {
+        let lines = output_lines(&[
+            ("version", "1.2.3".to_string()),
+            ("notes", "first\nsecond".to_string()),
+        ]);
+        let mut lines = lines.lines();
+        assert_eq!(lines.next(), Some("version=1.2.3"));
+        let delimiter = lines
+            .next()
+            .and_then(|line| line.strip_prefix("notes<<"))
+            .expect("multiline values use a heredoc");
+        assert_eq!(lines.collect_vec(), vec!["first", "second", delimiter]);
+    }
+
+    #[test]
+    fn test_asset_upload_url() -> Result<()> {
+        assert_eq!(
+            asset_upload_url(
+                "https://uploads.github.com/repos/o/r/releases/1/assets{?name,label}",
+                "My App_1.0.0_x64.msi"
+            ),
+            "https://uploads.github.com/repos/o/r/releases/1/assets?name=My%20App%5F1%2E0%2E0%5Fx64%2Emsi"
+        );
+        assert_eq!("github-release".parse::<Mirror>()?, Mirror::GithubRelease);
+        Ok(())
+    }
+
+    #[test]
+    fn test_artifacts_table() {
+        let artifacts = [StagedArtifact {
+            key: "main/x86_64-pc-windows-msvc/1.0.0/abcd1234/app.msi.zip".to_string(),
+            url: "https://cdn.example.com/app.msi.zip".to_string(),
+            sha256: "ab".repeat(32),
+            size: 3 * 1024 * 1024,
+            kind: Some(artifacts::ArtifactKind::UpdaterArchive),
+        }];
+        assert_eq!(
+            artifacts_table(&artifacts).lines().nth(2),
+            Some(
+                format!(
+                    "| [app.msi.zip](https://cdn.example.com/app.msi.zip) | updater_archive | 3.0 MiB | `{}` |",
+                    "ab".repeat(32)
+                )
+                .as_str()
+            )
+        );
+    }
+}
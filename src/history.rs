@@ -0,0 +1,707 @@
+use super::*;
+use crate::deploy::Deployment;
+
+/// label marking a version that must not be installed anymore
+pub const YANKED_LABEL: &str = "yanked";
+
+/// how many times the history record update is redone when another job changed it in the meantime
+const RECORD_UPDATE_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignOffEntry {
+    pub version: String,
+    /// e.g. `qa`, `security`, `product`
+    pub role: String,
+    pub by: String,
+    /// RFC3339
+    pub signed_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignOff {
+    pub entry: SignOffEntry,
+    /// base64 ed25519 signature of the json serialized `entry`, made with the receipt signing key
+    pub signature: Option<String>,
+    pub public_key: Option<String>,
+}
+
+/// label attached to a published version, e.g. `known-issue: crash on HiDPI` or `yanked`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Label {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+    /// RFC3339
+    pub added_at: String,
+}
+
+/// everything that happened to a version of a branch, shared by all its targets
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VersionRecord {
+    pub version: String,
+    #[serde(default)]
+    pub sign_offs: Vec<SignOff>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+}
+
+impl VersionRecord {
+    /// roles of the sign-offs that count - when a public key is configured only correctly signed ones do
+    fn signed_off_roles(
+        &self,
+        public_key: Option<&ed25519_dalek::VerifyingKey>,
+    ) -> std::collections::BTreeSet<&str> {
+        self.sign_offs
+            .iter()
+            .filter(|sign_off| sign_off.entry.version == self.version)
+            .filter(|sign_off| match (public_key, &sign_off.signature) {
+                (None, _) => true,
+                (Some(public_key), Some(signature)) => {
+                    receipt::check_json_signature(&sign_off.entry, signature, public_key).is_ok()
+                }
+                (Some(_), None) => false,
+            })
+            .map(|sign_off| sign_off.entry.role.as_str())
+            .collect()
+    }
+
+    pub fn is_yanked(&self) -> bool {
+        self.labels.iter().any(|label| label.label == YANKED_LABEL)
+    }
+
+    /// adds the labels not attached yet and removes the given ones
+    fn annotated(&mut self, add: &[String], remove: &[String], by: Option<&str>, now: &str) {
+        self.labels.retain(|label| !remove.contains(&label.label));
+        for label in add {
+            if !self.labels.iter().any(|existing| &existing.label == label) {
+                self.labels.push(Label {
+                    label: label.clone(),
+                    by: by.map(ToString::to_string),
+                    added_at: now.to_string(),
+                });
+            }
+        }
+    }
+
+    fn missing_sign_offs<'a>(
+        &self,
+        required: &'a [String],
+        public_key: Option<&ed25519_dalek::VerifyingKey>,
+    ) -> Vec<&'a str> {
+        let signed_off = self.signed_off_roles(public_key);
+        required
+            .iter()
+            .map(String::as_str)
+            .filter(|role| !signed_off.contains(role))
+            .collect()
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SignOffArgs {
+    /// what the sign-off vouches for, e.g. `qa`
+    #[clap(long, value_name = "ROLE")]
+    pub role: String,
+    /// who signs off
+    #[clap(long, value_name = "NAME")]
+    pub by: String,
+    /// defaults to the version in the tauri config
+    #[clap(long, value_name = "VERSION")]
+    pub version: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct AnnotateArgs {
+    /// defaults to the version in the tauri config
+    #[clap(long, value_name = "VERSION")]
+    pub version: Option<String>,
+    /// label to attach, e.g. `--label "known-issue: crash on HiDPI"`, can be repeated
+    #[clap(long, value_name = "LABEL")]
+    pub label: Vec<String>,
+    /// label to detach, can be repeated
+    #[clap(long, value_name = "LABEL")]
+    pub remove: Vec<String>,
+    /// who annotates
+    #[clap(long, value_name = "NAME")]
+    pub by: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct YankArgs {
+    #[clap(long, value_name = "VERSION")]
+    pub version: String,
+    /// who yanks
+    #[clap(long, value_name = "NAME")]
+    pub by: Option<String>,
+    #[clap(flatten)]
+    pub lock: lock::LockArgs,
+    #[clap(flatten)]
+    pub cdn: cdn::CdnArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct UnpublishArgs {
+    /// put this (staged, not yanked) version live instead of the newest earlier one that isn't yanked
+    #[clap(long, value_name = "VERSION")]
+    pub to: Option<String>,
+    /// delete the manifests instead of rolling them back, the updater is offered nothing until the next publish
+    #[clap(long, conflicts_with = "to")]
+    pub delete: bool,
+    /// who unpublishes
+    #[clap(long, value_name = "NAME")]
+    pub by: Option<String>,
+    #[clap(flatten)]
+    pub lock: lock::LockArgs,
+    #[clap(flatten)]
+    pub cdn: cdn::CdnArgs,
+}
+
+/// the newest version older than `yanked` that is not yanked itself
+fn rollback_target<'a>(
+    staged: &'a [semver::Version],
+    yanked: &semver::Version,
+    is_yanked: impl Fn(&semver::Version) -> bool,
+) -> Option<&'a semver::Version> {
+    staged
+        .iter()
+        .filter(|version| *version < yanked && !is_yanked(version))
+        .max()
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatusArgs {
+    /// what was live at this moment instead of now, RFC3339 with or without seconds (`2024-03-01T12:00Z`)
+    #[clap(long, value_name = "TIME")]
+    pub at: Option<String>,
+}
+
+fn parse_moment(val: &str) -> Result<time::OffsetDateTime> {
+    use time::format_description::well_known::Rfc3339;
+    time::OffsetDateTime::parse(val, &Rfc3339)
+        .or_else(|_| {
+            // `2024-03-01T12:00Z` or `2024-03-01T12:00-05:00` - support tends to leave the seconds out. the offset
+            // starts after the time, the date has dashes too
+            let time_start = val.find(['T', 't']).map_or(val.len(), |t| t + 1);
+            let offset_start = val[time_start..]
+                .find(['Z', 'z', '+', '-'])
+                .map_or(val.len(), |offset| time_start + offset);
+            let (minutes, offset) = val.split_at(offset_start);
+            time::OffsetDateTime::parse(&format!("{minutes}:00{offset}"), &Rfc3339)
+        })
+        .wrap_err_with(|| format!("[{val}] is not an RFC3339 date, e.g. 2024-03-01T12:00Z"))
+}
+
+/// the receipt of the last publish of the manifest at or before `at`, `None` when the manifest was deleted since
+fn live_at<'a>(
+    receipts: &'a [receipt::SignedReceipt],
+    manifest_key: &str,
+    at: time::OffsetDateTime,
+) -> Option<&'a receipt::Receipt> {
+    receipts
+        .iter()
+        .map(|signed| &signed.receipt)
+        .filter(|receipt| receipt.manifest_key == manifest_key)
+        .filter_map(|receipt| Some((parse_moment(&receipt.published_at).ok()?, receipt)))
+        .filter(|(published_at, _)| *published_at <= at)
+        .max_by_key(|(published_at, _)| *published_at)
+        .map(|(_, receipt)| receipt)
+        .filter(|receipt| !receipt.deleted)
+}
+
+/// what the updater of the target was served at the given moment (now by default), according to the deploy receipts
+pub async fn status(deployment: &Deployment<'_>, args: &StatusArgs) -> Result<()> {
+    let at = match &args.at {
+        Some(at) => parse_moment(at)?,
+        None => time::OffsetDateTime::now_utc(),
+    };
+    let manifest_key = deployment.release_file_key();
+    let receipts = receipt::list(deployment).await?;
+    let receipt = live_at(&receipts, &manifest_key, at).ok_or_else(|| {
+        eyre::eyre!(
+            "nothing was served from [{manifest_key}] at {at} - not published by then or deleted by `unpublish --delete` (out of {} receipts)",
+            receipts.len()
+        )
+    })?;
+    let labels = fetch_record(deployment, &receipt.version).await?.labels;
+    println!(
+        "{manifest_key} at {at}\n  version      {}\n  commit       {}\n  published at {}\n  deploy       {}{}",
+        receipt.version,
+        receipt.commit,
+        receipt.published_at,
+        receipt.deploy_id,
+        labels
+            .iter()
+            .map(|label| format!("\n  label        {}", label.label))
+            .join("")
+    );
+    match &receipt.manifest {
+        Some(manifest) => println!("{manifest}"),
+        None => warn!(
+            "receipt {} predates manifest archiving, only its sha256 is known: {}",
+            receipt.deploy_id, receipt.manifest_sha256
+        ),
+    }
+    Ok(())
+}
+
+fn now_rfc3339() -> Result<String> {
+    time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .wrap_err("formatting current date")
+}
+
+pub async fn fetch_record(deployment: &Deployment<'_>, version: &str) -> Result<VersionRecord> {
+    let key = namespacing::derive_version_record_s3_key(deployment.branch, version);
+    match storage::get_bytes(deployment.s3_config, &key)
+        .await
+        .wrap_err_with(|| format!("reading history record [{key}]"))?
+    {
+        Some(content) => serde_json::from_slice(&content)
+            .wrap_err_with(|| format!("parsing history record [{key}]")),
+        None => Ok(VersionRecord {
+            version: version.to_string(),
+            ..Default::default()
+        }),
+    }
+}
+
+/// read-modify-write of the history record of a version, safe against other jobs updating it at the same time
+pub async fn update_record<F>(
+    deployment: &Deployment<'_>,
+    version: &str,
+    mut modify: F,
+) -> Result<VersionRecord>
+where
+    F: FnMut(&mut VersionRecord) -> Result<()>,
+{
+    let key = namespacing::derive_version_record_s3_key(deployment.branch, version);
+    let content = storage::update_if_match(
+        deployment.s3_config,
+        deployment.fallback_endpoints,
+        &key,
+        "application/json",
+        RECORD_UPDATE_ATTEMPTS,
+        |current| {
+            let mut record = match current {
+                Some(current) => serde_json::from_slice::<VersionRecord>(current)
+                    .wrap_err_with(|| format!("parsing history record [{key}]"))?,
+                None => VersionRecord {
+                    version: version.to_string(),
+                    ..Default::default()
+                },
+            };
+            modify(&mut record)?;
+            serde_json::to_vec_pretty(&record).wrap_err("serializing history record")
+        },
+    )
+    .await?;
+    serde_json::from_slice(&content).wrap_err("parsing history record")
+}
+
+pub async fn sign_off(deployment: &Deployment<'_>, args: &SignOffArgs) -> Result<()> {
+    let version = args
+        .version
+        .clone()
+        .unwrap_or_else(|| deployment.tauri_conf_json.version().to_string());
+    let entry = SignOffEntry {
+        version: version.clone(),
+        role: args.role.clone(),
+        by: args.by.clone(),
+        signed_at: now_rfc3339()?,
+    };
+    let (signature, public_key) = match receipt::signing_key_from_env()? {
+        Some(signing_key) => {
+            let (signature, public_key) = receipt::sign_json(&entry, &signing_key)?;
+            (Some(signature), Some(public_key))
+        }
+        None => {
+            warn!(
+                "{} is not set, the sign-off will not be signed",
+                receipt::SIGNING_KEY_ENV
+            );
+            (None, None)
+        }
+    };
+    let sign_off = SignOff {
+        entry,
+        signature,
+        public_key,
+    };
+    let record = update_record(deployment, &version, |record| {
+        record.sign_offs.push(sign_off.clone());
+        Ok(())
+    })
+    .await?;
+    info!(
+        "sign-off :: {} signed off {version} [{}] as {} ({} sign-offs so far)",
+        args.by,
+        deployment.branch,
+        args.role,
+        record.sign_offs.len()
+    );
+    Ok(())
+}
+
+/// attaches / detaches labels of a version and re-renders the downloads page showing them
+pub async fn annotate(deployment: &Deployment<'_>, args: &AnnotateArgs) -> Result<()> {
+    if args.label.is_empty() && args.remove.is_empty() {
+        bail!("nothing to do - pass --label and/or --remove");
+    }
+    let version = args
+        .version
+        .clone()
+        .unwrap_or_else(|| deployment.tauri_conf_json.version().to_string());
+    let now = now_rfc3339()?;
+    let record = update_record(deployment, &version, |record| {
+        record.annotated(&args.label, &args.remove, args.by.as_deref(), &now);
+        Ok(())
+    })
+    .await?;
+    info!(
+        "annotate :: {version} [{}] labels: {}",
+        deployment.branch,
+        record.labels.iter().map(|label| &label.label).join(", ")
+    );
+    downloads::render_page(deployment).await
+}
+
+/// marks the version as yanked without deleting anything, every target serving it is rolled back to its newest earlier version that isn't yanked
+pub async fn yank(deployment: &Deployment<'_>, args: &YankArgs) -> Result<()> {
+    let yanked = semver::Version::parse(&args.version)
+        .wrap_err_with(|| format!("[{}] is not valid semver", args.version))?;
+    let now = now_rfc3339()?;
+    update_record(deployment, &args.version, |record| {
+        record.annotated(&[YANKED_LABEL.to_string()], &[], args.by.as_deref(), &now);
+        Ok(())
+    })
+    .await?;
+    info!(
+        "yank :: {} [{}] marked as yanked",
+        args.version, deployment.branch
+    );
+    // the history record is shared by the targets of the branch, so is the yank
+    let targets = RustTarget::into_enum_iter().collect_vec();
+    let mut rollbacks = vec![];
+    for target in &targets {
+        let deployment = deployment.for_target(target);
+        if deployment.live_version().await?.as_deref() != Some(args.version.as_str())
+            || !deployment.staged_versions().await?.contains(&yanked)
+        {
+            continue;
+        }
+        let rollback = newest_good_before(&deployment, &yanked)
+            .await?
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "{} is live for {target:?} and there is no earlier non-yanked release to roll back to - it stays live everywhere, publish a fixed version",
+                    args.version
+                )
+            })?;
+        rollbacks.push((deployment, rollback));
+    }
+    if rollbacks.is_empty() {
+        info!(
+            "yank :: {} is not live for any target, the manifests are left alone",
+            args.version
+        );
+        return downloads::render_page(deployment).await;
+    }
+    for (deployment, rollback) in &rollbacks {
+        warn!(
+            "yank :: {} is live for {:?}, rolling back to {rollback}",
+            args.version, deployment.target
+        );
+        let release = deployment
+            .fetch_staged_version(&rollback.to_string())
+            .await?;
+        let localized_keys = deployment.republish(&release, &args.lock).await?;
+        cdn::purge(deployment, &localized_keys, &args.cdn).await?;
+        info!("yank :: {:?} rolled back to {rollback}", deployment.target);
+    }
+    downloads::render_page(deployment).await
+}
+
+/// the newest staged version older than the given one that is not yanked
+async fn newest_good_before(
+    deployment: &Deployment<'_>,
+    version: &semver::Version,
+) -> Result<Option<semver::Version>> {
+    let staged = deployment.staged_versions().await?;
+    let records = futures::future::try_join_all(
+        staged
+            .iter()
+            .map(|version| async move { fetch_record(deployment, &version.to_string()).await }),
+    )
+    .await?;
+    let is_yanked = |version: &semver::Version| {
+        records
+            .iter()
+            .any(|record| record.version == version.to_string() && record.is_yanked())
+    };
+    Ok(rollback_target(&staged, version, is_yanked).cloned())
+}
+
+/// kill switch - stops the live version from being offered right away, by rolling the manifests back to the last known good version or deleting them. the version is marked as yanked so it isn't published again by accident
+pub async fn unpublish(deployment: &Deployment<'_>, args: &UnpublishArgs) -> Result<()> {
+    let live = deployment.live_version().await?.ok_or_else(|| {
+        eyre::eyre!(
+            "nothing is live on [{}], there is nothing to unpublish",
+            deployment.release_file_key()
+        )
+    })?;
+    let purged = match (&args.to, args.delete) {
+        (_, true) => {
+            warn!("unpublish :: deleting the manifests serving {live}");
+            deployment.delete_manifests(&live).await?
+        }
+        (Some(to), false) => {
+            ensure_not_yanked(deployment, to).await?;
+            warn!("unpublish :: rolling {live} back to {to}");
+            let release = deployment.fetch_staged_version(to).await?;
+            deployment.republish(&release, &args.lock).await?
+        }
+        (None, false) => {
+            let live_version = semver::Version::parse(&live)
+                .wrap_err_with(|| format!("live version [{live}] is not valid semver"))?;
+            let target = newest_good_before(deployment, &live_version)
+                .await?
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "there is no earlier non-yanked release to roll {live} back to - pass --to VERSION or --delete"
+                    )
+                })?;
+            warn!("unpublish :: rolling {live} back to {target}");
+            let release = deployment.fetch_staged_version(&target.to_string()).await?;
+            deployment.republish(&release, &args.lock).await?
+        }
+    };
+    cdn::purge(deployment, &purged, &args.cdn).await?;
+    let now = now_rfc3339()?;
+    update_record(deployment, &live, |record| {
+        record.annotated(&[YANKED_LABEL.to_string()], &[], args.by.as_deref(), &now);
+        Ok(())
+    })
+    .await?;
+    info!(
+        "unpublish :: {live} [{}] is not offered anymore and marked as yanked",
+        deployment.branch
+    );
+    downloads::render_page(deployment).await
+}
+
+/// refuses to put a yanked version live again, it has to be un-yanked with `annotate --remove yanked` first
+pub async fn ensure_not_yanked(deployment: &Deployment<'_>, version: &str) -> Result<()> {
+    if fetch_record(deployment, version).await?.is_yanked() {
+        bail!(
+            "{version} [{}] is yanked - remove the label with `annotate --version {version} --remove {YANKED_LABEL}` to publish it again",
+            deployment.branch
+        );
+    }
+    Ok(())
+}
+
+/// labels of every given version that has any
+pub async fn labels(
+    deployment: &Deployment<'_>,
+    versions: impl IntoIterator<Item = &str>,
+) -> Result<std::collections::BTreeMap<String, Vec<String>>> {
+    let records = futures::future::try_join_all(
+        versions
+            .into_iter()
+            .unique()
+            .map(|version| fetch_record(deployment, version)),
+    )
+    .await?;
+    Ok(records
+        .into_iter()
+        .filter(|record| !record.labels.is_empty())
+        .map(|record| {
+            (
+                record.version,
+                record.labels.into_iter().map(|label| label.label).collect(),
+            )
+        })
+        .collect())
+}
+
+/// fails unless every required role has signed off the version
+pub async fn require_sign_offs(
+    deployment: &Deployment<'_>,
+    version: &str,
+    required: &[String],
+) -> Result<()> {
+    if required.is_empty() {
+        return Ok(());
+    }
+    let record = fetch_record(deployment, version).await?;
+    let public_key = receipt::public_key_from_env()?;
+    if public_key.is_none() {
+        warn!(
+            "{} is not set, sign-off signatures are NOT checked",
+            receipt::PUBLIC_KEY_ENV
+        );
+    }
+    let missing = record.missing_sign_offs(required, public_key.as_ref());
+    if !missing.is_empty() {
+        bail!(
+            "{version} [{}] is missing sign-offs by: {} - run `sign-off --role <ROLE> --by <NAME>` first",
+            deployment.branch,
+            missing.join(", ")
+        );
+    }
+    info!(
+        "sign-off :: {version} signed off by {}",
+        required.join(", ")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    fn sign_off(role: &str, signing_key: Option<&SigningKey>) -> Result<SignOff> {
+        let entry = SignOffEntry {
+            version: "1.7.9".to_string(),
+            role: role.to_string(),
+            by: "alice".to_string(),
+            signed_at: "2022-04-01T13:06:47Z".to_string(),
+        };
+        let (signature, public_key) = match signing_key {
+            Some(signing_key) => {
+                let (signature, public_key) = receipt::sign_json(&entry, signing_key)?;
+                (Some(signature), Some(public_key))
+            }
+            None => (None, None),
+        };
+        Ok(SignOff {
+            entry,
+            signature,
+            public_key,
+        })
+    }
+
+    #[test]
+    fn test_missing_sign_offs() -> Result<()> {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let record = VersionRecord {
+            version: "1.7.9".to_string(),
+            sign_offs: vec![
+                sign_off("qa", Some(&signing_key))?,
+                sign_off("security", None)?,
+            ],
+            labels: vec![],
+        };
+        let required = [
+            "qa".to_string(),
+            "security".to_string(),
+            "product".to_string(),
+        ];
+        assert_eq!(record.missing_sign_offs(&required, None), vec!["product"]);
+        assert_eq!(
+            record.missing_sign_offs(&required, Some(&signing_key.verifying_key())),
+            vec!["security", "product"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_adds_and_removes_labels() {
+        let mut record = VersionRecord::default();
+        let labels = |record: &VersionRecord| {
+            record
+                .labels
+                .iter()
+                .map(|label| label.label.clone())
+                .collect_vec()
+        };
+        record.annotated(
+            &[
+                "known-issue: crash on HiDPI".to_string(),
+                "yanked".to_string(),
+            ],
+            &[],
+            Some("alice"),
+            "2022-04-01T13:06:47Z",
+        );
+        record.annotated(&["yanked".to_string()], &[], None, "2022-04-02T13:06:47Z");
+        assert_eq!(
+            labels(&record),
+            vec!["known-issue: crash on HiDPI", "yanked"]
+        );
+        assert_eq!(record.labels[1].by.as_deref(), Some("alice"));
+        record.annotated(&[], &["known-issue: crash on HiDPI".to_string()], None, "");
+        assert_eq!(labels(&record), vec!["yanked"]);
+    }
+
+    #[test]
+    fn test_rollback_target_skips_yanked_and_newer_versions() {
+        let versions = ["1.7.7", "1.7.8", "1.7.9", "1.8.0"]
+            .map(|version| semver::Version::parse(version).unwrap());
+        let yanked = semver::Version::parse("1.7.9").unwrap();
+        assert_eq!(
+            rollback_target(&versions, &yanked, |version| version.to_string() == "1.7.8"),
+            Some(&versions[0])
+        );
+        assert_eq!(rollback_target(&versions[2..], &yanked, |_| false), None);
+    }
+
+    #[test]
+    fn test_unpublish_deletes_or_rolls_back() {
+        #[derive(clap::Parser, Debug)]
+        struct Cli {
+            #[clap(flatten)]
+            unpublish: UnpublishArgs,
+        }
+        use clap::Parser;
+        assert!(Cli::try_parse_from(["cli", "--to", "1.7.8"]).is_ok());
+        assert!(Cli::try_parse_from(["cli", "--delete"]).is_ok());
+        assert!(Cli::try_parse_from(["cli", "--delete", "--to", "1.7.8"]).is_err());
+    }
+
+    #[test]
+    fn test_live_at_picks_the_last_publish_before() -> Result<()> {
+        let receipt = |version: &str, published_at: &str, manifest_key: &str| {
+            let signed = serde_json::json!({
+                "receipt": {
+                    "deploy_id": version, "branch": "release", "target": "x86_64-pc-windows-msvc",
+                    "version": version, "commit": "deadbeef", "published_at": published_at,
+                    "manifest_key": manifest_key, "manifest_url": "", "manifest_sha256": "", "artifacts": {},
+                },
+                "signature": null, "public_key": null,
+            });
+            serde_json::from_value::<receipt::SignedReceipt>(signed)
+        };
+        let key = "release/release-notes.json";
+        let mut deleted = receipt("1.8.0", "2024-03-02T00:00:00Z", key)?;
+        deleted.receipt.deleted = true;
+        let receipts = [
+            deleted,
+            receipt("1.7.8", "2024-02-20T09:00:00Z", key)?,
+            receipt("1.7.9", "2024-03-01T11:59:00Z", key)?,
+            receipt(
+                "1.8.0-beta",
+                "2024-03-01T11:59:30Z",
+                "beta/release-notes.json",
+            )?,
+            receipt("1.8.0", "2024-03-01T12:01:00Z", key)?,
+        ];
+        let version = |at: &str| -> Result<Option<String>> {
+            Ok(live_at(&receipts, key, parse_moment(at)?).map(|receipt| receipt.version.clone()))
+        };
+        assert_eq!(version("2024-03-01T12:00Z")?.as_deref(), Some("1.7.9"));
+        assert_eq!(
+            version("2024-03-01T13:00:00+01:00")?.as_deref(),
+            Some("1.7.9")
+        );
+        assert_eq!(version("2024-03-01T07:00-05:00")?.as_deref(), Some("1.7.9"));
+        assert_eq!(version("2024-03-01T07:01-05:00")?.as_deref(), Some("1.8.0"));
+        assert_eq!(version("2024-03-01T13:01+01:00")?.as_deref(), Some("1.8.0"));
+        assert!(parse_moment("2024-03-01T12:00").is_err());
+        assert_eq!(version("2024-01-01T00:00Z")?, None);
+        assert_eq!(version("2024-03-01T23:59Z")?.as_deref(), Some("1.8.0"));
+        assert_eq!(version("2024-03-02T00:00Z")?, None);
+        Ok(())
+    }
+}
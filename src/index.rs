@@ -0,0 +1,119 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::release_notes_file::StagedRelease;
+
+/// how many times the append is redone when another job changed the index in the meantime
+const INDEX_UPDATE_ATTEMPTS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexedArtifact {
+    pub url: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// one upload of a version for a target
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub version: String,
+    pub target: RustTarget,
+    pub commit: String,
+    /// RFC3339
+    pub uploaded_at: String,
+    pub artifacts: Vec<IndexedArtifact>,
+}
+
+/// `releases-index.json` of a branch, every upload of every target in the order they happened - entries are only ever appended
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReleasesIndex {
+    #[serde(default)]
+    pub releases: Vec<IndexEntry>,
+}
+
+impl ReleasesIndex {
+    fn appended(mut self, entry: IndexEntry) -> Self {
+        self.releases.push(entry);
+        self
+    }
+}
+
+fn entry(target: &RustTarget, staged: &StagedRelease, uploaded_at: &str) -> IndexEntry {
+    IndexEntry {
+        version: staged.version.clone(),
+        target: target.clone(),
+        commit: staged.commit.clone(),
+        uploaded_at: uploaded_at.to_string(),
+        artifacts: staged
+            .artifacts
+            .iter()
+            .map(|artifact| IndexedArtifact {
+                url: artifact.url.clone(),
+                size: artifact.size,
+                sha256: artifact.sha256.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// records the upload in the index of the branch, safe against other targets appending at the same time
+pub async fn append(deployment: &Deployment<'_>, staged: &StagedRelease) -> Result<()> {
+    let key = namespacing::derive_releases_index_s3_key(deployment.branch);
+    let uploaded_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .wrap_err("formatting current date")?;
+    let entry = entry(deployment.target, staged, &uploaded_at);
+    let content = storage::update_if_match(
+        deployment.s3_config,
+        deployment.fallback_endpoints,
+        &key,
+        "application/json",
+        INDEX_UPDATE_ATTEMPTS,
+        |current| {
+            let index = match current {
+                Some(current) => serde_json::from_slice::<ReleasesIndex>(current)
+                    .wrap_err_with(|| format!("parsing releases index [{key}]"))?,
+                None => ReleasesIndex::default(),
+            };
+            serde_json::to_vec_pretty(&index.appended(entry.clone()))
+                .wrap_err("serializing releases index")
+        },
+    )
+    .await
+    .wrap_err("appending to the releases index")?;
+    let index: ReleasesIndex =
+        serde_json::from_slice(&content).wrap_err("parsing releases index")?;
+    info!("releases index :: [{key}] {} entries", index.releases.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_appended() -> Result<()> {
+        let staged: StagedRelease = serde_json::from_value(serde_json::json!({
+            "version": "1.2.3",
+            "commit": "abcd1234",
+            "platforms": {},
+            "artifacts": [{
+                "key": "main/win64/1.2.3/abcd1234/App.msi.zip",
+                "url": "https://cdn.example.com/main/win64/1.2.3/abcd1234/App.msi.zip",
+                "sha256": "00",
+                "size": 42,
+            }],
+        }))?;
+        let first = entry(&RustTarget::Win64, &staged, "2024-06-01T12:00:00Z");
+        let index = ReleasesIndex::default()
+            .appended(first.clone())
+            .appended(first.clone());
+        assert_eq!(index.releases.len(), 2);
+        assert_eq!(index.releases[0].artifacts[0].size, 42);
+        let json = serde_json::to_value(&index)?;
+        assert_eq!(
+            json["releases"][1]["target"],
+            serde_json::to_value(RustTarget::Win64)?
+        );
+        Ok(())
+    }
+}
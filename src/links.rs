@@ -0,0 +1,134 @@
+use super::*;
+use crate::deploy::Deployment;
+use futures::StreamExt;
+
+/// how many urls are requested at the same time
+const CONCURRENT_REQUESTS: usize = 8;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct LinkCheckArgs {
+    /// after publishing, request every url written to the manifests and the downloads page through the public domain and fail when any of them is broken
+    #[clap(long)]
+    pub check_links: bool,
+}
+
+/// every absolute url in a json, html or xml document, in order of appearance and without duplicates
+pub fn extract_urls(content: &str) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+    let mut rest = content;
+    while let Some(start) = ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        let candidate = &rest[start..];
+        let end = candidate
+            .find(|c: char| c.is_whitespace() || "\"'<>`\\".contains(c))
+            .unwrap_or(candidate.len());
+        let url = candidate[..end]
+            .trim_end_matches(|c| ".,;)".contains(c))
+            .replace("&amp;", "&");
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+        rest = &candidate[end..];
+    }
+    urls
+}
+
+async fn check_url(client: &reqwest::Client, url: &str) -> Result<()> {
+    let response = client.head(url).send().await.wrap_err("requesting")?;
+    // some CDNs only answer GET
+    let response = if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        client.get(url).send().await.wrap_err("requesting")?
+    } else {
+        response
+    };
+    response
+        .error_for_status()
+        .map(|_| ())
+        .wrap_err("bad response")
+}
+
+/// requests the documents the deployer publishes on the branch and every url inside them, all through the public domain
+pub async fn check(deployment: &Deployment<'_>, report_args: &report::ReportArgs) -> Result<()> {
+    let client = reqwest::Client::new();
+    let mut findings = vec![];
+    let mut referenced: Vec<(String, String)> = vec![];
+    for key in deployment.published_document_keys() {
+        let document = namespacing::s3_key_url(deployment.s3_config, &key);
+        let fetched = client
+            .get(&document)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let content = match fetched {
+            Ok(response) => response
+                .text()
+                .await
+                .wrap_err_with(|| format!("reading [{document}]"))?,
+            Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                info!("links :: [{document}] not published, skipping");
+                continue;
+            }
+            Err(e) => {
+                findings.push(report::Finding::error("link", &document, format!("{e}")));
+                continue;
+            }
+        };
+        findings.push(report::Finding::pass("link", &document));
+        for url in extract_urls(&content) {
+            if !referenced.iter().any(|(known, _)| known == &url) {
+                referenced.push((url, document.clone()));
+            }
+        }
+    }
+    let checked = futures::stream::iter(referenced.iter())
+        .map(|(url, document)| {
+            let client = &client;
+            async move {
+                match check_url(client, url).await {
+                    Ok(()) => report::Finding::pass("link", url),
+                    Err(e) => report::Finding::error(
+                        "link",
+                        url,
+                        format!("{e:#} (referenced by [{document}])"),
+                    ),
+                }
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect::<Vec<_>>()
+        .await;
+    findings.extend(checked);
+    report::write(report_args, "link-check", &findings)?;
+    let broken = findings
+        .iter()
+        .filter(|finding| finding.is_error())
+        .inspect(|finding| error!("links :: BROKEN {} :: {}", finding.subject, finding.message))
+        .count();
+    info!("links :: {} checked, {broken} broken", findings.len());
+    if broken > 0 {
+        bail!("{broken} broken link(s)");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_urls() {
+        let content = r#"{"url": "https://cdn.example.com/master/app%201.7.9.tar.gz", "notes": "see https://example.com/changes."}
+<a href="https://cdn.example.com/master/downloads/app.dmg?a=1&amp;b=2">app.dmg</a> <a href="https://cdn.example.com/master/app%201.7.9.tar.gz">"#;
+        assert_eq!(
+            extract_urls(content),
+            vec![
+                "https://cdn.example.com/master/app%201.7.9.tar.gz",
+                "https://example.com/changes",
+                "https://cdn.example.com/master/downloads/app.dmg?a=1&b=2",
+            ]
+        );
+    }
+}
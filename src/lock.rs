@@ -0,0 +1,204 @@
+use super::*;
+use crate::deploy::Deployment;
+use std::time::Duration;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct LockArgs {
+    /// a lock not released within this time is considered stale (its holder crashed) and may be broken by others
+    #[clap(long, default_value = "5m", value_name = "DURATION")]
+    pub lock_ttl: humantime::Duration,
+    /// give up waiting for the release file lock after this time
+    #[clap(long, default_value = "10m", value_name = "DURATION")]
+    pub lock_timeout: humantime::Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LockInfo {
+    owner: String,
+    expires_at_unix: i64,
+}
+
+impl LockInfo {
+    fn is_expired(&self, now_unix: i64) -> bool {
+        self.expires_at_unix <= now_unix
+    }
+
+    /// the lock as it's stored in the bucket, `None` when it's gone or not readable
+    fn read(object: Option<&storage::VersionedObject>) -> Option<Self> {
+        object.and_then(|object| serde_json::from_slice(&object.content).ok())
+    }
+}
+
+/// held while the release file is being read, merged and written back
+#[derive(Debug, Clone)]
+pub struct ReleaseFileLock {
+    key: String,
+    owner: String,
+}
+
+impl ReleaseFileLock {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// whether the lock in the bucket is still this one, it isn't once it expired and another job broke it
+    fn is_held(&self, current: Option<&LockInfo>) -> bool {
+        current.is_some_and(|current| current.owner == self.owner)
+    }
+}
+
+fn now_unix() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// takes the lock guarding the object under `locked_key`, created with a conditional PUT so only one job can hold it at a time
+#[instrument(skip(deployment, args))]
+pub async fn acquire(
+    deployment: &Deployment<'_>,
+    locked_key: &str,
+    args: &LockArgs,
+) -> Result<ReleaseFileLock> {
+    let key = format!("{locked_key}.lock");
+    let owner = format!(
+        "{}/{}@{} ({})",
+        deployment.branch,
+        serde_variant::to_variant_name(deployment.target).expect("this will always serialize"),
+        deployment.git_hash,
+        uuid::Uuid::new_v4()
+    );
+    let lock_ttl: Duration = args.lock_ttl.into();
+    let lock_timeout: Duration = args.lock_timeout.into();
+    let started = std::time::Instant::now();
+    loop {
+        let info = LockInfo {
+            owner: owner.clone(),
+            expires_at_unix: now_unix() + lock_ttl.as_secs() as i64,
+        };
+        let content = serde_json::to_vec_pretty(&info).wrap_err("serializing lock")?;
+        if storage::put_if_none_match(deployment.s3_config, &key, &content, "application/json")
+            .await
+            .wrap_err("creating lock object")?
+        {
+            info!("lock :: acquired [{key}]");
+            let lock = ReleaseFileLock { key, owner };
+            deployment.staged_objects.hold(&lock);
+            return Ok(lock);
+        }
+        let current = storage::get_versioned(deployment.s3_config, &key)
+            .await
+            .wrap_err("reading lock object")?;
+        let etag = current.as_ref().and_then(|current| current.etag.clone());
+        match (LockInfo::read(current.as_ref()), etag) {
+            (Some(held_by), Some(etag)) if held_by.is_expired(now_unix()) => {
+                warn!(
+                    "lock :: breaking stale lock [{key}] held by {}",
+                    held_by.owner
+                );
+                // only the stale lock that was read, another job may have broken it and taken the lock already
+                if !storage::delete_if_match(deployment.s3_config, &key, &etag)
+                    .await
+                    .wrap_err("breaking stale lock")?
+                {
+                    info!("lock :: [{key}] was taken over in the meantime");
+                }
+                continue;
+            }
+            (Some(held_by), None) if held_by.is_expired(now_unix()) => {
+                warn!(
+                    "lock :: no etag returned for the stale lock [{key}] held by {}, waiting for it to be removed",
+                    held_by.owner
+                );
+            }
+            (Some(held_by), _) => info!("lock :: [{key}] is held by {}, waiting", held_by.owner),
+            // released in the meantime or not readable yet
+            (None, _) => debug!("lock :: [{key}] is held, waiting"),
+        }
+        if started.elapsed() > lock_timeout {
+            bail!(
+                "could not acquire lock [{key}] within {}",
+                humantime::format_duration(lock_timeout)
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+impl ReleaseFileLock {
+    /// deletes the lock if it's still this one - a lock held past its ttl may have been broken and taken by another job,
+    /// which must keep it
+    pub async fn release(self, deployment: &Deployment<'_>) {
+        deployment.staged_objects.unhold(&self.key);
+        match self.delete(deployment.s3_config).await {
+            Ok(true) => info!("lock :: released [{}]", self.key),
+            Ok(false) => warn!(
+                "lock :: [{}] expired and was taken over by another job, leaving it in place",
+                self.key
+            ),
+            Err(e) => error!(
+                "lock :: failed to release [{}], others will have to wait for it to expire :: {e:?}",
+                self.key
+            ),
+        }
+    }
+
+    /// deletes the lock if it's still this one, `false` when it isn't
+    pub async fn delete(&self, s3_config: &S3Config) -> Result<bool> {
+        let current = storage::get_versioned(s3_config, &self.key)
+            .await
+            .wrap_err("reading lock object")?;
+        if !self.is_held(LockInfo::read(current.as_ref()).as_ref()) {
+            return Ok(false);
+        }
+        match current.and_then(|current| current.etag) {
+            Some(etag) => storage::delete_if_match(s3_config, &self.key, &etag).await,
+            None => {
+                warn!(
+                    "lock :: no etag returned for [{}], deleting it unconditionally",
+                    self.key
+                );
+                storage::delete(s3_config, &self.key).await.map(|()| true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_expiry() {
+        let info = LockInfo {
+            owner: "release/x86_64-pc-windows-msvc@deadbeef".to_string(),
+            expires_at_unix: 100,
+        };
+        assert!(!info.is_expired(99));
+        assert!(info.is_expired(100));
+    }
+
+    #[test]
+    fn test_taken_over_lock_is_not_released() {
+        let lock = |owner: &str, expires_at_unix| storage::VersionedObject {
+            content: serde_json::to_vec(&LockInfo {
+                owner: owner.to_string(),
+                expires_at_unix,
+            })
+            .unwrap(),
+            etag: Some("\"etag\"".to_string()),
+        };
+        let stale = ReleaseFileLock {
+            key: "release/release-notes.json.lock".to_string(),
+            owner: "release/x86_64-pc-windows-msvc@deadbeef (a)".to_string(),
+        };
+        let ours = LockInfo::read(Some(&lock(&stale.owner, 100))).unwrap();
+        assert!(ours.is_expired(100));
+        assert!(stale.is_held(Some(&ours)));
+
+        // another job broke the stale lock and took it over
+        let taken_over =
+            LockInfo::read(Some(&lock("release/x86_64-apple-darwin@deadbeef (b)", 400))).unwrap();
+        assert!(!taken_over.is_expired(100));
+        assert!(!stale.is_held(Some(&taken_over)));
+        assert!(!stale.is_held(LockInfo::read(None).as_ref()));
+    }
+}
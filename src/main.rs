@@ -1,4 +1,6 @@
 use clap::{
+    CommandFactory,
+    FromArgMatches,
     Parser,
     Subcommand,
 };
@@ -40,10 +42,19 @@ use tracing::{
 };
 
 use crate::{
+    bump::BumpArgs,
+    deploy::{
+        ArtifactsArgs,
+        PublishArgs,
+    },
     namespacing::{
         derive_binary_file_s3_key,
+        derive_deploy_queue_prefix,
         derive_release_file_s3_key,
+        derive_staged_release_file_s3_key,
     },
+    queue::QueueArgs,
+    receipt::VerifyArgs,
     release_notes_file::RemoteRelease,
 };
 
@@ -63,6 +74,15 @@ pub enum RustTarget {
 }
 
 impl RustTarget {
+    /// name tauri uses for platform specific config files, e.g. `tauri.windows.conf.json`
+    pub fn platform_name(&self) -> &'static str {
+        match self {
+            RustTarget::Win32 | RustTarget::Win64 => "windows",
+            RustTarget::Linux64 => "linux",
+            RustTarget::MacOsX86_64 => "macos",
+        }
+    }
+
     pub fn to_release_platform(&self) -> Result<Vec<release_notes_file::ReleasePlatform>> {
         match self {
             RustTarget::Win32 => Ok(vec![
@@ -78,6 +98,7 @@ impl RustTarget {
                 release_notes_file::ReleasePlatform::V2(ReleasePlatformV2::Linux),
             ]),
             RustTarget::MacOsX86_64 => Ok(vec![
+                release_notes_file::ReleasePlatform::V1(ReleasePlatformV1::Darwin),
                 release_notes_file::ReleasePlatform::V2(ReleasePlatformV2::MacOsX86_64),
             ])
         }
@@ -120,7 +141,10 @@ impl FromStr for RustTarget {
 }
 
 mod release_notes_file {
-    use std::collections::HashMap;
+    use std::collections::{
+        BTreeMap,
+        HashMap,
+    };
 
     use time::OffsetDateTime;
 
@@ -134,6 +158,8 @@ mod release_notes_file {
         Win32,
         #[serde(rename = "linux")]
         Linux,
+        #[serde(rename = "darwin")]
+        Darwin,
     }
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
     pub enum ReleasePlatformV2 {
@@ -165,40 +191,75 @@ mod release_notes_file {
         V2(ReleasePlatformV2),
     }
 
-    // impl ReleasePlatform {
-    //     pub fn to_installer_str(&self) -> String {
-    //         // match self {
-    //         //     ReleasePlatform::Win64 => "x64",
-    //         //     ReleasePlatform::Win32 => "x86",
-    //         //     ReleasePlatform::Linux => unimplemented!("this platform is not supported"),
-    //         // }
-    //         // .to_owned()
-
-    //         match self {
-    //             ReleasePlatform::V1(r) => match r {
-    //                 ReleasePlatformV1::Win64 => "x64",
-    //                 ReleasePlatformV1::Win32 => "x86",
-    //                 ReleasePlatformV1::Linux => {
-    //                     unimplemented!("linux platform is not supported at the moment")
-    //                 }
-    //             },
-    //             ReleasePlatform::V2(r) => match r {
-    //                 ReleasePlatformV2::Win64 => "x64",
-    //                 ReleasePlatformV2::Win32 => "x86",
-    //                 ReleasePlatformV2::Linux => {
-    //                     unimplemented!("linux platform is not supported at the moment")
-    //                 }
-    //             },
-    //         }
-    //         .to_owned()
-    //     }
-    // }
+    /// which updater manifests get published - `v1` is release-notes.json with every platform key, `v2` is tauri 2's latest.json keyed by `{os}-{arch}` only
+    #[derive(
+        Debug,
+        Clone,
+        Copy,
+        Serialize,
+        Deserialize,
+        PartialEq,
+        Eq,
+        PartialOrd,
+        Ord,
+        Hash,
+        IntoEnumIterator,
+    )]
+    #[serde(rename_all = "snake_case")]
+    pub enum ManifestFormat {
+        V1,
+        V2,
+        Both,
+    }
+
+    impl FromStr for ManifestFormat {
+        type Err = eyre::Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            matched_variant!(Self, s)
+        }
+    }
+
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct RemoteRelease {
         pub url: String,
         pub signature: String,
     }
 
+    /// written by `upload-artifacts` next to the binaries, `publish` turns it into the live release-notes.json
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct StagedRelease {
+        pub version: String,
+        pub commit: String,
+        /// release train given to `upload-artifacts`, `publish` may override it
+        #[serde(default)]
+        pub train: Option<String>,
+        pub platforms: HashMap<ReleasePlatform, RemoteRelease>,
+        pub artifacts: Vec<StagedArtifact>,
+        /// installers published to the downloads page along with the release
+        #[serde(default)]
+        pub downloads: Vec<crate::downloads::Download>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct StagedArtifact {
+        pub key: String,
+        pub url: String,
+        pub sha256: String,
+        pub size: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub kind: Option<crate::artifacts::ArtifactKind>,
+    }
+
+    /// `contents.json` of a `{version}/{commit}` prefix - every object the deploy uploaded, so nothing has to LIST the bucket. written once all uploads finished, a prefix without one was left half uploaded
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Contents {
+        pub version: String,
+        pub commit: String,
+        pub target: RustTarget,
+        pub objects: Vec<StagedArtifact>,
+    }
+
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct ReleaseNotes {
         // pub name: String,
@@ -207,7 +268,69 @@ mod release_notes_file {
         pub notes: String,
         #[serde(with = "serde_pub_date")]
         pub pub_date: OffsetDateTime,
+        /// named release train (e.g. `2024.06`) grouping releases across branches and targets, ignored by the updater
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub train: Option<String>,
+        /// apps below this version should force the update, ignored by the stock updater
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub minimum_version: Option<String>,
+        /// the app should install the release without asking, ignored by the stock updater
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pub mandatory: bool,
         pub platforms: HashMap<ReleasePlatform, RemoteRelease>,
+        /// every uploaded file of the release by name, so downloads can be verified - ignored by the updater
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub files: BTreeMap<String, FileChecksum>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct FileChecksum {
+        pub url: String,
+        pub sha256: String,
+        pub size: u64,
+    }
+
+    impl ReleaseNotes {
+        /// keeps the platforms published by other targets, the platforms of this release take precedence
+        pub fn merged_with(self, existing: ReleaseNotes) -> Self {
+            if existing.version != self.version {
+                let stale = existing
+                    .platforms
+                    .keys()
+                    .filter(|platform| !self.platforms.contains_key(platform))
+                    .collect_vec();
+                if !stale.is_empty() {
+                    warn!(
+                        "platforms {stale:?} are still on version {} while publishing {}",
+                        existing.version, self.version
+                    );
+                }
+            }
+            let mut platforms = existing.platforms;
+            platforms.extend(self.platforms);
+            let mut files = existing.files;
+            files.extend(self.files);
+            Self {
+                platforms,
+                files,
+                ..self
+            }
+        }
+
+        /// `sha256sum -c` compatible listing of the files
+        pub fn sha256sums(&self) -> String {
+            self.files
+                .iter()
+                .map(|(name, file)| format!("{}  {name}\n", file.sha256))
+                .join("")
+        }
+
+        /// tauri 2's latest.json only understands the `{os}-{arch}` platform keys
+        pub fn v2_only(mut self) -> Self {
+            self.platforms
+                .retain(|platform, _| matches!(platform, ReleasePlatform::V2(_)));
+            self
+        }
     }
 
     mod serde_pub_date {
@@ -252,7 +375,11 @@ mod release_notes_file {
                 version: "1.2.3".to_string(),
                 notes: "test".to_string(),
                 pub_date: OffsetDateTime::now_utc(),
+                train: None,
+                minimum_version: None,
+                mandatory: false,
                 platforms: Default::default(),
+                files: Default::default(),
             };
 
             let serialized = serde_json::to_string_pretty(&example).wrap_err("serializing")?;
@@ -263,6 +390,97 @@ mod release_notes_file {
             Ok(())
         }
         #[test]
+        fn test_merging_preserves_other_platforms() {
+            let remote = |url: &str| RemoteRelease {
+                url: url.to_string(),
+                signature: String::new(),
+            };
+            let file = |url: &str| FileChecksum {
+                url: url.to_string(),
+                sha256: crate::checksum::sha256_bytes(url.as_bytes()),
+                size: 1024,
+            };
+            let existing = ReleaseNotes {
+                version: "1.2.3".to_string(),
+                notes: "old".to_string(),
+                pub_date: OffsetDateTime::now_utc(),
+                train: None,
+                minimum_version: None,
+                mandatory: false,
+                platforms: [
+                    (ReleasePlatformV2::Linux.into(), remote("linux-old")),
+                    (ReleasePlatformV2::Win64.into(), remote("windows-old")),
+                ]
+                .into_iter()
+                .collect(),
+                files: [
+                    ("app.AppImage".to_string(), file("linux")),
+                    ("app.msi".to_string(), file("windows-old")),
+                ]
+                .into_iter()
+                .collect(),
+            };
+            let current = ReleaseNotes {
+                version: "1.2.3".to_string(),
+                notes: "new".to_string(),
+                pub_date: OffsetDateTime::now_utc(),
+                train: None,
+                minimum_version: None,
+                mandatory: false,
+                platforms: [(ReleasePlatformV2::Win64.into(), remote("windows-new"))]
+                    .into_iter()
+                    .collect(),
+                files: [("app.msi".to_string(), file("windows-new"))]
+                    .into_iter()
+                    .collect(),
+            };
+            let merged = current.merged_with(existing);
+            assert_eq!(merged.notes, "new");
+            assert_eq!(
+                merged.platforms[&ReleasePlatformV2::Linux.into()].url,
+                "linux-old"
+            );
+            assert_eq!(
+                merged.platforms[&ReleasePlatformV2::Win64.into()].url,
+                "windows-new"
+            );
+            assert_eq!(
+                merged.sha256sums(),
+                format!(
+                    "{}  app.AppImage\n{}  app.msi\n",
+                    file("linux").sha256,
+                    file("windows-new").sha256
+                )
+            );
+        }
+        #[test]
+        fn test_v2_only_drops_legacy_platforms() {
+            let remote = RemoteRelease {
+                url: "windows".to_string(),
+                signature: String::new(),
+            };
+            let release = ReleaseNotes {
+                version: "1.2.3".to_string(),
+                notes: "new".to_string(),
+                pub_date: OffsetDateTime::now_utc(),
+                train: None,
+                minimum_version: None,
+                mandatory: false,
+                platforms: RustTarget::Win64
+                    .to_release_platform()
+                    .unwrap()
+                    .into_iter()
+                    .map(|platform| (platform, remote.clone()))
+                    .collect(),
+                files: Default::default(),
+            }
+            .v2_only();
+            assert_eq!(
+                release.platforms.keys().collect_vec(),
+                vec![&ReleasePlatform::V2(ReleasePlatformV2::Win64)]
+            );
+        }
+        #[test]
         fn check_current_release_file_works() -> eyre::Result<()> {
             const CURRENT: &str = include_str!("../test_data/release-notes.json");
             let parsed: ReleaseNotes =
@@ -284,6 +502,7 @@ pub mod tauri_conf_json {
     #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Package {
+        #[serde(alias = "product-name")]
         pub product_name: String,
         pub version: String,
     }
@@ -309,36 +528,377 @@ pub mod tauri_conf_json {
         pub rest: serde_json::Value,
     }
     #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct TauriConfJson {
+    pub struct TauriConfJsonV1 {
         pub package: Package,
         pub tauri: Tauri,
         #[serde(flatten)]
         pub rest: serde_json::Value,
     }
 
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct UpdaterV2 {
+        #[serde(default)]
+        pub endpoints: Vec<String>,
+        #[serde(flatten)]
+        pub rest: serde_json::Map<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct PluginsV2 {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub updater: Option<UpdaterV2>,
+        #[serde(flatten)]
+        pub rest: serde_json::Map<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BundleV2 {
+        /// `true`, `false` or `"v1Compatible"`
+        #[serde(
+            default,
+            alias = "create-updater-artifacts",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub create_updater_artifacts: Option<serde_json::Value>,
+        #[serde(flatten)]
+        pub rest: serde_json::Map<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TauriConfJsonV2 {
+        #[serde(alias = "product-name")]
+        pub product_name: String,
+        pub version: String,
+        pub identifier: String,
+        #[serde(default)]
+        pub bundle: BundleV2,
+        #[serde(default)]
+        pub plugins: PluginsV2,
+        #[serde(flatten)]
+        pub rest: serde_json::Map<String, serde_json::Value>,
+    }
+
+    /// tauri v1 (`package`, `tauri.updater`, `tauri.bundle`) or v2 (everything at the top level, updater under `plugins`) configuration, detected from the file contents
+    #[derive(Debug, Clone, Serialize)]
+    #[serde(untagged)]
+    pub enum TauriConfJson {
+        V1(TauriConfJsonV1),
+        V2(TauriConfJsonV2),
+    }
+
+    impl<'de> Deserialize<'de> for TauriConfJson {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let value = serde_json::Value::deserialize(deserializer)?;
+            if value.get("package").is_some() || value.get("tauri").is_some() {
+                serde_json::from_value(value)
+                    .map(Self::V1)
+                    .map_err(serde::de::Error::custom)
+            } else {
+                serde_json::from_value(value)
+                    .map(Self::V2)
+                    .map_err(serde::de::Error::custom)
+            }
+        }
+    }
+
     impl TauriConfJson {
+        pub fn version(&self) -> &str {
+            match self {
+                Self::V1(conf) => &conf.package.version,
+                Self::V2(conf) => &conf.version,
+            }
+        }
+
+        pub fn product_name(&self) -> &str {
+            match self {
+                Self::V1(conf) => &conf.package.product_name,
+                Self::V2(conf) => &conf.product_name,
+            }
+        }
+
+        pub fn identifier(&self) -> &str {
+            match self {
+                Self::V1(conf) => &conf.tauri.bundle.identifier,
+                Self::V2(conf) => &conf.identifier,
+            }
+        }
+
+        pub fn update_endpoints(&self) -> &[String] {
+            match self {
+                Self::V1(conf) => &conf.tauri.updater.endpoints,
+                Self::V2(conf) => conf
+                    .plugins
+                    .updater
+                    .as_ref()
+                    .map(|updater| updater.endpoints.as_slice())
+                    .unwrap_or_default(),
+            }
+        }
+
+        /// the minisign public key the updater checks the `.sig` of the bundles against
+        pub fn updater_pubkey(&self) -> Option<&str> {
+            let rest = match self {
+                Self::V1(conf) => conf.tauri.updater.rest.as_object()?,
+                Self::V2(conf) => &conf.plugins.updater.as_ref()?.rest,
+            };
+            rest.get("pubkey")?.as_str()
+        }
+
         pub fn with_update_endpoint(&mut self, endpoint: String) -> &mut Self {
-            let old = self.tauri.updater.endpoints.clone();
-            self.tauri.updater.endpoints = vec![endpoint];
-            info!(
-                "tauri.updater.endpoints :: {:?} -> {:?}",
-                old, self.tauri.updater.endpoints
-            );
+            match self {
+                Self::V1(conf) => {
+                    let old = conf.tauri.updater.endpoints.clone();
+                    conf.tauri.updater.endpoints = vec![endpoint];
+                    info!(
+                        "tauri.updater.endpoints :: {:?} -> {:?}",
+                        old, conf.tauri.updater.endpoints
+                    );
+                }
+                Self::V2(conf) => {
+                    let updater = conf.plugins.updater.get_or_insert_with(Default::default);
+                    let old = updater.endpoints.clone();
+                    updater.endpoints = vec![endpoint];
+                    info!(
+                        "plugins.updater.endpoints :: {:?} -> {:?}",
+                        old, updater.endpoints
+                    );
+                }
+            }
             self
         }
 
         pub fn with_update_identifier(&mut self, identifier: String) -> &mut Self {
-            let old = self.tauri.bundle.identifier.clone();
+            let (field, current) = match self {
+                Self::V1(conf) => ("tauri.bundle.identifier", &mut conf.tauri.bundle.identifier),
+                Self::V2(conf) => ("identifier", &mut conf.identifier),
+            };
+            let old = std::mem::replace(current, identifier);
+            info!("{field} :: {:?} -> {:?}", old, current);
+            self
+        }
 
-            self.tauri.bundle.identifier = identifier;
-            info!(
-                "tauri.bundle.identifier :: {:?} -> {:?}",
-                old, self.tauri.bundle.identifier
-            );
+        /// tauri v2 only builds the updater bundles (and their signatures) when asked to
+        pub fn with_updater_artifacts(&mut self) -> &mut Self {
+            if let Self::V2(conf) = self {
+                let enabled = match &conf.bundle.create_updater_artifacts {
+                    Some(serde_json::Value::Bool(enabled)) => *enabled,
+                    Some(serde_json::Value::String(mode)) => mode == "v1Compatible",
+                    _ => false,
+                };
+                if !enabled {
+                    info!(
+                        "bundle.createUpdaterArtifacts :: {:?} -> true",
+                        conf.bundle.create_updater_artifacts
+                    );
+                    conf.bundle.create_updater_artifacts = Some(serde_json::Value::Bool(true));
+                }
+            }
             self
         }
     }
 
+    /// tauri reads its configuration from `tauri.conf.json`, `tauri.conf.json5` or `Tauri.toml`, patched files are written back in the format they were read in
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConfigFormat {
+        Json,
+        Json5,
+        Toml,
+    }
+
+    impl ConfigFormat {
+        pub fn from_path<T: AsRef<Path>>(path: T) -> Self {
+            match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+                Some("json5") => Self::Json5,
+                Some("toml") => Self::Toml,
+                _ => Self::Json,
+            }
+        }
+
+        /// the raw file contents, before any overlays are merged in
+        pub fn parse_value(self, content: &str) -> Result<serde_json::Value> {
+            match self {
+                Self::Json => serde_json::from_str(content).wrap_err("parsing tauri.conf.json"),
+                Self::Json5 => json5::from_str(content).wrap_err("parsing tauri.conf.json5"),
+                Self::Toml => toml::from_str(content).wrap_err("parsing Tauri.toml"),
+            }
+        }
+
+        pub fn parse(self, content: &str) -> Result<TauriConfJson> {
+            self.parse_value(content).and_then(|value| {
+                serde_json::from_value(value).wrap_err("bad format for tauri.conf.json")
+            })
+        }
+
+        /// json5 is written back as plain json (which is valid json5), comments are not preserved
+        pub fn render_value(self, value: &serde_json::Value) -> Result<String> {
+            match self {
+                Self::Json | Self::Json5 => serde_json::to_string_pretty(value)
+                    .wrap_err("serializing tauri.conf.json content"),
+                // going through toml::Value makes sure plain values are emitted before tables
+                Self::Toml => toml::Value::try_from(value)
+                    .and_then(|value| toml::to_string_pretty(&value))
+                    .wrap_err("serializing Tauri.toml content"),
+            }
+        }
+
+        pub fn render(self, tauri_conf_json: &TauriConfJson) -> Result<String> {
+            serde_json::to_value(tauri_conf_json)
+                .wrap_err("serializing tauri.conf.json content")
+                .and_then(|value| self.render_value(&value))
+        }
+    }
+
+    /// when the given config doesn't exist, looks for the other file names tauri accepts in the same directory
+    pub fn locate<T: AsRef<Path>>(path: T) -> PathBuf {
+        let path = path.as_ref();
+        if path.exists() {
+            return path.to_path_buf();
+        }
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        ["tauri.conf.json", "tauri.conf.json5", "Tauri.toml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|candidate| candidate.exists())
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// `tauri.conf.json` -> `tauri.windows.conf.json`, `Tauri.toml` -> `Tauri.linux.toml`
+    pub fn overlay_path<T: AsRef<Path>>(path: T, target: &RustTarget) -> Option<PathBuf> {
+        let path = path.as_ref();
+        let file_name = path.file_name()?.to_str()?;
+        let platform = target.platform_name();
+        let overlay = match file_name.split_once('.') {
+            Some((stem, "toml")) => format!("{stem}.{platform}.toml"),
+            Some((stem, rest)) => format!("{stem}.{platform}.{rest}"),
+            None => return None,
+        };
+        Some(path.with_file_name(overlay))
+    }
+
+    /// RFC 7396 json merge patch, the way tauri applies platform overlays
+    fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+        let patch = match patch {
+            serde_json::Value::Object(patch) => patch,
+            patch => {
+                *target = patch.clone();
+                return;
+            }
+        };
+        if !target.is_object() {
+            *target = serde_json::Value::Object(Default::default());
+        }
+        if let serde_json::Value::Object(target) = target {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(key);
+                } else {
+                    merge_patch(target.entry(key).or_insert(serde_json::Value::Null), value);
+                }
+            }
+        }
+    }
+
+    /// the merge patch turning `before` into `after`, `None` when they are equal
+    fn diff(before: &serde_json::Value, after: &serde_json::Value) -> Option<serde_json::Value> {
+        if before == after {
+            return None;
+        }
+        match (before, after) {
+            (serde_json::Value::Object(before), serde_json::Value::Object(after)) => {
+                let removed = before
+                    .keys()
+                    .filter(|key| !after.contains_key(*key))
+                    .map(|key| (key.clone(), serde_json::Value::Null));
+                let changed = after
+                    .iter()
+                    .filter_map(|(key, value)| match before.get(key) {
+                        Some(old) => diff(old, value).map(|patch| (key.clone(), patch)),
+                        None => Some((key.clone(), value.clone())),
+                    });
+                Some(serde_json::Value::Object(removed.chain(changed).collect()))
+            }
+            (_, after) => Some(after.clone()),
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Overlay {
+        path: PathBuf,
+        format: ConfigFormat,
+    }
+
+    /// the config as tauri sees it when building for a target - the base file with the platform overlay (if any) merged over it
+    #[derive(Debug, Clone)]
+    pub struct LoadedConfig {
+        pub path: PathBuf,
+        format: ConfigFormat,
+        effective: serde_json::Value,
+        overlay: Option<Overlay>,
+        pub tauri_conf_json: TauriConfJson,
+    }
+
+    impl LoadedConfig {
+        pub fn load<T: AsRef<Path>>(path: T, target: &RustTarget) -> Result<Self> {
+            let path = path.as_ref().to_path_buf();
+            let format = ConfigFormat::from_path(&path);
+            let mut effective = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("reading [{}]", path.display()))
+                .and_then(|content| format.parse_value(&content))?;
+            let overlay = match overlay_path(&path, target).filter(|overlay| overlay.exists()) {
+                Some(overlay_path) => {
+                    let format = ConfigFormat::from_path(&overlay_path);
+                    let value = std::fs::read_to_string(&overlay_path)
+                        .wrap_err_with(|| format!("reading [{}]", overlay_path.display()))
+                        .and_then(|content| format.parse_value(&content))?;
+                    info!("merging platform overlay [{}]", overlay_path.display());
+                    merge_patch(&mut effective, &value);
+                    Some(Overlay {
+                        path: overlay_path,
+                        format,
+                    })
+                }
+                None => None,
+            };
+            let tauri_conf_json = serde_json::from_value(effective.clone())
+                .wrap_err("bad format for tauri.conf.json")?;
+            Ok(Self {
+                path,
+                format,
+                effective,
+                overlay,
+                tauri_conf_json,
+            })
+        }
+
+        /// writes the patched config back - with an overlay only the changes go into the overlay, so they win over the base file for this platform
+        ///
+        /// the file is locked and read again first, so changes of others patching it in parallel (monorepos) are kept
+        pub fn save(&self, patched: &TauriConfJson) -> Result<()> {
+            let patched = serde_json::to_value(patched).wrap_err("serializing tauri.conf.json")?;
+            let (path, format) = match &self.overlay {
+                Some(overlay) => (&overlay.path, overlay.format),
+                None => (&self.path, self.format),
+            };
+            let changes = match diff(&self.effective, &patched) {
+                Some(changes) => changes,
+                None => {
+                    info!("{path:?} is up to date");
+                    return Ok(());
+                }
+            };
+            let _lock = files::FileLock::acquire(path)?;
+            let mut value = std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("reading [{}]", path.display()))
+                .and_then(|content| format.parse_value(&content))?;
+            merge_patch(&mut value, &changes);
+            let content = format.render_value(&value)?;
+            info!("writing to {:?}:\n\n{}\n\n", path, content);
+            files::write_atomic(path, &content).wrap_err("saving tauri.conf.json")
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -359,6 +919,115 @@ pub mod tauri_conf_json {
             assert_eq!(original, reparsed);
             Ok(())
         }
+
+        const CONTENT_V2: &str = include_str!("../test_data/tauri.v2.conf.json");
+        #[test]
+        fn test_v2_file_loads() -> Result<()> {
+            let original: serde_json::Value =
+                serde_json::from_str(CONTENT_V2).wrap_err("failed to parse tauri.conf.json")?;
+            let parsed: TauriConfJson =
+                serde_json::from_str(CONTENT_V2).wrap_err("failed to parse tauri.conf.json")?;
+            assert!(matches!(parsed, TauriConfJson::V2(_)));
+            assert_eq!(parsed.version(), "2.0.1");
+            assert_eq!(parsed.identifier(), "com.random-company.random-app");
+            let reparsed: serde_json::Value =
+                serde_json::from_str(&serde_json::to_string_pretty(&parsed)?)?;
+            assert_eq!(original, reparsed);
+            Ok(())
+        }
+
+        #[test]
+        fn test_overlay_path() {
+            assert_eq!(
+                overlay_path("src-tauri/tauri.conf.json", &RustTarget::Win64),
+                Some(PathBuf::from("src-tauri/tauri.windows.conf.json"))
+            );
+            assert_eq!(
+                overlay_path("src-tauri/Tauri.toml", &RustTarget::MacOsX86_64),
+                Some(PathBuf::from("src-tauri/Tauri.macos.toml"))
+            );
+        }
+
+        #[test]
+        fn test_overlay_gets_only_the_changes() -> Result<()> {
+            let mut effective: serde_json::Value = serde_json::from_str(CONTENT)?;
+            let overlay = serde_json::json!({ "tauri": { "bundle": { "identifier": "com.random-company.random-app.windows" } } });
+            merge_patch(&mut effective, &overlay);
+            let mut patched: TauriConfJson = serde_json::from_value(effective.clone())?;
+            assert_eq!(
+                patched.identifier(),
+                "com.random-company.random-app.windows"
+            );
+            patched.with_update_endpoint("https://example.com/release-notes.json".to_string());
+            let changes = diff(&effective, &serde_json::to_value(&patched)?);
+            assert_eq!(
+                changes,
+                Some(
+                    serde_json::json!({ "tauri": { "updater": { "endpoints": ["https://example.com/release-notes.json"] } } })
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_json5_and_toml_load() -> Result<()> {
+            let json5 = ConfigFormat::Json5.parse(
+                r#"{
+                    // comments are fine
+                    productName: "random-app",
+                    version: "2.0.1",
+                    identifier: "com.random-company.random-app",
+                    plugins: { updater: { endpoints: [], }, },
+                }"#,
+            )?;
+            assert_eq!(json5.version(), "2.0.1");
+            let toml = ConfigFormat::Toml.parse(
+                r#"
+                product-name = "random-app"
+                version = "2.0.1"
+                identifier = "com.random-company.random-app"
+
+                [bundle]
+                create-updater-artifacts = false
+                "#,
+            )?;
+            assert_eq!(toml.product_name(), "random-app");
+            let mut patched = toml;
+            patched
+                .with_update_endpoint("https://example.com/latest.json".to_string())
+                .with_updater_artifacts();
+            let rendered = ConfigFormat::Toml.render(&patched)?;
+            let reparsed = ConfigFormat::Toml.parse(&rendered)?;
+            assert_eq!(
+                reparsed.update_endpoints(),
+                ["https://example.com/latest.json".to_string()]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_v2_patching() -> Result<()> {
+            let mut parsed: TauriConfJson =
+                serde_json::from_str(CONTENT_V2).wrap_err("failed to parse tauri.conf.json")?;
+            parsed
+                .with_update_endpoint("https://example.com/release-notes.json".to_string())
+                .with_update_identifier("com.random-company.random-app.release".to_string())
+                .with_updater_artifacts();
+            let patched = serde_json::to_value(&parsed)?;
+            assert_eq!(
+                patched["plugins"]["updater"]["endpoints"],
+                serde_json::json!(["https://example.com/release-notes.json"])
+            );
+            assert_eq!(
+                patched["identifier"],
+                serde_json::json!("com.random-company.random-app.release")
+            );
+            assert_eq!(
+                patched["bundle"]["createUpdaterArtifacts"],
+                serde_json::json!(true)
+            );
+            Ok(())
+        }
     }
 }
 
@@ -498,15 +1167,69 @@ pub mod metadata {
         Ok(target)
     }
 
+    /// the branch from the CI provider's env when there is one (checkouts there are often detached), `git branch --show-current` otherwise
+    ///
+    /// on a detached HEAD it's the branch pointing at it, `--branch` has to name it when there is none
     #[instrument(ret, level = "debug")]
     pub fn current_branch() -> Result<String> {
-        let out = std::process::Command::new("git")
-            .arg("branch")
-            .arg("--show-current")
-            .output()
-            .wrap_err("getting current branch")?;
+        if let Some(branch) = ci::branch() {
+            return Ok(branch);
+        }
+        let branch = git(&["branch", "--show-current"])?;
+        if !branch.is_empty() {
+            return Ok(branch);
+        }
+        let refs = git(&[
+            "for-each-ref",
+            "--points-at",
+            "HEAD",
+            "--format=%(refname)",
+            "refs/heads/",
+            "refs/remotes/",
+        ])?;
+        match branch_from_refs(&refs) {
+            Some(branch) => {
+                info!("HEAD is detached, using [{branch}] pointing at it");
+                Ok(branch)
+            }
+            None => bail!("HEAD is detached and no branch points at it, pass --branch"),
+        }
+    }
+
+    /// the first local branch among the refs, else the first remote-tracking one without its remote
+    fn branch_from_refs(refs: &str) -> Option<String> {
+        let refs = refs.lines().map(str::trim).collect_vec();
+        refs.iter()
+            .find_map(|name| name.strip_prefix("refs/heads/"))
+            .or_else(|| {
+                refs.iter()
+                    .filter_map(|name| name.strip_prefix("refs/remotes/"))
+                    .filter_map(|name| name.split_once('/'))
+                    .map(|(_remote, branch)| branch)
+                    .find(|branch| *branch != "HEAD")
+            })
+            .map(ToString::to_string)
+    }
+
+    /// CI checkouts usually are, their history ends a few commits back and tags may be missing
+    pub fn is_shallow() -> bool {
+        git(&["rev-parse", "--is-shallow-repository"]).is_ok_and(|shallow| shallow == "true")
+    }
 
-        decode_command_output(&out.stdout)
+    /// stdout of a git command that has to succeed
+    pub fn git(args: &[&str]) -> Result<String> {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .output()
+            .wrap_err_with(|| format!("running git {}", args.join(" ")))?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed :: {}",
+                args.join(" "),
+                decode_command_output(&output.stderr).unwrap_or_default()
+            );
+        }
+        decode_command_output(&output.stdout)
     }
 
     #[cfg(test)]
@@ -522,40 +1245,510 @@ pub mod metadata {
             println!("detected current branch: [{}]", current_branch()?);
             Ok(())
         }
+        #[test]
+        fn test_branch_from_refs() {
+            assert_eq!(
+                branch_from_refs("refs/heads/release\nrefs/remotes/origin/main\n").as_deref(),
+                Some("release")
+            );
+            assert_eq!(
+                branch_from_refs("refs/remotes/origin/HEAD\nrefs/remotes/origin/feature/login\n")
+                    .as_deref(),
+                Some("feature/login")
+            );
+            assert_eq!(branch_from_refs(""), None);
+        }
     }
 }
 pub mod namespacing {
     use super::*;
+    use unicode_normalization::{
+        char::is_combining_mark,
+        UnicodeNormalization,
+    };
+
+    /// letters NFKD leaves alone because they are not composed with a diacritic
+    const ASCII_FOLDS: &[(char, &str)] = &[
+        ('ł', "l"),
+        ('Ł', "L"),
+        ('đ', "d"),
+        ('Đ', "D"),
+        ('ø', "o"),
+        ('Ø', "O"),
+        ('ß', "ss"),
+        ('æ', "ae"),
+        ('Æ', "AE"),
+    ];
+
+    /// bundle identifiers may only hold ascii letters, digits, `-`, `_` and `.` - diacritics are stripped (NFKD), whatever is left is replaced with `-`
     #[instrument(ret)]
-    pub fn derive_release_base_key(branch_name: &str, target: &RustTarget) -> String {
-        format!(
-            "{}/{}",
-            branch_name,
-            serde_variant::to_variant_name(&target).expect("this will always serialize")
-        )
+    pub fn ascii_identifier(identifier: &str) -> String {
+        identifier
+            .nfkd()
+            .filter(|c| !is_combining_mark(*c))
+            .map(|c| {
+                ASCII_FOLDS
+                    .iter()
+                    .find(|(from, _)| *from == c)
+                    .map(|(_, to)| to.to_string())
+                    .unwrap_or_else(|| c.to_string())
+            })
+            .collect::<String>()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-._".contains(c) {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect()
     }
 
-    #[instrument(ret)]
-    pub fn derive_release_file_s3_key(branch_name: &str, target: &RustTarget) -> String {
-        format!(
+    /// what the updater follows, keys are namespaced by it instead of the branch when one is set
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, IntoEnumIterator)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ReleaseChannel {
+        Stable,
+        Beta,
+        Nightly,
+    }
+
+    impl FromStr for ReleaseChannel {
+        type Err = eyre::Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            matched_variant!(Self, s)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct ChannelRule {
+        /// glob matched against the branch name, e.g. `release/*`
+        pub branch: String,
+        pub channel: ReleaseChannel,
+    }
+
+    /// `[[rules]]` mapping branches to channels, the first matching rule wins
+    #[derive(Debug, Clone, Default, Deserialize)]
+    pub struct ChannelRules {
+        #[serde(default)]
+        pub rules: Vec<ChannelRule>,
+    }
+
+    impl ChannelRules {
+        pub fn load(path: Option<&Path>) -> Result<Self> {
+            let path = match path {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            };
+            std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("reading channel rules [{}]", path.display()))
+                .and_then(|content| toml::from_str(&content).wrap_err("parsing channel rules"))
+        }
+
+        pub fn channel_for(&self, branch_name: &str) -> Result<Option<ReleaseChannel>> {
+            for rule in &self.rules {
+                let glob = globset::Glob::new(&rule.branch)
+                    .wrap_err_with(|| format!("bad branch glob [{}]", rule.branch))?
+                    .compile_matcher();
+                if glob.is_match(branch_name) {
+                    return Ok(Some(rule.channel));
+                }
+            }
+            Ok(None)
+        }
+    }
+
+    fn resolved_channel(
+        branch_name: &str,
+        channel: Option<ReleaseChannel>,
+        rules: &ChannelRules,
+    ) -> Result<Option<ReleaseChannel>> {
+        match channel {
+            Some(channel) => Ok(Some(channel)),
+            None => rules.channel_for(branch_name),
+        }
+    }
+
+    /// the first segment of every key - the channel (`--channel`, or the one the rules map the branch to), the sanitized branch name when there's none
+    #[instrument(ret, skip(rules))]
+    pub fn derive_release_namespace(
+        branch_name: &str,
+        channel: Option<ReleaseChannel>,
+        rules: &ChannelRules,
+    ) -> Result<String> {
+        match resolved_channel(branch_name, channel, rules)? {
+            Some(channel) => Ok(serde_variant::to_variant_name(&channel)
+                .expect("this will always serialize")
+                .to_string()),
+            None => sanitize_branch_name(branch_name),
+        }
+    }
+
+    /// who the namespace belongs to, recorded in it to catch two branches sanitized to the same name
+    pub fn derive_namespace_owner(
+        branch_name: &str,
+        channel: Option<ReleaseChannel>,
+        rules: &ChannelRules,
+    ) -> Result<String> {
+        Ok(match resolved_channel(branch_name, channel, rules)? {
+            Some(channel) => format!(
+                "channel {}",
+                serde_variant::to_variant_name(&channel).expect("this will always serialize")
+            ),
+            None => format!("branch {branch_name}"),
+        })
+    }
+
+    /// keys get long with the target, version, commit and file name on top of the branch
+    pub const MAX_BRANCH_NAME_LEN: usize = 100;
+
+    /// refuses branch names that can't be a key namespace - empty ones, too long ones, ones with an empty, `.` or `..` segment or a control character, and ones under the latest aliases
+    pub fn validate_branch_name(branch_name: &str) -> Result<()> {
+        if branch_name.is_empty() {
+            bail!("empty branch name - is HEAD detached?");
+        }
+        if branch_name.chars().count() > MAX_BRANCH_NAME_LEN {
+            bail!("branch name [{branch_name}] is longer than {MAX_BRANCH_NAME_LEN} characters");
+        }
+        if let Some(c) = branch_name.chars().find(|c| c.is_control()) {
+            bail!(
+                "branch name [{}] holds the control character {c:?}",
+                branch_name.escape_debug()
+            );
+        }
+        if branch_name
+            .split('/')
+            .any(|segment| matches!(segment, "" | "." | ".."))
+        {
+            bail!("branch name [{branch_name}] has an empty, `.` or `..` segment");
+        }
+        if branch_name.split('/').next() == Some(LATEST_ALIAS_NAMESPACE) {
+            bail!("branch name [{branch_name}] starts with [{LATEST_ALIAS_NAMESPACE}], which holds the latest aliases");
+        }
+        Ok(())
+    }
+
+    /// the key namespace of a branch - every `/` separated segment made [`ascii_identifier`] safe, so ascii branch names stay as they are
+    #[instrument(ret)]
+    pub fn sanitize_branch_name(branch_name: &str) -> Result<String> {
+        validate_branch_name(branch_name)?;
+        Ok(branch_name.split('/').map(ascii_identifier).join("/"))
+    }
+
+    /// what `patch` appends to the bundle identifier for the namespace
+    #[instrument(ret)]
+    pub fn derive_identifier_suffix(namespace: &str) -> String {
+        ascii_identifier(&namespace.replace(['/', ' ', ':'], "_"))
+    }
+
+    /// the namespace the latest aliases live in, no branch may be named like it
+    pub const LATEST_ALIAS_NAMESPACE: &str = "latest";
+
+    /// a permanent key for a manifest of the namespace, `main/win64/release-notes.json` -> `latest/win64/release-notes.json`
+    #[instrument(ret)]
+    pub fn derive_latest_alias_s3_key(branch_name: &str, manifest_key: &str) -> String {
+        let key = manifest_key
+            .strip_prefix(branch_name)
+            .and_then(|key| key.strip_prefix('/'))
+            .unwrap_or(manifest_key);
+        format!("{LATEST_ALIAS_NAMESPACE}/{key}")
+    }
+
+    /// records which branch (or channel) the namespace belongs to
+    #[instrument(ret)]
+    pub fn derive_namespace_owner_s3_key(namespace: &str) -> String {
+        format!("{namespace}/owner.json")
+    }
+
+    #[instrument(ret)]
+    pub fn derive_release_base_key(branch_name: &str, target: &RustTarget) -> String {
+        format!(
+            "{}/{}",
+            branch_name,
+            serde_variant::to_variant_name(&target).expect("this will always serialize")
+        )
+    }
+
+    #[instrument(ret)]
+    pub fn derive_release_file_s3_key(branch_name: &str, target: &RustTarget) -> String {
+        format!(
             "{}/release-notes.json",
             derive_release_base_key(branch_name, target)
         )
     }
 
+    /// `SHA256SUMS` next to the manifest
+    #[instrument(ret)]
+    pub fn derive_checksums_s3_key(manifest_key: &str) -> String {
+        match manifest_key.rsplit_once('/') {
+            Some((dir, _)) => format!("{dir}/SHA256SUMS"),
+            None => "SHA256SUMS".to_string(),
+        }
+    }
+
+    #[instrument(ret)]
+    pub fn derive_deploy_queue_prefix(branch_name: &str) -> String {
+        format!("{branch_name}/deploy-queue/")
+    }
+
+    #[instrument(ret)]
+    pub fn derive_staged_release_file_s3_key(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+    ) -> String {
+        format!(
+            "{}/{version}/staged-release.json",
+            derive_release_base_key(branch_name, target)
+        )
+    }
+
+    #[instrument(ret)]
+    pub fn derive_contents_s3_key(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+        git_commit_hash: &str,
+    ) -> String {
+        format!(
+            "{}/{version}/{git_commit_hash}/contents.json",
+            derive_release_base_key(branch_name, target)
+        )
+    }
+
+    /// tauri 2 updater manifest
+    #[instrument(ret)]
+    pub fn derive_latest_json_s3_key(branch_name: &str, target: &RustTarget) -> String {
+        format!(
+            "{}/latest.json",
+            derive_release_base_key(branch_name, target)
+        )
+    }
+
+    #[instrument(ret)]
+    pub fn derive_shared_latest_json_s3_key(branch_name: &str) -> String {
+        format!("{branch_name}/latest.json")
+    }
+
+    /// one manifest for all targets of a branch, used when publishing merges platforms instead of overwriting them
+    #[instrument(ret)]
+    pub fn derive_shared_release_file_s3_key(branch_name: &str) -> String {
+        format!("{branch_name}/release-notes.json")
+    }
+
+    /// the manifest with the notes in another language, `main/release-notes.json` -> `main/release-notes.de.json`
+    pub fn derive_localized_s3_key(manifest_key: &str, locale: &str) -> String {
+        match manifest_key.strip_suffix(".json") {
+            Some(stem) => format!("{stem}.{locale}.json"),
+            None => format!("{manifest_key}.{locale}"),
+        }
+    }
+
+    /// the locale of a key derived by [`derive_localized_s3_key`] from the manifest key
+    pub fn localized_s3_key_locale<'a>(manifest_key: &str, key: &'a str) -> Option<&'a str> {
+        let stem = manifest_key.strip_suffix(".json")?;
+        key.strip_prefix(stem)?
+            .strip_prefix('.')?
+            .strip_suffix(".json")
+            .filter(|locale| is_locale(locale))
+    }
+
+    /// `de`, `pt-BR`, `zh-Hant` - a bcp 47 language tag, checked loosely
+    pub fn is_locale(locale: &str) -> bool {
+        let mut subtags = locale.split('-');
+        let language = subtags.next().unwrap_or_default();
+        (2..=3).contains(&language.len())
+            && language.chars().all(|c| c.is_ascii_alphabetic())
+            && subtags.all(|subtag| {
+                (2..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+    }
+
+    #[instrument(ret)]
+    pub fn derive_receipt_s3_key(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+        deploy_id: &str,
+    ) -> String {
+        format!(
+            "{}{deploy_id}.json",
+            derive_version_receipts_prefix(branch_name, target, version)
+        )
+    }
+
+    /// every receipt of the branch is under this prefix, so they can be listed without the binaries
+    #[instrument(ret)]
+    pub fn derive_receipts_prefix(branch_name: &str) -> String {
+        format!("{branch_name}/receipts/")
+    }
+
+    /// every receipt of one version of the target is under this prefix
+    #[instrument(ret)]
+    pub fn derive_version_receipts_prefix(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+    ) -> String {
+        format!(
+            "{}{}/{version}/",
+            derive_receipts_prefix(branch_name),
+            serde_variant::to_variant_name(&target).expect("this will always serialize")
+        )
+    }
+
+    /// sparkle feed of the target, next to its manifest
+    #[instrument(ret)]
+    pub fn derive_appcast_s3_key(branch_name: &str, target: &RustTarget) -> String {
+        format!(
+            "{}/appcast.xml",
+            derive_release_base_key(branch_name, target)
+        )
+    }
+
+    /// appstream metainfo of the target, next to its manifest
+    #[instrument(ret)]
+    pub fn derive_metainfo_s3_key(branch_name: &str, target: &RustTarget) -> String {
+        format!(
+            "{}/metainfo.xml",
+            derive_release_base_key(branch_name, target)
+        )
+    }
+
+    /// every upload of the branch, appended to by each of them
+    #[instrument(ret)]
+    pub fn derive_releases_index_s3_key(branch_name: &str) -> String {
+        format!("{branch_name}/releases-index.json")
+    }
+
+    /// history of a version shared by all targets of the branch (sign-offs and the like)
+    #[instrument(ret)]
+    pub fn derive_version_record_s3_key(branch_name: &str, version: &str) -> String {
+        format!("{branch_name}/history/{version}.json")
+    }
+
+    #[instrument(ret)]
+    pub fn derive_downloads_prefix(branch_name: &str) -> String {
+        format!("{branch_name}/downloads/")
+    }
+
+    #[instrument(ret)]
+    pub fn derive_download_s3_key(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+        file_name: &str,
+    ) -> String {
+        format!(
+            "{}{}/{version}/{file_name}",
+            derive_downloads_prefix(branch_name),
+            serde_variant::to_variant_name(&target).expect("this will always serialize")
+        )
+    }
+
+    pub fn derive_packages_prefix(branch_name: &str) -> String {
+        format!("{branch_name}/packages/")
+    }
+
+    /// distro packages (`.deb`, `.rpm`) are kept apart from the updater bundles and the other installers
+    #[instrument(ret)]
+    pub fn derive_package_s3_key(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+        file_name: &str,
+    ) -> String {
+        format!(
+            "{}{}/{version}/{file_name}",
+            derive_packages_prefix(branch_name),
+            serde_variant::to_variant_name(&target).expect("this will always serialize")
+        )
+    }
+
+    /// the web frontend of the branch, synced by `deploy-frontend`
+    pub fn derive_frontend_prefix(branch_name: &str) -> String {
+        format!("{branch_name}/frontend/")
+    }
+
+    /// debug symbols of a build, kept apart from everything served to users
+    #[instrument(ret)]
+    pub fn derive_symbols_prefix(
+        branch_name: &str,
+        target: &RustTarget,
+        version: &str,
+        git_commit_hash: &str,
+    ) -> String {
+        format!(
+            "{branch_name}/symbols/{}/{version}/{git_commit_hash}/",
+            serde_variant::to_variant_name(&target).expect("this will always serialize")
+        )
+    }
+
+    /// stable key always holding the latest installer of the given kind, e.g. `release/downloads/x86_64-unknown-linux-gnu/latest.deb`
+    #[instrument(ret)]
+    pub fn derive_download_permalink_s3_key(
+        branch_name: &str,
+        target: &RustTarget,
+        suffix: &str,
+    ) -> String {
+        format!(
+            "{}{}/latest{suffix}",
+            derive_downloads_prefix(branch_name),
+            serde_variant::to_variant_name(&target).expect("this will always serialize")
+        )
+    }
+
+    #[instrument(ret)]
+    pub fn derive_downloads_manifest_s3_key(branch_name: &str) -> String {
+        format!("{}downloads.json", derive_downloads_prefix(branch_name))
+    }
+
+    #[instrument(ret)]
+    pub fn derive_downloads_page_s3_key(branch_name: &str) -> String {
+        format!("{}index.html", derive_downloads_prefix(branch_name))
+    }
+
     #[instrument(ret)]
     pub fn derive_release_file_s3_url(
         branch_name: &str,
         target: &RustTarget,
         s3_config: &S3Config,
     ) -> String {
+        s3_key_url(s3_config, &derive_release_file_s3_key(branch_name, target))
+    }
+
+    /// characters that break a url path segment, e.g. spaces in `My App_1.2.3_x64.msi.zip` (non-ascii is always encoded)
+    const PATH_SEGMENT: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+        .add(b' ')
+        .add(b'"')
+        .add(b'#')
+        .add(b'%')
+        .add(b'+')
+        .add(b'<')
+        .add(b'>')
+        .add(b'?')
+        .add(b'`')
+        .add(b'{')
+        .add(b'}');
+
+    /// percent-encodes every segment of the key, `/` separators are kept
+    pub fn encode_key(key: &str) -> String {
+        key.split('/')
+            .map(|segment| percent_encoding::utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+            .join("/")
+    }
+
+    /// public url of the key, safe to put into manifests
+    pub fn s3_key_url(s3_config: &S3Config, key: &str) -> String {
         use s3_handler::handle_s3::{
             s3_path_with_subdirectory,
             s3_url,
         };
         s3_url(
             s3_config,
-            &s3_path_with_subdirectory(s3_config, &derive_release_file_s3_key(branch_name, target)),
+            &encode_key(&s3_path_with_subdirectory(s3_config, key)),
         )
     }
 
@@ -577,7 +1770,7 @@ pub mod namespacing {
         Ok(format!(
             "{}/{}/{git_commit_hash}/{}",
             derive_release_base_key(branch_name, target),
-            &tauri_conf_json.package.version,
+            tauri_conf_json.version(),
             filename
         ))
     }
@@ -588,6 +1781,126 @@ pub mod namespacing {
         use eyre::Result;
         use s3_helpers::BucketConfig;
 
+        #[test]
+        fn test_release_namespace() -> Result<()> {
+            let rules: ChannelRules = toml::from_str(
+                "[[rules]]\nbranch = \"main\"\nchannel = \"stable\"\n\n[[rules]]\nbranch = \"release/*\"\nchannel = \"beta\"\n",
+            )?;
+            assert_eq!(derive_release_namespace("main", None, &rules)?, "stable");
+            assert_eq!(
+                derive_release_namespace("release/2.0", None, &rules)?,
+                "beta"
+            );
+            assert_eq!(
+                derive_release_namespace("feature/x", None, &rules)?,
+                "feature/x"
+            );
+            assert_eq!(
+                derive_release_namespace("main", Some(ReleaseChannel::Nightly), &rules)?,
+                "nightly"
+            );
+            assert_eq!(
+                derive_release_namespace("main", None, &ChannelRules::default())?,
+                "main"
+            );
+            assert_eq!(
+                derive_namespace_owner("develop", Some(ReleaseChannel::Beta), &rules)?,
+                "channel beta"
+            );
+            assert_eq!(
+                derive_namespace_owner("feature/x", None, &rules)?,
+                "branch feature/x"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn test_branch_names_are_sanitized() -> Result<()> {
+            assert_eq!(sanitize_branch_name("release/2024.06")?, "release/2024.06");
+            assert_eq!(sanitize_branch_name("feature/żółw")?, "feature/zolw");
+            assert_eq!(
+                sanitize_branch_name("feature/żółw")?,
+                sanitize_branch_name("feature/zolw")?
+            );
+            assert!(sanitize_branch_name("").is_err());
+            assert!(sanitize_branch_name("feature//x").is_err());
+            assert!(sanitize_branch_name("feature/../main").is_err());
+            assert!(sanitize_branch_name("a\tb").is_err());
+            assert!(sanitize_branch_name(&"x".repeat(MAX_BRANCH_NAME_LEN + 1)).is_err());
+            assert!(sanitize_branch_name("latest/x").is_err());
+            assert_eq!(sanitize_branch_name("latest-fixes")?, "latest-fixes");
+            assert_eq!(derive_identifier_suffix("feature/żółw"), "feature_zolw");
+            Ok(())
+        }
+
+        #[test]
+        fn test_latest_alias_keys() {
+            assert_eq!(
+                derive_latest_alias_s3_key("release/2.0", "release/2.0/win64/release-notes.json"),
+                "latest/win64/release-notes.json"
+            );
+            assert_eq!(
+                derive_latest_alias_s3_key("stable", "stable/latest.json"),
+                "latest/latest.json"
+            );
+        }
+
+        #[test]
+        fn test_localized_keys() {
+            assert_eq!(
+                derive_localized_s3_key("main/release-notes.json", "de"),
+                "main/release-notes.de.json"
+            );
+            assert_eq!(
+                localized_s3_key_locale("main/release-notes.json", "main/release-notes.pt-BR.json"),
+                Some("pt-BR")
+            );
+            assert_eq!(
+                localized_s3_key_locale("main/release-notes.json", "main/release-notes.json.sig"),
+                None
+            );
+            assert_eq!(
+                localized_s3_key_locale(
+                    "main/release-notes.json",
+                    "main/release-notes.de.json.sig"
+                ),
+                None
+            );
+            assert!(is_locale("zh-Hant"));
+            assert!(!is_locale("de/../x"));
+            assert!(!is_locale("d"));
+        }
+
+        #[test]
+        fn test_keys_are_percent_encoded_in_urls() {
+            assert_eq!(
+                encode_key(
+                    "release/x86_64-pc-windows-msvc/1.2.3/deadbeef/My App_1.2.3_x64.msi.zip"
+                ),
+                "release/x86_64-pc-windows-msvc/1.2.3/deadbeef/My%20App_1.2.3_x64.msi.zip"
+            );
+            assert_eq!(
+                encode_key("release/downloads/Zażółć #1+2?.dmg"),
+                "release/downloads/Za%C5%BC%C3%B3%C5%82%C4%87%20%231%2B2%3F.dmg"
+            );
+        }
+
+        #[test]
+        fn test_ascii_identifier() {
+            assert_eq!(
+                ascii_identifier("pl.pieprzyk.Zażółć-Gęślą-Jaźń.release"),
+                "pl.pieprzyk.Zazolc-Gesla-Jazn.release"
+            );
+            assert_eq!(
+                ascii_identifier("pl.pieprzyk.Łódź Śródmieście.feature_x"),
+                "pl.pieprzyk.Lodz-Srodmiescie.feature_x"
+            );
+            assert_eq!(
+                ascii_identifier("com.random-company.random-app"),
+                "com.random-company.random-app"
+            );
+        }
+
         #[test]
         fn test_release_file_s3_path() -> Result<()> {
             const TAURI_CONF_JSON: &str = include_str!("../test_data/tauri.conf.json");
@@ -600,6 +1913,58 @@ pub mod namespacing {
             Ok(())
         }
         #[test]
+        fn test_shared_release_file_s3_key() {
+            assert_eq!(
+                derive_shared_release_file_s3_key("release"),
+                "release/release-notes.json"
+            );
+        }
+        #[test]
+        fn test_staged_release_file_s3_key() {
+            assert_eq!(
+                derive_staged_release_file_s3_key("release", &RustTarget::Win64, "1.7.9"),
+                "release/x86_64-pc-windows-msvc/1.7.9/staged-release.json"
+            );
+        }
+        #[test]
+        fn test_contents_s3_key() {
+            assert_eq!(
+                derive_contents_s3_key("release", &RustTarget::Win64, "1.7.9", "deadbeef"),
+                "release/x86_64-pc-windows-msvc/1.7.9/deadbeef/contents.json"
+            );
+        }
+        #[test]
+        fn test_receipt_s3_key() {
+            assert_eq!(
+                derive_receipt_s3_key("release", &RustTarget::Win64, "1.7.9", "deploy"),
+                "release/receipts/x86_64-pc-windows-msvc/1.7.9/deploy.json"
+            );
+            assert!(
+                derive_receipt_s3_key("release", &RustTarget::Win64, "1.7.9", "deploy")
+                    .starts_with(&derive_receipts_prefix("release"))
+            );
+        }
+        #[test]
+        fn test_download_s3_key() {
+            assert_eq!(
+                derive_download_s3_key("release", &RustTarget::MacOsX86_64, "1.7.9", "App.dmg"),
+                "release/downloads/x86_64-apple-darwin/1.7.9/App.dmg"
+            );
+            assert_eq!(
+                derive_package_s3_key(
+                    "release",
+                    &RustTarget::Linux64,
+                    "1.7.9",
+                    "app_1.7.9_amd64.deb"
+                ),
+                "release/packages/x86_64-unknown-linux-gnu/1.7.9/app_1.7.9_amd64.deb"
+            );
+            assert_eq!(
+                derive_symbols_prefix("release", &RustTarget::Win64, "1.7.9", "deadbeef"),
+                "release/symbols/x86_64-pc-windows-msvc/1.7.9/deadbeef/"
+            );
+        }
+        #[test]
         fn test_release_file_s3_url() -> Result<()> {
             assert_eq!(
                 derive_release_file_s3_url(
@@ -621,6 +1986,42 @@ pub mod namespacing {
         }
     }
 }
+pub mod aggregate;
+pub mod appcast;
+pub mod artifacts;
+pub mod build;
+pub mod bump;
+pub mod cache;
+pub mod cdn;
+pub mod checksum;
+pub mod ci;
+pub mod compression;
+pub mod config;
+pub mod deploy;
+pub mod downloads;
+pub mod files;
+pub mod frontend;
+pub mod github;
+pub mod history;
+pub mod index;
+pub mod links;
+pub mod lock;
+pub mod metainfo;
+pub mod minisign;
+pub mod notes;
+pub mod notify;
+pub mod packaging;
+pub mod policy;
+pub mod queue;
+pub mod receipt;
+pub mod report;
+pub mod storage;
+pub mod symbols;
+pub mod tag;
+pub mod teardown;
+pub mod triage;
+pub mod zsync;
+
 const DEFAULT_TAURI_CONF_JSON_PATH: &str = "./src-tauri/tauri.conf.json";
 
 /// should return "./src-tauri/target/release/bundle/"
@@ -638,18 +2039,92 @@ fn release_assets_path(target: &RustTarget) -> Result<PathBuf> {
     candidates.iter().find(|p| p.exists()).ok_or_else(|| eyre::eyre!("no candidate for release target directory, tried: {candidates:?}")).cloned()
 }
 
+/// targets with a bundle dir of their own under `src-tauri/target/<triple>/release/bundle`
+fn built_targets() -> Vec<RustTarget> {
+    RustTarget::into_enum_iter()
+        .filter(|target| {
+            serde_variant::to_variant_name(target)
+                .map(|triple| {
+                    Path::new("./src-tauri/target")
+                        .join(triple)
+                        .join("release")
+                        .join("bundle")
+                        .exists()
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     /// must be run before tauri action, tauri.conf.json needs to be patched in order for updater to reference the correct S3 release manifest file.
     Patch,
-    /// this builds and publishes the release according to s3 config
-    Upload {
-        #[clap(short, long, value_name = "DIR")]
-        release_dir: Option<PathBuf>,
-        /// this stage also cleans up release artifacts after uploading them - by default rust-cache action saves them all which makes the cache grow out of control
-        #[clap(short, long)]
-        cleanup: bool,
-    },
+    /// this builds and publishes the release according to s3 config (upload-artifacts + publish in one go)
+    Upload(UploadArgs),
+    /// patches tauri.conf.json, runs `tauri build` for the target (passing the updater signing key through) and uploads the result - the whole CI deploy in one step
+    Build(BuildArgs),
+    /// uploads the binaries and stages the release without touching the live release-notes.json - lets every CI matrix job land its binaries first
+    UploadArtifacts(ArtifactsArgs),
+    /// flips the live release-notes.json to the release staged by upload-artifacts for the current version
+    Publish(PublishArgs),
+    /// checks the live deployment against a deploy receipt emitted by publish
+    Verify(VerifyArgs),
+    /// bumps the app version in tauri.conf.json (and optionally Cargo.toml / package.json) and prints the new one
+    Bump(BumpArgs),
+    /// records a (signed) sign-off of the version in its history record, publish can require sign-offs of given roles
+    SignOff(history::SignOffArgs),
+    /// attaches labels (`known-issue: ...`, `yanked`) to a published version, shown on the downloads page
+    Annotate(history::AnnotateArgs),
+    /// marks a version as yanked, rolling the manifest back to the newest earlier non-yanked version when it is live
+    Yank(history::YankArgs),
+    /// kill switch - stops offering the live version right away, rolling the manifests back to the last known good version (or `--to`) or deleting them with `--delete`
+    Unpublish(history::UnpublishArgs),
+    /// requests every url in the published manifests and downloads page through the public domain
+    CheckLinks(report::ReportArgs),
+    /// shows what the updater of the target is (or with `--at` was) served, from the deploy receipts
+    Status(history::StatusArgs),
+    /// finds the release a crash report came from (`--version`, `--commit`) and shows its provenance, artifacts and symbols
+    Triage(triage::TriageArgs),
+    /// removes everything a discontinued app has in the bucket (after a listing and confirmation), leaving tombstones in place of its updater manifests and downloads pages
+    Teardown(teardown::TeardownArgs),
+    /// renders package manager manifests (winget, chocolatey, scoop) of the live version from its uploaded installers, optionally opening a pull request with them
+    PackageManifests(packaging::PackageManifestsArgs),
+    /// syncs a built web frontend (`dist/`) to the bucket, skipping unchanged files - `--versioned` keeps every version for `deploy-frontend rollback`
+    DeployFrontend(frontend::DeployFrontendArgs),
+    /// waits for the matrix jobs of the `--require-target`s to stage the release, then publishes every target that staged it into one combined manifest
+    Aggregate(aggregate::AggregateArgs),
+    /// lists every environment variable the deployer reads, whether it's set and whether the value comes from the environment or `.env`
+    EnvHelp,
+}
+
+#[derive(clap::Args, Debug)]
+struct UploadArgs {
+    #[clap(flatten)]
+    artifacts: ArtifactsArgs,
+    #[clap(flatten)]
+    publish: PublishArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct BuildArgs {
+    #[clap(flatten)]
+    build: build::BuildArgs,
+    #[clap(flatten)]
+    upload: UploadArgs,
+}
+
+/// points the updater at the release manifest of the branch and namespaces the bundle identifier by it
+fn patch(tauri_conf_json: &mut TauriConfJson, branch: &str, update_endpoint: String) {
+    let new_identifier = namespacing::ascii_identifier(&format!(
+        "{}.{}",
+        tauri_conf_json.identifier(),
+        namespacing::derive_identifier_suffix(branch)
+    ));
+    tauri_conf_json
+        .with_update_endpoint(update_endpoint)
+        .with_update_identifier(new_identifier)
+        .with_updater_artifacts();
 }
 
 /// CI script for easier tauri app deployment
@@ -660,248 +2135,355 @@ struct Args {
     #[clap(long, default_value_t = String::from(DEFAULT_TAURI_CONF_JSON_PATH), value_name = "FILE")]
     /// path to tauri.conf.json
     tauri_conf_json_path: String,
+    #[clap(long, value_name = "BRANCH")]
+    /// branch to deploy as, instead of the one from the CI provider's env or git - needed on a detached HEAD no branch points at
+    branch: Option<String>,
+    #[clap(long, value_name = "SHA")]
+    /// commit the release is recorded under, instead of the one from the CI provider's env or git (first 8 characters are used)
+    commit: Option<String>,
     #[clap(long)]
-    /// override rust target
-    target: Option<RustTarget>,
+    /// override rust target, repeat it to upload/publish the bundle dirs of several targets in one run into a combined manifest (implies --merge-manifest)
+    target: Vec<RustTarget>,
+    #[clap(long, conflicts_with = "target")]
+    /// process every target with a `src-tauri/target/<triple>/release/bundle` dir, e.g. cross-compiled outputs collected onto one runner
+    all_targets: bool,
+    #[clap(long)]
+    /// keep a single release-notes.json per branch shared by all targets - publishing merges the current target's platforms into the existing one instead of overwriting it
+    merge_manifest: bool,
+    #[clap(long, default_value = "v1", value_name = "v1|v2|both")]
+    /// updater manifests to publish - release-notes.json (v1), tauri 2's latest.json (v2) or both, the updater is pointed at latest.json for tauri 2 configs
+    manifest_format: release_notes_file::ManifestFormat,
+    #[clap(long, value_name = "stable|beta|nightly")]
+    /// namespace the keys (and the bundle identifier `patch` sets) by this channel instead of the branch, so renaming a branch doesn't strand its installs
+    channel: Option<namespacing::ReleaseChannel>,
+    #[clap(long, value_name = "FILE")]
+    /// toml file with `[[rules]]` mapping branch globs to channels (`branch = "develop"`, `channel = "beta"`), used when `--channel` isn't given
+    channel_rules: Option<PathBuf>,
+    #[clap(long, value_name = "TRAIN")]
+    /// release train (e.g. 2024.06) grouping releases across branches and targets, recorded in the manifests and receipts
+    train: Option<String>,
+    #[clap(long)]
+    /// publish a version that isn't newer than the live one (a downgrade or a rerun of an old commit), upload binaries over different ones under the same version and commit
+    force: bool,
+    #[clap(long, value_name = "FILE")]
+    /// toml file with `[[rules]]` extending the built-in table deciding which bundle dir files are updater archives, signatures, installers, packages or symbols
+    artifact_rules: Option<PathBuf>,
+    #[clap(long, value_name = "DURATION")]
+    /// abort upload/publish if it hasn't completed within this time (e.g. 20m), staged objects are removed and the process exits with code 124
+    deadline: Option<humantime::Duration>,
+    #[clap(flatten)]
+    retry: storage::RetryArgs,
+    #[clap(flatten)]
+    cache: cache::CacheArgs,
+    #[clap(flatten)]
+    cache_control: storage::CacheControlArgs,
+    #[clap(long = "meta", value_name = "KEY=VALUE")]
+    /// stamps every uploaded object with an `x-amz-meta-KEY` header (e.g. `--meta ci-run-id=1234`), on top of DEPLOY_METADATA
+    meta: Vec<String>,
     #[clap(subcommand)]
     command: Command,
 }
 
+/// abbreviated commit the keys are namespaced by - from the CI provider's env, else `git rev-parse`, which works on detached and shallow checkouts alike
 fn git_hash() -> Result<String> {
-    let output = std::process::Command::new("git")
-        .args(&["rev-parse", "HEAD"])
-        .output()
-        .context("faled to read git hash")?;
-    let git_hash = String::from_utf8(output.stdout)
-        .map(|v| v.trim().to_string())
-        .context("faled to read git hash")
-        .and_then(|v| {
-            if v.is_empty() {
-                Err(eyre::eyre!("empty git commit"))
-            } else {
-                Ok(v)
-            }
-        })?
-        .chars()
-        .take(8)
-        .collect();
-    Ok(git_hash)
+    if let Some(commit) = ci::commit() {
+        return Ok(commit.chars().take(8).collect());
+    }
+    let git_hash = metadata::git(&["rev-parse", "--short=8", "HEAD"])?;
+    if git_hash.is_empty() {
+        bail!("empty git commit");
+    }
+    // keys stay 8 characters long even when git needs more to be unambiguous
+    Ok(git_hash.chars().take(8).collect())
+}
+
+/// the bucket from the env, after checking the whole env for problems
+fn s3_config_from_env() -> Result<S3Config> {
+    config::validate_env()?;
+    let s3_config = S3Config::try_from_env()
+        .map_err(|e| eyre::eyre!("{e:?}"))
+        .wrap_err("getting s3 config from env")?;
+    debug!(?s3_config);
+    Ok(s3_config)
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let process_env = config::process_env_names();
     dotenv::dotenv().ok();
     color_eyre::install().ok();
     tracing_subscriber::fmt::init();
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    config::ensure_command_allowed(matches.subcommand_name().unwrap_or_default())?;
+    storage::set_retry_policy(args.retry.clone());
+    cache::set_cache(args.cache.clone());
+    storage::set_cache_control(args.cache_control.clone());
     let path = args.tauri_conf_json_path;
-    let git_hash = git_hash().unwrap_or_else(|e| {
-        warn!("no commit hash: {e:?}");
-        warn!("using uuid instead");
-        uuid::Uuid::new_v4().to_string()
-    });
-    // tauri.conf.json
-    let tauri_conf_json_path = PathBuf::from_str(&path).wrap_err("parsing tauri.conf.json path")?;
-    let mut tauri_conf_json: TauriConfJson = std::fs::read_to_string(&tauri_conf_json_path)
-        .wrap_err("reading tauri.conf.json")
-        .and_then(|content| serde_json::from_str(&content).wrap_err("parsing tauri.conf.json"))?;
-    // metadata
-    let branch = metadata::current_branch().wrap_err("getting branch name")?;
-    let target = match args.target {
-        Some(t) => t,
+    if let Command::Bump(bump) = &args.command {
+        // needs neither s3 nor git
+        let config_path = PathBuf::from_str(&path)
+            .map(tauri_conf_json::locate)
+            .wrap_err("parsing tauri.conf.json path")?;
+        return bump::run(&config_path, bump);
+    }
+    if let Command::EnvHelp = &args.command {
+        return config::env_help(&process_env);
+    }
+    let git_hash = match args.commit {
+        Some(commit) => commit.chars().take(8).collect(),
         None => {
-            let target =
-                metadata::current_target().wrap_err("getting rust from environment target")?;
-            warn!("target not set, using {target:?}");
-            target
+            git_hash().wrap_err("getting commit hash, pass --commit outside of a git checkout")?
         }
     };
-    let release_platforms = target
-        .to_release_platform()
-        .wrap_err("getting release platform from target")?;
-    // s3 config
-    let s3_config = S3Config::try_from_env()
-        .map_err(|e| eyre::eyre!("{e:?}"))
-        .wrap_err("getting s3 config from env")?;
-
-    debug!(?s3_config);
-    match args.command {
-        Command::Patch => {
-            info!("patching {}", tauri_conf_json_path.display());
-            let new_identifier = format!(
-                "{}.{}",
-                tauri_conf_json.tauri.bundle.identifier,
-                branch.replace('/', "_").replace(' ', "_").replace(':', "_")
-            );
-            tauri_conf_json
-                .with_update_endpoint(namespacing::derive_release_file_s3_url(
-                    &branch,
-                    &target,
-                    &s3_config,
-                ))
-                .with_update_identifier(new_identifier);
-        }
-        Command::Upload {
-            release_dir,
-            cleanup,
-        } => {
-            let release_dir = match release_dir {
-                Some(r) => r,
-                None => release_assets_path(&target).wrap_err("failed to derive a release path")?,
+    // metadata
+    let git_branch = match args.branch {
+        Some(branch) => branch,
+        None => metadata::current_branch().wrap_err("getting branch name")?,
+    };
+    let channel_rules = namespacing::ChannelRules::load(args.channel_rules.as_deref())?;
+    let branch = namespacing::derive_release_namespace(&git_branch, args.channel, &channel_rules)?;
+    if branch != git_branch {
+        info!("branch [{git_branch}] publishes to [{branch}]");
+    }
+    let metadata = storage::metadata_from_env()
+        .into_iter()
+        .chain(args.meta.iter().cloned())
+        .collect_vec();
+    let metadata = storage::metadata_headers(metadata.iter().map(String::as_str))
+        .wrap_err("parsing object metadata")?;
+    if let Command::DeployFrontend(deploy_frontend) = &args.command {
+        // a plain web frontend has neither a tauri.conf.json nor a rust target
+        let s3_config = s3_config_from_env()?;
+        let fallback_endpoints = storage::fallback_endpoints_from_env();
+        let frontend = frontend::Frontend {
+            s3_config: &s3_config,
+            fallback_endpoints: &fallback_endpoints,
+            branch: &branch,
+            git_hash: &git_hash,
+        };
+        frontend.set_default_headers(&metadata);
+        frontend::deploy(&frontend, deploy_frontend).await?;
+        info!("DONE");
+        return Ok(());
+    }
+    let namespace_owner =
+        namespacing::derive_namespace_owner(&git_branch, args.channel, &channel_rules)?;
+    let mut targets = if let Command::Aggregate(_) = &args.command {
+        if args.all_targets || !args.target.is_empty() {
+            bail!(
+                "aggregate looks at every target, pick the ones it waits for with --require-target"
+            );
+        }
+        RustTarget::into_enum_iter().collect_vec()
+    } else if args.all_targets {
+        let targets = built_targets();
+        if targets.is_empty() {
+            bail!("--all-targets found no src-tauri/target/<triple>/release/bundle dir");
+        }
+        info!("found bundles of {targets:?}");
+        targets
+    } else {
+        args.target.into_iter().unique().collect_vec()
+    };
+    if targets.is_empty() {
+        let target = metadata::current_target().wrap_err("getting rust from environment target")?;
+        warn!("target not set, using {target:?}");
+        targets.push(target);
+    }
+    let multi_target = targets.len() > 1;
+    if multi_target
+        && !matches!(
+            args.command,
+            Command::Upload(_)
+                | Command::UploadArtifacts(_)
+                | Command::Publish(_)
+                | Command::Aggregate(_)
+        )
+    {
+        bail!("several targets can only be processed by upload, upload-artifacts and publish");
+    }
+    let target = targets[0].clone();
+    // tauri.conf.json
+    let load_config = |target: &RustTarget| {
+        PathBuf::from_str(&path)
+            .map(tauri_conf_json::locate)
+            .wrap_err("parsing tauri.conf.json path")
+            .and_then(|path| tauri_conf_json::LoadedConfig::load(path, target))
+    };
+    let loaded_config = load_config(&target)?;
+    let mut tauri_conf_json = loaded_config.tauri_conf_json.clone();
+    let s3_config = s3_config_from_env()?;
+    let fallback_endpoints = storage::fallback_endpoints_from_env();
+    debug!(?fallback_endpoints);
+    let deployment = deploy::Deployment {
+        tauri_conf_json: &tauri_conf_json,
+        target: &target,
+        branch: &branch,
+        git_hash: &git_hash,
+        s3_config: &s3_config,
+        fallback_endpoints: &fallback_endpoints,
+        merge_manifest: args.merge_manifest || multi_target,
+        manifest_format: args.manifest_format,
+        train: args.train.as_deref(),
+        force: args.force,
+        classifier: artifacts::Classifier::load(args.artifact_rules.as_deref())?,
+        staged_objects: Default::default(),
+    };
+    deployment.set_default_headers(&metadata);
+    let deadline = args.deadline.map(Into::into);
+    if multi_target {
+        info!("merging the manifests of {targets:?}");
+        let other_configs = targets[1..]
+            .iter()
+            .map(load_config)
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(config) = other_configs
+            .iter()
+            .find(|config| config.tauri_conf_json.version() != tauri_conf_json.version())
+        {
+            bail!(
+                "targets disagree on the version - {} in [{}], {} for {target:?}",
+                config.tauri_conf_json.version(),
+                config.path.display(),
+                tauri_conf_json.version()
+            );
+        }
+        let others = targets[1..]
+            .iter()
+            .zip(&other_configs)
+            .map(|(target, config)| deploy::Deployment {
+                tauri_conf_json: &config.tauri_conf_json,
+                target,
+                classifier: deployment.classifier.clone(),
+                staged_objects: Default::default(),
+                ..deployment
+            })
+            .collect_vec();
+        let deployments = std::iter::once(deployment).chain(others).collect_vec();
+        let (artifacts, publish) = match &args.command {
+            Command::Upload(UploadArgs { artifacts, publish }) => (Some(artifacts), Some(publish)),
+            Command::UploadArtifacts(artifacts) => (Some(artifacts), None),
+            Command::Publish(publish)
+            | Command::Aggregate(aggregate::AggregateArgs { publish, .. }) => (None, Some(publish)),
+            _ => unreachable!("checked when resolving the targets"),
+        };
+        if artifacts.is_some() {
+            deployments[0].claim_namespace(&namespace_owner).await?;
+        }
+        let deploy = async {
+            let targets = match &args.command {
+                Command::Aggregate(aggregate) => {
+                    aggregate::wait_for_targets(&deployments, aggregate).await?
+                }
+                _ => deployments.iter().collect_vec(),
             };
-
-            const EXTENSIONS_WHITELIST: &[&str] = &[
-                // macos
-                ".dmg",
-                // macos & linux
-                ".tar.gz",
-                // linux
-                ".deb",
-                ".AppImage",
-                // windows
-                ".msi",
-                ".zip",
-                // all
-                ".sig"
-
-            ];
-            let files = walkdir::WalkDir::new(&release_dir)
-                .into_iter()
-                .collect::<Result<Vec<_>, _>>()
-                .wrap_err("reading release dir entries")?
-                .into_iter()
-                .filter(|e| {
-                    e.path()
-                        .to_str()
-                        .map(
-                            |name| EXTENSIONS_WHITELIST.iter()
-                                .any(|ext| name.ends_with(ext)))
-                        .unwrap_or_default()
-                })
-                .map(|entry| entry.path().canonicalize().wrap_err("absolute path"))
-                .collect::<Result<Vec<_>, _>>()
-                .wrap_err("getting absolute paths")?;
-            let with_keys = files
-                .iter()
-                .map(|binary_file_path| {
-                    derive_binary_file_s3_key(
-                        &tauri_conf_json,
-                        &target,
-                        &branch,
-                        binary_file_path.clone(),
-                        &git_hash,
-                    )
-                    .map(|key| (binary_file_path, key))
-                })
-                .collect::<Result<Vec<_>, _>>()
-                .wrap_err("extracting s3 keys")?;
-            info!("uploading:\n{:#?}", with_keys);
-            let tasks = with_keys
-                .iter()
-                .map(|(path, key)| {
-                    handle_s3::upload_to_s3(
-                        path,
-                        &s3_config,
-                        handle_s3::s3_path_with_subdirectory(&s3_config, key),
-                    )
-                })
-                .collect_vec();
-            let urls = futures::future::try_join_all(tasks)
-                .await
-                .map_err(|e| eyre::eyre!("{e:?}"))
-                .wrap_err("uploading all binary files")?;
-
-            let binary_url = urls
-                .iter()
-                .sorted()
-                .rev()
-                .collect_vec()
-                .into_iter()
-                .find(|url| url.ends_with(".zip") || url.ends_with(".tar.gz"))
-                .ok_or_else(|| eyre::eyre!("getting zip file"))?; // TODO: this is only for windows
-            info!(binary_url);
-            let signature_file = files
-                    .iter()
-                    .find(|file| file.extension().map(|ext| ext == "sig").unwrap_or_default()) // TODO: this is only for windows
-                    .ok_or_else(|| eyre::eyre!("getting sig file"))?;
-            let signature = tokio::fs::read_to_string(signature_file).await.wrap_err("reading signature from found file")?;
-
-            info!("all files uploaded");
-            if cleanup {
-                warn!("cleaning up to prevent cache from growing out of control");
-                std::fs::remove_dir_all(&release_dir).wrap_err("cleaning up cache failed")?;
+            let deploy = deploy::deploy_targets(&targets, &metadata, artifacts, publish);
+            match publish {
+                Some(publish) => queue::run_queued(targets[0], &publish.queue, deploy).await,
+                None => deploy.await,
             }
-            let release = release_notes_file::ReleaseNotes {
-                notes: format!(
-                    "new {} release: {}",
-                    branch, tauri_conf_json.package.version
-                ),
-                version: tauri_conf_json.package.version.clone(),
-                // notes: "released new version".to_string(), // TODO: customise this
-                pub_date: time::OffsetDateTime::now_utc(),
-                platforms: release_platforms
-                    .into_iter()
-                    .map(|release_platform| {
-                        (
-                            release_platform,
-                            RemoteRelease {
-                                url: binary_url.clone(),
-                                signature: signature.clone(),
-                            },
-                        )
-                    })
-                    .collect(), // platforms: []
-                                // .into_iter()
-                                // .collect(),
-            };
-            info!(
-                " :: uploading release ::\n{}\n\n",
-                serde_json::to_string_pretty(&release).unwrap_or_default()
-            );
-            let release_local_path = {
-                let path = PathBuf::from_str("./")
-                    .wrap_err("this should work")?
-                    .join("TEMP_RELEASE_FILE.json");
-                std::fs::write(
-                    path.clone(),
-                    serde_json::to_string_pretty(&release).wrap_err("serializing release file")?,
-                )
-                .wrap_err("dumping release file to a file")?;
-                path
-            };
-            let release_key = derive_release_file_s3_key(&branch, &target);
-            info!("binaries upload successfully, generating release_file");
-            let release_file_url = handle_s3::upload_to_s3(
-                release_local_path,
-                &s3_config,
-                handle_s3::s3_path_with_subdirectory(&s3_config, &release_key),
-            )
-            .await
-            .map_err(|e| eyre::eyre!("{e:?}"))
-            .wrap_err("uploading release file to s3")?;
-
-            info!(" :: validating ::");
-            if !tauri_conf_json
-                .tauri
-                .updater
-                .endpoints
-                .iter()
-                .any(|url| url == &release_file_url)
-            {
-                error!("CRITICAL ERROR! UPDATE WILL NOT BE TRIGGERED!");
-                bail!("configuration error - release file url is '{release_file_url}', but no such endpoint was found in tauri.conf.json file. entries found: {:?}", &tauri_conf_json.tauri.updater.endpoints)
+        };
+        deploy::with_targets_deadline(&deployments, deadline, deploy).await?;
+        info!("DONE");
+        return Ok(());
+    }
+    match args.command {
+        Command::Patch => {
+            info!("patching {}", loaded_config.path.display());
+            let update_endpoint = deployment.release_file_url();
+            patch(&mut tauri_conf_json, &branch, update_endpoint);
+        }
+        Command::Build(BuildArgs {
+            build,
+            upload: UploadArgs { artifacts, publish },
+        }) => {
+            info!("patching {}", loaded_config.path.display());
+            // the build reads the patched file, the deployment keeps using the config as loaded
+            let mut patched = tauri_conf_json.clone();
+            patch(&mut patched, &branch, deployment.release_file_url());
+            loaded_config.save(&patched)?;
+            build::run(&tauri_conf_json, &target, &artifacts.tauri_cli, &build).await?;
+            deployment.claim_namespace(&namespace_owner).await?;
+            if build.stage_only {
+                let deploy = deployment.upload_artifacts(&artifacts);
+                deployment.with_deadline(deadline, deploy).await?;
+            } else {
+                let deploy = queue::run_queued(&deployment, &publish.queue, async {
+                    let staged = deployment.upload_artifacts(&artifacts).await?;
+                    deployment.publish(&staged, &publish).await
+                });
+                deployment.with_deadline(deadline, deploy).await?;
             }
-
-            info!(" ::: uploaded to [{release_key}], update is LIVE :::");
+        }
+        Command::Upload(UploadArgs { artifacts, publish }) => {
+            deployment.claim_namespace(&namespace_owner).await?;
+            let deploy = queue::run_queued(&deployment, &publish.queue, async {
+                let staged = deployment.upload_artifacts(&artifacts).await?;
+                deployment.publish(&staged, &publish).await
+            });
+            deployment.with_deadline(deadline, deploy).await?;
+        }
+        Command::UploadArtifacts(artifacts) => {
+            deployment.claim_namespace(&namespace_owner).await?;
+            let deploy = deployment.upload_artifacts(&artifacts);
+            deployment.with_deadline(deadline, deploy).await?;
+        }
+        Command::Publish(publish) => {
+            let deploy = queue::run_queued(&deployment, &publish.queue, async {
+                let staged = deployment.fetch_staged().await?;
+                deployment.publish(&staged, &publish).await
+            });
+            deployment.with_deadline(deadline, deploy).await?;
+        }
+        Command::Verify(verify) => {
+            receipt::verify(&s3_config, &verify).await?;
+        }
+        Command::SignOff(sign_off) => {
+            history::sign_off(&deployment, &sign_off).await?;
+        }
+        Command::Annotate(annotate) => {
+            history::annotate(&deployment, &annotate).await?;
+        }
+        Command::Yank(yank) => {
+            history::yank(&deployment, &yank).await?;
+        }
+        Command::Unpublish(unpublish) => {
+            history::unpublish(&deployment, &unpublish).await?;
+        }
+        Command::CheckLinks(report) => {
+            links::check(&deployment, &report).await?;
+        }
+        Command::Status(status) => {
+            history::status(&deployment, &status).await?;
+        }
+        Command::Triage(triage) => {
+            triage::triage(&deployment, &triage).await?;
+        }
+        Command::Teardown(teardown) => {
+            teardown::teardown(&deployment, &teardown).await?;
+        }
+        Command::PackageManifests(package_manifests) => {
+            packaging::run(&deployment, &package_manifests).await?;
+        }
+        Command::Aggregate(_) => unreachable!("aggregate always processes every target"),
+        Command::Bump(_) | Command::EnvHelp | Command::DeployFrontend(_) => {
+            unreachable!("handled before loading the deployment")
         }
     }
 
-    serde_json::to_string_pretty(&tauri_conf_json)
-        .wrap_err("serializing tauri.conf.json content")
-        .and_then(|conf| {
-            info!("writing to {:?}:\n\n{}\n\n", tauri_conf_json_path, conf);
-            std::fs::write(tauri_conf_json_path, &conf).wrap_err("saving tauri.conf.json")
-        })?;
+    loaded_config.save(&tauri_conf_json)?;
     info!("DONE");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        // arg ids in `requires`/`conflicts_with` are only checked when the command is built
+        Args::command().debug_assert();
+    }
+}
@@ -0,0 +1,157 @@
+use super::*;
+use crate::deploy::Deployment;
+
+/// how many times the metainfo is reread when another deploy changed it in the meantime
+const METAINFO_UPDATE_ATTEMPTS: usize = 5;
+
+const RELEASES_OPEN: &str = "  <releases>\n";
+
+/// the component the release entries are collected in, downstream packaging merges them into its own metainfo
+fn component_xml(id: &str, name: &str) -> String {
+    let escape = downloads::escape_html;
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<component type="desktop-application">
+  <id>{id}</id>
+  <metadata_license>CC0-1.0</metadata_license>
+  <name>{name}</name>
+  <summary>{name}</summary>
+  <launchable type="desktop-id">{id}.desktop</launchable>
+{RELEASES_OPEN}  </releases>
+</component>
+"#,
+        id = escape(id),
+        name = escape(name),
+    )
+}
+
+/// notes as appstream markup - `- ` and `* ` lines become list items, the other lines are joined into paragraphs
+fn description_xml(notes: &str) -> String {
+    let escape = downloads::escape_html;
+    let mut blocks: Vec<(bool, Vec<&str>)> = vec![];
+    for line in notes.lines().map(str::trim) {
+        let (is_item, text) = match line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+            Some(item) => (true, item.trim()),
+            None => (false, line),
+        };
+        match blocks.last_mut() {
+            _ if text.is_empty() => blocks.push((false, vec![])),
+            Some((last_is_item, lines)) if *last_is_item == is_item => lines.push(text),
+            _ => blocks.push((is_item, vec![text])),
+        }
+    }
+    blocks
+        .into_iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .map(|(is_item, lines)| match is_item {
+            true => format!(
+                "        <ul>\n{}        </ul>\n",
+                lines
+                    .iter()
+                    .map(|line| format!("          <li>{}</li>\n", escape(line)))
+                    .join("")
+            ),
+            false => format!("        <p>{}</p>\n", escape(&lines.join(" "))),
+        })
+        .join("")
+}
+
+fn release_xml(version: &str, date: &str, notes: &str) -> String {
+    format!(
+        "    <release version=\"{}\" date=\"{date}\">\n      <description>\n{}      </description>\n    </release>\n",
+        downloads::escape_html(version),
+        description_xml(notes)
+    )
+}
+
+/// puts the release entry first into the metainfo, replacing an earlier entry of the same version
+fn with_release(metainfo: &str, version: &str, release: &str) -> Result<String> {
+    let start = format!(
+        "    <release version=\"{}\"",
+        downloads::escape_html(version)
+    );
+    let mut metainfo = metainfo.to_string();
+    if let Some(begin) = metainfo.find(&start) {
+        let end = metainfo[begin..]
+            .find("</release>\n")
+            .map(|end| begin + end + "</release>\n".len())
+            .ok_or_else(|| eyre::eyre!("unterminated release {version} in metainfo"))?;
+        metainfo.replace_range(begin..end, "");
+    }
+    let at = metainfo
+        .find(RELEASES_OPEN)
+        .map(|at| at + RELEASES_OPEN.len())
+        .ok_or_else(|| eyre::eyre!("metainfo has no <releases>"))?;
+    metainfo.insert_str(at, release);
+    Ok(metainfo)
+}
+
+/// adds the release to `metainfo.xml` next to the linux artifacts, returns its key
+pub async fn publish(deployment: &Deployment<'_>, version: &str, notes: &str) -> Result<String> {
+    if deployment.target != &RustTarget::Linux64 {
+        warn!(
+            "metainfo :: appstream is linux only, publishing one for {:?} anyway",
+            deployment.target
+        );
+    }
+    let date = time::OffsetDateTime::now_utc().date().to_string();
+    let release = release_xml(version, &date, notes);
+    let key = namespacing::derive_metainfo_s3_key(deployment.branch, deployment.target);
+    storage::update_if_match(
+        deployment.s3_config,
+        deployment.fallback_endpoints,
+        &key,
+        "application/xml; charset=utf-8",
+        METAINFO_UPDATE_ATTEMPTS,
+        |current| {
+            let current = match current {
+                Some(current) => String::from_utf8(current.to_vec())
+                    .wrap_err_with(|| format!("bad encoding of [{key}]"))?,
+                None => component_xml(
+                    deployment.tauri_conf_json.identifier(),
+                    deployment.tauri_conf_json.product_name(),
+                ),
+            };
+            with_release(&current, version, &release).map(String::into_bytes)
+        },
+    )
+    .await
+    .wrap_err("updating metainfo")?;
+    info!(
+        "metainfo :: {}",
+        namespacing::s3_key_url(deployment.s3_config, &key)
+    );
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_entries() -> Result<()> {
+        let metainfo = component_xml("com.example.app", "App & Co");
+        assert!(metainfo.contains("<name>App &amp; Co</name>"));
+        let metainfo = with_release(
+            &metainfo,
+            "1.2.3",
+            &release_xml("1.2.3", "2024-06-01", "first"),
+        )?;
+        let metainfo = with_release(
+            &metainfo,
+            "1.3.0",
+            &release_xml("1.3.0", "2024-07-01", "fixes:\n- a <crash>\n- b\n\nthanks"),
+        )?;
+        assert!(metainfo.contains(
+            "    <release version=\"1.3.0\" date=\"2024-07-01\">\n      <description>\n        <p>fixes:</p>\n        <ul>\n          <li>a &lt;crash&gt;</li>\n          <li>b</li>\n        </ul>\n        <p>thanks</p>\n      </description>\n    </release>\n    <release version=\"1.2.3\""
+        ));
+        let republished = with_release(
+            &metainfo,
+            "1.2.3",
+            &release_xml("1.2.3", "2024-08-01", "again"),
+        )?;
+        assert_eq!(republished.matches("<release ").count(), 2);
+        assert!(republished.contains("<release version=\"1.2.3\" date=\"2024-08-01\">"));
+        Ok(())
+    }
+}
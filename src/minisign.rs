@@ -0,0 +1,237 @@
+use super::*;
+use blake2::{
+    Blake2b512,
+    Digest,
+};
+use ed25519_dalek::{
+    Signer,
+    SigningKey,
+};
+use minisign_verify::{
+    PublicKey,
+    Signature,
+};
+use tokio::io::AsyncReadExt;
+
+/// base64 encoded 32 byte ed25519 seed signing the updater manifests
+pub const MANIFEST_SIGNING_KEY_ENV: &str = "DEPLOY_MANIFEST_SIGNING_KEY";
+/// minisign public key (`RW...`) the manifest signatures are verified against
+pub const MANIFEST_PUBLIC_KEY_ENV: &str = "DEPLOY_MANIFEST_PUBLIC_KEY";
+
+/// tauri keeps both the `pubkey` and the `.sig` files as base64 of the minisign text format
+fn decode_text(encoded: &str) -> Result<String> {
+    data_encoding::BASE64
+        .decode(encoded.trim().as_bytes())
+        .wrap_err("not valid base64")
+        .and_then(|decoded| String::from_utf8(decoded).wrap_err("not valid utf-8"))
+}
+
+pub fn public_key(encoded: &str) -> Result<PublicKey> {
+    decode_text(encoded)
+        .and_then(|text| PublicKey::decode(&text).map_err(|e| eyre::eyre!("{e}")))
+        .wrap_err("bad updater pubkey")
+}
+
+pub fn signature(encoded: &str) -> Result<Signature> {
+    decode_text(encoded)
+        .and_then(|text| Signature::decode(&text).map_err(|e| eyre::eyre!("{e}")))
+        .wrap_err("bad updater signature")
+}
+
+/// checks the bundle against its `.sig` the way the updater will, reading it in chunks
+pub async fn verify_file<T: AsRef<Path>>(
+    path: T,
+    signature: &Signature,
+    public_key: &PublicKey,
+) -> Result<()> {
+    let mut verifier = public_key
+        .verify_stream(signature)
+        .map_err(|e| eyre::eyre!("{e}"))?;
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .wrap_err_with(|| format!("opening [{}] for signature check", path.as_ref().display()))?;
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await.wrap_err_with(|| {
+            format!("reading [{}] for signature check", path.as_ref().display())
+        })?;
+        if read == 0 {
+            break;
+        }
+        verifier.update(&buffer[..read]);
+    }
+    verifier.finalize().map_err(|e| eyre::eyre!("{e}"))
+}
+
+pub fn manifest_signing_key_from_env() -> Result<Option<SigningKey>> {
+    match std::env::var(MANIFEST_SIGNING_KEY_ENV) {
+        Ok(val) => receipt::decode_key(&val)
+            .map(|seed| Some(SigningKey::from_bytes(&seed)))
+            .wrap_err_with(|| format!("bad {MANIFEST_SIGNING_KEY_ENV}")),
+        Err(_) => Ok(None),
+    }
+}
+
+pub fn manifest_public_key_from_env() -> Result<Option<PublicKey>> {
+    match std::env::var(MANIFEST_PUBLIC_KEY_ENV) {
+        Ok(val) => PublicKey::from_base64(val.trim())
+            .map(Some)
+            .map_err(|e| eyre::eyre!("bad {MANIFEST_PUBLIC_KEY_ENV}: {e}")),
+        Err(_) => Ok(None),
+    }
+}
+
+/// minisign key ids are random, ours are derived from the key so they stay stable
+fn key_id(signing_key: &SigningKey) -> [u8; 8] {
+    let hash = sha2::Sha256::digest(signing_key.verifying_key().as_bytes());
+    let mut key_id = [0; 8];
+    key_id.copy_from_slice(&hash[..8]);
+    key_id
+}
+
+/// the key in the `RW...` form `minisign -P` and [`MANIFEST_PUBLIC_KEY_ENV`] take
+pub fn encode_public_key(signing_key: &SigningKey) -> String {
+    let mut key = b"Ed".to_vec();
+    key.extend_from_slice(&key_id(signing_key));
+    key.extend_from_slice(signing_key.verifying_key().as_bytes());
+    data_encoding::BASE64.encode(&key)
+}
+
+/// prehashed minisign signature of the content, checkable with `minisign -Vm {file_name} -P {public key}`
+pub fn sign(content: &[u8], file_name: &str, signing_key: &SigningKey, timestamp: i64) -> String {
+    let signature = signing_key.sign(&Blake2b512::digest(content)).to_bytes();
+    let trusted_comment = format!("timestamp:{timestamp}\tfile:{file_name}");
+    let global_signature = signing_key.sign(&[&signature[..], trusted_comment.as_bytes()].concat());
+    let signature_line = [&b"ED"[..], &key_id(signing_key), &signature].concat();
+    format!(
+        "untrusted comment: signature from {}\n{}\ntrusted comment: {trusted_comment}\n{}\n",
+        env!("CARGO_PKG_NAME"),
+        data_encoding::BASE64.encode(&signature_line),
+        data_encoding::BASE64.encode(&global_signature.to_bytes())
+    )
+}
+
+/// checks a manifest against its `.sig` written by [`sign`]
+pub fn verify_manifest(content: &[u8], signature: &str, public_key: &PublicKey) -> Result<()> {
+    let signature =
+        Signature::decode(signature).map_err(|e| eyre::eyre!("bad manifest signature: {e}"))?;
+    public_key
+        .verify(content, &signature, false)
+        .map_err(|e| eyre::eyre!("{e}"))
+}
+
+/// private keys the tauri signer reads from the environment, tauri 1 and tauri 2 names
+pub const TAURI_PRIVATE_KEY_ENVS: &[&str] = &["TAURI_PRIVATE_KEY", "TAURI_SIGNING_PRIVATE_KEY"];
+
+/// signs the bundle with `{tauri_cli} signer sign` when a tauri private key is in the environment (the signer picks the key and its password up from there too), returns the written `.sig`
+pub async fn sign_with_tauri(tauri_cli: &str, bundle: &Path) -> Result<Option<PathBuf>> {
+    if !TAURI_PRIVATE_KEY_ENVS
+        .iter()
+        .any(|name| std::env::var_os(name).is_some())
+    {
+        return Ok(None);
+    }
+    let mut command = tauri_cli.split_whitespace();
+    let program = command
+        .next()
+        .ok_or_else(|| eyre::eyre!("--tauri-cli is empty"))?;
+    info!(
+        "signing [{}] with `{tauri_cli} signer sign`",
+        bundle.display()
+    );
+    let output = tokio::process::Command::new(program)
+        .args(command)
+        .args(["signer", "sign"])
+        .arg(bundle)
+        .output()
+        .await
+        .wrap_err_with(|| format!("running `{tauri_cli} signer sign`"))?;
+    if !output.status.success() {
+        bail!(
+            "`{tauri_cli} signer sign` failed ({}):\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let mut signature = bundle.as_os_str().to_owned();
+    signature.push(".sig");
+    let signature = PathBuf::from(signature);
+    if !signature.is_file() {
+        bail!(
+            "`{tauri_cli} signer sign` succeeded but [{}] was not written",
+            signature.display()
+        );
+    }
+    Ok(Some(signature))
+}
+
+/// refuses a bundle the updater would reject, before anything gets uploaded
+pub async fn check_bundle(
+    tauri_conf_json: &TauriConfJson,
+    bundle: &Path,
+    encoded_signature: &str,
+    strict: bool,
+) -> Result<()> {
+    let encoded_key = match tauri_conf_json.updater_pubkey() {
+        Some(key) => key,
+        None if strict => bail!(
+            "no updater pubkey in tauri.conf.json, signature of [{}] can't be checked (--strict-signatures)",
+            bundle.display()
+        ),
+        None => {
+            warn!(
+                "no updater pubkey in tauri.conf.json, signature of [{}] is NOT checked",
+                bundle.display()
+            );
+            return Ok(());
+        }
+    };
+    let public_key = public_key(encoded_key)?;
+    verify_file(bundle, &signature(encoded_signature)?, &public_key)
+        .await
+        .wrap_err_with(|| {
+            format!(
+                "signature of [{}] doesn't verify against the updater pubkey in tauri.conf.json - clients would reject this update (was it signed with another TAURI_PRIVATE_KEY?)",
+                bundle.display()
+            )
+        })?;
+    info!("signature :: [{}] OK", bundle.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str = "untrusted comment: minisign public key E7620F1842B4E81F\nRWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\n";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key\nRUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=\ntrusted comment: timestamp:1556193335\tfile:test\ny/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==\n";
+
+    #[tokio::test]
+    async fn test_bundle_signature_is_checked() -> Result<()> {
+        let encode = |text: &str| data_encoding::BASE64.encode(text.as_bytes());
+        let public_key = public_key(&encode(PUBLIC_KEY))?;
+        let signature = signature(&encode(SIGNATURE))?;
+        let dir = std::env::temp_dir().join(format!("minisign-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("signed"), "test")?;
+        std::fs::write(dir.join("tampered"), "Test")?;
+        let signed = verify_file(dir.join("signed"), &signature, &public_key).await;
+        let tampered = verify_file(dir.join("tampered"), &signature, &public_key).await;
+        std::fs::remove_dir_all(&dir)?;
+        signed?;
+        assert!(tampered.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_manifest_verifies() -> Result<()> {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let public_key = PublicKey::from_base64(&encode_public_key(&signing_key))
+            .map_err(|e| eyre::eyre!("{e}"))?;
+        let manifest = br#"{"version": "1.7.9"}"#;
+        let signature = sign(manifest, "release-notes.json", &signing_key, 1718000000);
+        verify_manifest(manifest, &signature, &public_key)?;
+        assert!(verify_manifest(br#"{"version": "6.6.6"}"#, &signature, &public_key).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,733 @@
+use super::*;
+use std::collections::BTreeMap;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NotesArgs {
+    /// notes shown by the updater dialog instead of the generated `new {branch} release: {version}`
+    #[clap(
+        long,
+        value_name = "TEXT",
+        conflicts_with_all = &["notes-file", "changelog-notes", "notes-from-changelog"]
+    )]
+    pub notes: Option<String>,
+    /// like --notes, but read from a file
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = &["changelog-notes", "notes-from-changelog"]
+    )]
+    pub notes_file: Option<PathBuf>,
+    /// notes for the team only (ticket references and the like) - kept in the deploy receipt, never in the manifest shown by the app
+    #[clap(long, value_name = "TEXT", conflicts_with = "internal-notes-file")]
+    pub internal_notes: Option<String>,
+    /// like --internal-notes, but read from a file
+    #[clap(long, value_name = "FILE")]
+    pub internal_notes_file: Option<PathBuf>,
+    /// toml file with rules the published notes are checked against before anything goes live
+    #[clap(long, value_name = "FILE")]
+    pub notes_lint: Option<PathBuf>,
+    /// notes shown by the updater generated from the conventional commits since the live release (the previous `v*` tag when there's none), grouped by type
+    #[clap(long)]
+    pub changelog_notes: bool,
+    /// notes shown by the updater taken from the section of the version in a keep-a-changelog file, which has to have one
+    #[clap(long, conflicts_with = "changelog-notes")]
+    pub notes_from_changelog: bool,
+    /// the keep-a-changelog file for --notes-from-changelog
+    #[clap(long, default_value = "CHANGELOG.md", value_name = "FILE")]
+    pub changelog_file: PathBuf,
+    /// list the titles and links of the pull requests merged since the live release in the notes (needs GITHUB_TOKEN and GITHUB_REPOSITORY)
+    #[clap(
+        long,
+        conflicts_with_all = &["notes", "notes-file", "notes-from-changelog"]
+    )]
+    pub pr_notes: bool,
+    /// publish a copy of the manifests with these notes for the locale next to them (`release-notes.de.json`), repeatable
+    #[clap(long, value_name = "LOCALE=FILE")]
+    pub localized_notes: Vec<String>,
+    /// template the updater notes are rendered with, `{{version}}`, `{{branch}}`, `{{commit}}`, `{{date}}`, `{{train}}`, `{{app}}`, `{{notes}}`, `{{changelog}}` and `{{pull_requests}}` are substituted
+    #[clap(long, value_name = "FILE")]
+    pub notes_template: Option<PathBuf>,
+}
+
+/// notes of a single release, resolved from their sources before publishing
+#[derive(Debug, Clone, Default)]
+pub struct Notes {
+    /// what the updater dialog shows, `new {branch} release: {version}` when not given
+    pub public: Option<String>,
+    /// never ends up in the updater manifest
+    pub internal: Option<String>,
+    /// notes of the localized manifests, by locale
+    pub localized: BTreeMap<String, String>,
+}
+
+pub fn default_notes(branch: &str, version: &str) -> String {
+    format!("new {branch} release: {version}")
+}
+
+impl Notes {
+    pub fn public_or_default(&self, branch: &str, version: &str) -> String {
+        self.public
+            .clone()
+            .unwrap_or_else(|| default_notes(branch, version))
+    }
+}
+
+/// `type(scope)!: description`, anything else isn't a conventional commit
+fn parse_conventional(subject: &str) -> Option<(&str, bool, &str)> {
+    let (head, description) = subject.split_once(": ")?;
+    let (head, breaking) = match head.strip_suffix('!') {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+    let kind = match head.split_once('(') {
+        Some((kind, scope)) if scope.ends_with(')') => kind,
+        Some(_) => return None,
+        None => head,
+    };
+    let description = description.trim();
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) || description.is_empty() {
+        return None;
+    }
+    Some((kind, breaking, description))
+}
+
+/// groups shown to users, in this order - chores, docs, ci and the like are left out
+const CHANGELOG_GROUPS: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug fixes"),
+    ("perf", "Performance"),
+];
+
+/// the user facing conventional commits grouped by type, breaking changes of any type first, `None` when there are none
+pub fn changelog<'a>(subjects: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let commits = subjects
+        .into_iter()
+        .filter_map(parse_conventional)
+        .collect_vec();
+    let breaking = commits
+        .iter()
+        .filter(|(_, breaking, _)| *breaking)
+        .map(|(_, _, description)| *description)
+        .collect_vec();
+    let groups = std::iter::once(("Breaking changes", breaking))
+        .chain(CHANGELOG_GROUPS.iter().map(|(kind, title)| {
+            let descriptions = commits
+                .iter()
+                .filter(|(commit_kind, breaking, _)| commit_kind == kind && !breaking)
+                .map(|(_, _, description)| *description)
+                .collect_vec();
+            (*title, descriptions)
+        }))
+        .filter(|(_, descriptions)| !descriptions.is_empty())
+        .map(|(title, descriptions)| {
+            format!(
+                "{title}:\n{}",
+                descriptions
+                    .iter()
+                    .map(|description| format!("- {description}"))
+                    .join("\n")
+            )
+        })
+        .collect_vec();
+    match groups.is_empty() {
+        true => None,
+        false => Some(groups.join("\n\n")),
+    }
+}
+
+/// substitutes `{{ name }}` placeholders (the subset of handlebars and tera without blocks), an unknown name is an error
+pub fn render_template(template: &str, variables: &BTreeMap<&str, String>) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .map(|end| start + end)
+            .ok_or_else(|| eyre::eyre!("unclosed {{{{ in the notes template"))?;
+        let name = rest[start + 2..end].trim();
+        let value = variables.get(name).ok_or_else(|| {
+            eyre::eyre!(
+                "unknown variable [{name}] in the notes template, known are: {}",
+                variables.keys().join(", ")
+            )
+        })?;
+        rendered.push_str(value);
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// `## [1.2.3] - 2024-06-01`, `## 1.2.3` or `## v1.2.3`
+fn is_version_heading(line: &str, version: &str) -> bool {
+    let heading = match line.strip_prefix("## ") {
+        Some(heading) => heading.trim(),
+        None => return false,
+    };
+    let heading = heading
+        .strip_prefix('[')
+        .and_then(|heading| heading.split_once(']'))
+        .map(|(name, _)| name)
+        .unwrap_or_else(|| heading.split_whitespace().next().unwrap_or_default());
+    heading.strip_prefix('v').unwrap_or(heading) == version
+}
+
+/// the section of the version in a keep-a-changelog file, without its heading and the link definitions
+pub fn changelog_section(changelog: &str, version: &str) -> Option<String> {
+    let section = changelog
+        .lines()
+        .skip_while(|line| !is_version_heading(line, version))
+        .skip(1)
+        .take_while(|line| !line.starts_with("## "))
+        // `[1.2.3]: https://...` link definitions follow the last section
+        .filter(|line| {
+            !(line.starts_with('[')
+                && line
+                    .split_once("]: ")
+                    .is_some_and(|(name, _)| !name.contains(' ')))
+        })
+        .join("\n");
+    Some(section.trim().to_string()).filter(|section| !section.is_empty())
+}
+
+fn pull_request_notes(pull_requests: &[github::PullRequest]) -> Option<String> {
+    match pull_requests.is_empty() {
+        true => None,
+        false => Some(format!(
+            "Pull requests:\n{}",
+            pull_requests
+                .iter()
+                .map(|pull_request| format!("- {} ({})", pull_request.title, pull_request.url))
+                .join("\n")
+        )),
+    }
+}
+
+struct Commit {
+    sha: String,
+    subject: String,
+}
+
+/// most commits a changelog covers, a base far behind (or none at all) would otherwise walk the whole history
+const MAX_CHANGELOG_COMMITS: usize = 500;
+
+/// the newest `v*` tag other than the one of this version, the range is every commit when there's none
+fn range_since_previous_tag(version: &str) -> String {
+    let previous = metadata::git(&[
+        "describe",
+        "--tags",
+        "--abbrev=0",
+        "--match",
+        "v[0-9]*",
+        "--exclude",
+        &format!("v{version}"),
+        "HEAD",
+    ])
+    .ok();
+    match &previous {
+        Some(previous) => format!("{previous}..HEAD"),
+        None if metadata::is_shallow() => {
+            warn!("notes :: no previous v* tag in this shallow clone, the changelog only covers the fetched commits - fetch the full history and tags (e.g. `fetch-depth: 0`)");
+            "HEAD".to_string()
+        }
+        None => {
+            warn!("notes :: no previous v* tag, the changelog covers every commit");
+            "HEAD".to_string()
+        }
+    }
+}
+
+/// the commits since `live_commit` (the commit of the live release), newest first - since the previous `v*` tag when
+/// nothing is live or the checkout doesn't have the commit
+fn commits_since(live_commit: Option<&str>, version: &str) -> Result<Vec<Commit>> {
+    let live_commit = live_commit.filter(|commit| {
+        let known = metadata::git(&["cat-file", "-e", &format!("{commit}^{{commit}}")]).is_ok();
+        if !known {
+            warn!("notes :: the live release's commit {commit} is not in this checkout, the changelog starts at the previous v* tag instead - fetch the full history (e.g. `fetch-depth: 0`)");
+        }
+        known
+    });
+    let range = match live_commit {
+        Some(commit) => format!("{commit}..HEAD"),
+        None => range_since_previous_tag(version),
+    };
+    let log = metadata::git(&[
+        "log",
+        "--no-merges",
+        &format!("--max-count={}", MAX_CHANGELOG_COMMITS + 1),
+        "--format=%H %s",
+        &range,
+    ])?;
+    let mut commits = log
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(sha, subject)| Commit {
+            sha: sha.to_string(),
+            subject: subject.to_string(),
+        })
+        .collect_vec();
+    if commits.len() > MAX_CHANGELOG_COMMITS {
+        warn!("notes :: more than {MAX_CHANGELOG_COMMITS} commits since the previous release, the changelog only covers the newest {MAX_CHANGELOG_COMMITS}");
+        commits.truncate(MAX_CHANGELOG_COMMITS);
+    }
+    Ok(commits)
+}
+
+async fn pull_requests_of(commits: &[Commit]) -> Result<Option<String>> {
+    let shas = commits
+        .iter()
+        .map(|commit| commit.sha.clone())
+        .collect_vec();
+    let pull_requests = github::merged_pull_requests(&shas)
+        .await
+        .wrap_err("looking up merged pull requests")?;
+    Ok(pull_request_notes(&pull_requests))
+}
+
+fn subjects(commits: &[Commit]) -> impl Iterator<Item = &str> {
+    commits.iter().map(|commit| commit.subject.as_str())
+}
+
+impl NotesArgs {
+    /// what the updater shows, `None` for the default notes
+    async fn public_notes(
+        &self,
+        version: &str,
+        live_commit: Option<&str>,
+    ) -> Result<Option<String>> {
+        let given = match (&self.notes, &self.notes_file) {
+            (Some(notes), _) => Some(notes.clone()),
+            (None, Some(path)) => Some(
+                tokio::fs::read_to_string(path)
+                    .await
+                    .wrap_err_with(|| format!("reading notes from [{}]", path.display()))?,
+            ),
+            (None, None) => None,
+        };
+        if let Some(given) = given {
+            let given = given.trim();
+            if given.is_empty() {
+                bail!("the given release notes are empty");
+            }
+            return Ok(Some(given.to_string()));
+        }
+        if self.notes_from_changelog {
+            let path = &self.changelog_file;
+            let changelog = tokio::fs::read_to_string(path)
+                .await
+                .wrap_err_with(|| format!("reading changelog [{}]", path.display()))?;
+            return changelog_section(&changelog, version)
+                .map(Some)
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "no (or an empty) section for {version} in [{}]",
+                        path.display()
+                    )
+                });
+        }
+        if !self.changelog_notes && !self.pr_notes {
+            return Ok(None);
+        }
+        let commits =
+            commits_since(live_commit, version).wrap_err("reading commits for the changelog")?;
+        let changelog = match self.changelog_notes {
+            true => changelog(subjects(&commits)),
+            false => None,
+        };
+        let pull_requests = match self.pr_notes {
+            true => pull_requests_of(&commits).await?,
+            false => None,
+        };
+        let generated = [changelog, pull_requests]
+            .into_iter()
+            .flatten()
+            .collect_vec();
+        if generated.is_empty() {
+            warn!(
+                "notes :: nothing user facing since the previous release, using the default notes"
+            );
+            return Ok(None);
+        }
+        Ok(Some(generated.join("\n\n")))
+    }
+
+    /// `variables` are substituted as they are, `notes` is the resolved (or default) notes and `changelog` the conventional commits since the live release
+    async fn rendered(
+        &self,
+        template: &Path,
+        version: &str,
+        live_commit: Option<&str>,
+        notes: String,
+        mut variables: BTreeMap<&str, String>,
+    ) -> Result<String> {
+        let template = tokio::fs::read_to_string(template)
+            .await
+            .wrap_err_with(|| format!("reading notes template [{}]", template.display()))?;
+        let uses_pull_requests = template.contains("pull_requests");
+        let commits = match template.contains("changelog") || uses_pull_requests {
+            true => {
+                commits_since(live_commit, version).wrap_err("reading commits for the changelog")?
+            }
+            false => vec![],
+        };
+        let pull_requests = match uses_pull_requests {
+            true => pull_requests_of(&commits).await?,
+            false => None,
+        };
+        variables.insert("notes", notes);
+        variables.insert(
+            "changelog",
+            changelog(subjects(&commits)).unwrap_or_default(),
+        );
+        variables.insert("pull_requests", pull_requests.unwrap_or_default());
+        let rendered = render_template(&template, &variables)?;
+        let rendered = rendered.trim();
+        if rendered.is_empty() {
+            bail!("the notes template rendered empty notes");
+        }
+        Ok(rendered.to_string())
+    }
+
+    /// `variables` fill the `--notes-template`, see [`NotesArgs::rendered`] - generated notes cover the commits since `live_commit`
+    pub async fn resolve(
+        &self,
+        version: &str,
+        live_commit: Option<&str>,
+        default: &str,
+        variables: BTreeMap<&str, String>,
+    ) -> Result<Notes> {
+        let public = self.public_notes(version, live_commit).await?;
+        let public = match &self.notes_template {
+            Some(template) => {
+                let notes = public.unwrap_or_else(|| default.to_string());
+                Some(
+                    self.rendered(template, version, live_commit, notes, variables)
+                        .await?,
+                )
+            }
+            None => public,
+        };
+        let internal = match (&self.internal_notes, &self.internal_notes_file) {
+            (Some(notes), _) => Some(notes.clone()),
+            (None, Some(path)) => {
+                Some(tokio::fs::read_to_string(path).await.wrap_err_with(|| {
+                    format!("reading internal notes from [{}]", path.display())
+                })?)
+            }
+            (None, None) => None,
+        };
+        let mut localized = BTreeMap::new();
+        for entry in &self.localized_notes {
+            let (locale, path) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre::eyre!("--localized-notes {entry} is not LOCALE=FILE"))?;
+            if !namespacing::is_locale(locale) {
+                bail!("--localized-notes {entry} :: [{locale}] is not a locale like de or pt-BR");
+            }
+            let notes = tokio::fs::read_to_string(path)
+                .await
+                .wrap_err_with(|| format!("reading {locale} notes from [{path}]"))?;
+            let notes = notes.trim();
+            if notes.is_empty() {
+                bail!("the {locale} notes in [{path}] are empty");
+            }
+            localized.insert(locale.to_string(), notes.to_string());
+        }
+        Ok(Notes {
+            public,
+            localized,
+            internal: internal
+                .map(|notes| notes.trim().to_string())
+                .filter(|notes| !notes.is_empty()),
+        })
+    }
+
+    /// whether the notes are generated from the commits since the live release, which `resolve` then needs
+    pub fn reads_commits(&self) -> bool {
+        self.changelog_notes || self.pr_notes || self.notes_template.is_some()
+    }
+
+    /// rules of the channel, `None` when linting is not configured
+    pub async fn lint_rules(&self, channel: &str) -> Result<Option<LintRules>> {
+        let path = match &self.notes_lint {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let config: LintConfig = tokio::fs::read_to_string(path)
+            .await
+            .wrap_err_with(|| format!("reading notes lint config [{}]", path.display()))
+            .and_then(|content| toml::from_str(&content).wrap_err("parsing notes lint config"))?;
+        Ok(Some(config.for_channel(channel)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LintRules {
+    pub max_line_length: Option<usize>,
+    /// matched case insensitively, as whole words
+    pub forbidden_words: Vec<String>,
+    /// flags of features that are not released yet, must not leak into the notes
+    pub unreleased_feature_flags: Vec<String>,
+    /// `[text](target)` links with a missing or malformed target
+    pub check_links: bool,
+}
+
+/// top level rules apply to every channel without its own `[channels.<name>]` table, which replaces them as a whole
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct LintConfig {
+    #[serde(flatten)]
+    default: LintRules,
+    channels: BTreeMap<String, LintRules>,
+}
+
+impl LintConfig {
+    fn for_channel(mut self, channel: &str) -> LintRules {
+        self.channels.remove(channel).unwrap_or(self.default)
+    }
+}
+
+fn contains_word(line: &str, word: &str) -> bool {
+    let word = word.to_lowercase();
+    line.to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .any(|candidate| candidate == word)
+}
+
+/// targets of `[text](target)` links, `None` for a link that is never closed
+fn link_targets(line: &str) -> Vec<Option<&str>> {
+    line.match_indices("](")
+        .map(|(start, _)| {
+            let target = &line[start + 2..];
+            target.find(')').map(|end| &target[..end])
+        })
+        .collect()
+}
+
+fn is_valid_link_target(target: &str) -> bool {
+    !target.is_empty()
+        && !target.contains(char::is_whitespace)
+        && ["https://", "http://", "mailto:", "#"]
+            .iter()
+            .any(|prefix| target.starts_with(prefix) && target.len() > prefix.len())
+}
+
+/// subject of the lint findings in reports
+const NOTES_SUBJECT: &str = "release notes";
+
+/// every problem found, with the line it was found on
+pub fn lint(notes: &str, rules: &LintRules) -> Vec<report::Finding> {
+    notes
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let problem = |rule: &str, message: String| {
+                report::Finding::error(rule, NOTES_SUBJECT, message).at_line(index + 1)
+            };
+            let mut problems = vec![];
+            if let Some(max) = rules.max_line_length {
+                let length = line.chars().count();
+                if length > max {
+                    problems.push(problem(
+                        "line-length",
+                        format!("{length} characters long, max is {max}"),
+                    ));
+                }
+            }
+            for word in &rules.forbidden_words {
+                if contains_word(line, word) {
+                    problems.push(problem(
+                        "forbidden-word",
+                        format!("forbidden word [{word}]"),
+                    ));
+                }
+            }
+            for flag in &rules.unreleased_feature_flags {
+                if contains_word(line, flag) {
+                    problems.push(problem(
+                        "unreleased-feature",
+                        format!("mentions unreleased feature [{flag}]"),
+                    ));
+                }
+            }
+            if rules.check_links {
+                for target in link_targets(line) {
+                    match target {
+                        Some(target) if is_valid_link_target(target) => {}
+                        Some(target) => problems.push(problem(
+                            "broken-link",
+                            format!("broken link target [{target}]"),
+                        )),
+                        None => problems.push(problem("broken-link", "unclosed link".to_string())),
+                    }
+                }
+            }
+            problems
+        })
+        .collect()
+}
+
+/// fails listing every problem at once, so all of them can be fixed in one go
+pub fn check(notes: &str, rules: &LintRules, report_args: &report::ReportArgs) -> Result<()> {
+    let problems = lint(notes, rules);
+    let findings = if problems.is_empty() {
+        vec![report::Finding::pass("notes-lint", NOTES_SUBJECT)]
+    } else {
+        problems
+    };
+    report::write(report_args, "notes-lint", &findings)?;
+    let problems = findings
+        .iter()
+        .filter(|finding| finding.is_error())
+        .collect_vec();
+    if problems.is_empty() {
+        return Ok(());
+    }
+    problems.iter().for_each(|problem| {
+        error!(
+            "notes lint :: line {}: {}",
+            problem.line.unwrap_or_default(),
+            problem.message
+        )
+    });
+    bail!(
+        "release notes failed the lint ({} problems)",
+        problems.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blank_internal_notes_are_dropped() -> Result<()> {
+        let args = NotesArgs {
+            notes: Some(" shown by the updater\n".to_string()),
+            notes_file: None,
+            internal_notes: Some(" \n".to_string()),
+            internal_notes_file: None,
+            notes_lint: None,
+            changelog_notes: false,
+            notes_from_changelog: false,
+            changelog_file: PathBuf::from("CHANGELOG.md"),
+            pr_notes: false,
+            localized_notes: vec![],
+            notes_template: None,
+        };
+        let notes = args
+            .resolve("1.0.0", None, "new main release: 1.0.0", BTreeMap::new())
+            .await?;
+        assert_eq!(notes.internal, None);
+        assert_eq!(notes.public.as_deref(), Some("shown by the updater"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_changelog_groups_conventional_commits() {
+        let subjects = [
+            "feat(updater): resume interrupted downloads",
+            "fix: crash when the config is empty",
+            "chore: bump deps",
+            "Merge branch 'main'",
+            "feat!: drop windows 7",
+            "perf: faster startup",
+            "fix(ui) missing colon",
+        ];
+        assert_eq!(
+            changelog(subjects).as_deref(),
+            Some(
+                "Breaking changes:\n- drop windows 7\n\nFeatures:\n- resume interrupted downloads\n\nBug fixes:\n- crash when the config is empty\n\nPerformance:\n- faster startup"
+            )
+        );
+        assert_eq!(changelog(["chore: nothing for users"]), None);
+    }
+
+    #[test]
+    fn test_pull_request_notes() {
+        let pull_requests = [github::PullRequest {
+            number: 12,
+            title: "Resume interrupted downloads".to_string(),
+            url: "https://github.com/o/r/pull/12".to_string(),
+        }];
+        assert_eq!(
+            pull_request_notes(&pull_requests).as_deref(),
+            Some("Pull requests:\n- Resume interrupted downloads (https://github.com/o/r/pull/12)")
+        );
+        assert_eq!(pull_request_notes(&[]), None);
+    }
+
+    #[test]
+    fn test_render_template() -> Result<()> {
+        let variables = [
+            ("version", "1.2.3".to_string()),
+            ("branch", "main".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            render_template("{{version}} ({{ branch }}) {{version}}", &variables)?,
+            "1.2.3 (main) 1.2.3"
+        );
+        assert!(render_template("{{ versoin }}", &variables).is_err());
+        assert!(render_template("{{ version", &variables).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_changelog_section() {
+        let changelog = "# Changelog\n\n## [Unreleased]\n\n## [1.2.3] - 2024-06-01\n### Added\n- resumable downloads\n\n## [1.2.2] - 2024-05-01\n### Fixed\n- crash on start\n\n[1.2.3]: https://example.com/compare/v1.2.2...v1.2.3\n[1.2.2]: https://example.com/releases/v1.2.2\n";
+        assert_eq!(
+            changelog_section(changelog, "1.2.3").as_deref(),
+            Some("### Added\n- resumable downloads")
+        );
+        assert_eq!(
+            changelog_section(changelog, "1.2.2").as_deref(),
+            Some("### Fixed\n- crash on start")
+        );
+        assert_eq!(
+            changelog_section("## v2.0.0\n- new\n", "2.0.0").as_deref(),
+            Some("- new")
+        );
+        assert_eq!(changelog_section(changelog, "1.2.4"), None);
+        assert_eq!(changelog_section(changelog, "Unreleased"), None);
+        assert_eq!(changelog_section(changelog, "1.2"), None);
+    }
+
+    fn lines(findings: Vec<report::Finding>) -> Vec<String> {
+        findings
+            .into_iter()
+            .map(|finding| {
+                format!(
+                    "line {}: {}",
+                    finding.line.unwrap_or_default(),
+                    finding.message
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_rules_per_channel() -> Result<()> {
+        let config: LintConfig = toml::from_str(
+            "max_line_length = 20\nforbidden_words = [\"TODO\"]\ncheck_links = true\n\n[channels.beta]\nunreleased_feature_flags = [\"new-editor\"]\n",
+        )?;
+        let notes = "fixed the todo list crash\nsee [docs](docs/page.md) and [site](https://example.com)\ntry new-editor";
+        assert_eq!(
+            lines(lint(notes, &config.clone().for_channel("release"))),
+            vec![
+                "line 1: 25 characters long, max is 20",
+                "line 1: forbidden word [TODO]",
+                "line 2: 56 characters long, max is 20",
+                "line 2: broken link target [docs/page.md]",
+            ]
+        );
+        assert_eq!(
+            lines(lint(notes, &config.for_channel("beta"))),
+            vec!["line 3: mentions unreleased feature [new-editor]"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,347 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::release_notes_file::StagedRelease;
+
+/// webhook payloads are signed with this key when it's set
+pub const WEBHOOK_SECRET_ENV: &str = "DEPLOY_WEBHOOK_SECRET";
+
+/// incoming webhook of a teams channel, `--teams-webhook` takes precedence - the url is a credential
+pub const TEAMS_WEBHOOK_ENV: &str = "DEPLOY_TEAMS_WEBHOOK_URL";
+
+/// bot posting to `--telegram-chat-id`
+pub const TELEGRAM_TOKEN_ENV: &str = "DEPLOY_TELEGRAM_BOT_TOKEN";
+
+pub const TELEGRAM_CHAT_ENV: &str = "DEPLOY_TELEGRAM_CHAT_ID";
+
+/// telegram refuses longer messages
+const TELEGRAM_MAX_MESSAGE_CHARS: usize = 4096;
+
+/// `sha256=<hex hmac of the body>`, the same scheme github uses for its webhooks
+pub const SIGNATURE_HEADER: &str = "x-deployer-signature-256";
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct NotifyArgs {
+    /// POST a json description of the release to this url once it's published, repeatable
+    #[clap(long = "webhook", value_name = "URL")]
+    pub webhooks: Vec<String>,
+    /// post an adaptive card announcing the release to this teams incoming webhook [default: DEPLOY_TEAMS_WEBHOOK_URL]
+    #[clap(long, value_name = "URL")]
+    pub teams_webhook: Option<String>,
+    /// tell this telegram chat (via the DEPLOY_TELEGRAM_BOT_TOKEN bot) whether publishing succeeded or failed [default: DEPLOY_TELEGRAM_CHAT_ID]
+    #[clap(long, value_name = "ID")]
+    pub telegram_chat_id: Option<String>,
+}
+
+impl NotifyArgs {
+    fn teams_webhook(&self) -> Option<String> {
+        self.teams_webhook
+            .clone()
+            .or_else(|| std::env::var(TEAMS_WEBHOOK_ENV).ok())
+            .filter(|url| !url.trim().is_empty())
+    }
+
+    /// bot token and chat, `None` when telegram is not configured
+    fn telegram(&self) -> Result<Option<(String, String)>> {
+        let chat_id = match self
+            .telegram_chat_id
+            .clone()
+            .or_else(|| std::env::var(TELEGRAM_CHAT_ENV).ok())
+            .filter(|id| !id.trim().is_empty())
+        {
+            Some(chat_id) => chat_id,
+            None => return Ok(None),
+        };
+        let token = std::env::var(TELEGRAM_TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.trim().is_empty())
+            .ok_or_else(|| {
+                eyre::eyre!("telegram chat {chat_id} is set but {TELEGRAM_TOKEN_ENV} isn't")
+            })?;
+        Ok(Some((token, chat_id)))
+    }
+}
+
+/// what a webhook receives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Published {
+    pub event: String,
+    pub app: String,
+    pub version: String,
+    pub branch: String,
+    pub target: RustTarget,
+    pub commit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub train: Option<String>,
+    /// updater platform keys the release was published for
+    pub platforms: Vec<String>,
+    pub manifest_url: String,
+    pub artifact_urls: Vec<String>,
+    pub notes: String,
+}
+
+impl Published {
+    pub fn new(deployment: &Deployment<'_>, staged: &StagedRelease, notes: &str) -> Self {
+        Self {
+            event: "published".to_string(),
+            app: deployment.tauri_conf_json.product_name().to_string(),
+            version: staged.version.clone(),
+            branch: deployment.branch.to_string(),
+            target: deployment.target.clone(),
+            commit: staged.commit.clone(),
+            train: deployment.train(staged),
+            platforms: staged
+                .platforms
+                .keys()
+                .filter_map(|platform| {
+                    serde_json::to_value(platform)
+                        .ok()
+                        .and_then(|name| name.as_str().map(str::to_string))
+                })
+                .sorted()
+                .collect(),
+            manifest_url: deployment.release_file_url(),
+            artifact_urls: staged
+                .platforms
+                .values()
+                .map(|remote| remote.url.clone())
+                .chain(staged.downloads.iter().map(|download| download.url.clone()))
+                .sorted()
+                .dedup()
+                .collect(),
+            notes: notes.to_string(),
+        }
+    }
+}
+
+fn signature(secret: &str, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        data_encoding::HEXLOWER.encode(&checksum::hmac_sha256(secret.as_bytes(), body))
+    )
+}
+
+/// `label` is logged in place of the url, which may carry a token
+async fn post_webhook(label: &str, url: &str, body: &[u8], secret: Option<&str>) -> Result<()> {
+    storage::retry(&format!("notifying {label}"), || async {
+        let request = reqwest::Client::new()
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_vec());
+        let request = match secret {
+            Some(secret) => request.header(SIGNATURE_HEADER, signature(secret, body)),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .wrap_err("sending webhook")?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("webhook failed, Got HTTP {}", status.as_u16());
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// adaptive card message for a teams incoming webhook
+fn teams_card(published: &Published) -> serde_json::Value {
+    let fact = |title: &str, value: &str| serde_json::json!({ "title": title, "value": value });
+    let facts = [
+        Some(fact("Version", &published.version)),
+        Some(fact("Branch", &published.branch)),
+        published.train.as_deref().map(|train| fact("Train", train)),
+        Some(fact("Platforms", &published.platforms.join(", "))),
+        Some(fact("Commit", &published.commit)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect_vec();
+    serde_json::json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "contentUrl": null,
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": [
+                    {
+                        "type": "TextBlock",
+                        "size": "Medium",
+                        "weight": "Bolder",
+                        "wrap": true,
+                        "text": format!("{} {} published", published.app, published.version),
+                    },
+                    { "type": "FactSet", "facts": facts },
+                    { "type": "TextBlock", "wrap": true, "text": published.notes },
+                ],
+                "actions": [{
+                    "type": "Action.OpenUrl",
+                    "title": "Manifest",
+                    "url": published.manifest_url,
+                }],
+            },
+        }],
+    })
+}
+
+fn truncated(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars.saturating_sub(1)) {
+        Some((end, _)) if text.chars().count() > max_chars => format!("{}…", &text[..end]),
+        _ => text.to_string(),
+    }
+}
+
+/// plain text, so nothing in the notes needs escaping
+fn telegram_message(published: &Published, failure: Option<&eyre::Report>) -> String {
+    let headline = match failure {
+        None => format!("{} {} published", published.app, published.version),
+        Some(_) => format!("{} {} failed to publish", published.app, published.version),
+    };
+    let details = [
+        Some(format!("branch: {}", published.branch)),
+        published
+            .train
+            .as_ref()
+            .map(|train| format!("train: {train}")),
+        Some(format!("platforms: {}", published.platforms.join(", "))),
+        Some(format!("manifest: {}", published.manifest_url)),
+    ]
+    .into_iter()
+    .flatten()
+    .join("\n");
+    let body = match failure {
+        None => published.notes.clone(),
+        Some(e) => format!("{e:#}"),
+    };
+    truncated(
+        &format!("{headline}\n{details}\n\n{body}"),
+        TELEGRAM_MAX_MESSAGE_CHARS,
+    )
+}
+
+/// url and body of a `sendMessage` call
+fn telegram_request(token: &str, chat_id: &str, text: &str) -> Result<(String, Vec<u8>)> {
+    let body = serde_json::to_vec(&serde_json::json!({
+        "chat_id": chat_id,
+        "text": text,
+        "disable_web_page_preview": true,
+    }))
+    .wrap_err("serializing telegram message")?;
+    Ok((
+        format!("https://api.telegram.org/bot{token}/sendMessage"),
+        body,
+    ))
+}
+
+/// tells the chats that take failures too, errors are only logged so they don't hide the one that failed publishing
+pub async fn notify_failure(args: &NotifyArgs, published: &Published, failure: &eyre::Report) {
+    let telegram = match args.telegram() {
+        Ok(Some(telegram)) => telegram,
+        Ok(None) => return,
+        Err(e) => return error!("notify :: [telegram] :: {e:?}"),
+    };
+    let (token, chat_id) = telegram;
+    let sent = async {
+        let (url, body) = telegram_request(
+            &token,
+            &chat_id,
+            &telegram_message(published, Some(failure)),
+        )?;
+        post_webhook("telegram", &url, &body, None).await
+    }
+    .await;
+    match sent {
+        Ok(()) => info!("notify :: [telegram] notified of the failure"),
+        Err(e) => error!("notify :: [telegram] :: {e:?}"),
+    }
+}
+
+/// every notification is sent even when one fails, the release is live either way
+pub async fn notify(args: &NotifyArgs, published: &Published) -> Result<()> {
+    let body = serde_json::to_vec(published).wrap_err("serializing webhook payload")?;
+    let secret = std::env::var(WEBHOOK_SECRET_ENV)
+        .ok()
+        .filter(|secret| !secret.is_empty());
+    let mut sends = args
+        .webhooks
+        .iter()
+        .map(|url| (url.clone(), url.clone(), body.clone(), secret.as_deref()))
+        .collect_vec();
+    if let Some(url) = args.teams_webhook() {
+        let card = serde_json::to_vec(&teams_card(published)).wrap_err("serializing teams card")?;
+        sends.push(("teams".to_string(), url, card, None));
+    }
+    if let Some((token, chat_id)) = args.telegram()? {
+        let (url, message) =
+            telegram_request(&token, &chat_id, &telegram_message(published, None))?;
+        sends.push(("telegram".to_string(), url, message, None));
+    }
+    let total = sends.len();
+    let mut failed = 0;
+    for (label, url, body, secret) in sends {
+        match post_webhook(&label, &url, &body, secret).await {
+            Ok(()) => info!("notify :: [{label}] notified of {}", published.version),
+            Err(e) => {
+                error!("notify :: [{label}] :: {e:?}");
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {total} notifications failed, the release is published regardless");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_matches_github_scheme() {
+        // from the github docs on validating webhook deliveries
+        assert_eq!(
+            signature("It's a Secret to Everybody", b"Hello, World!"),
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+        );
+    }
+
+    #[test]
+    fn test_teams_card() {
+        let published = Published {
+            event: "published".to_string(),
+            app: "App".to_string(),
+            version: "1.2.3".to_string(),
+            branch: "main".to_string(),
+            target: RustTarget::Win64,
+            commit: "abcd1234".to_string(),
+            train: None,
+            platforms: vec!["windows-x86_64".to_string(), "win64".to_string()],
+            manifest_url: "https://cdn.example.com/main/release-notes.json".to_string(),
+            artifact_urls: vec![],
+            notes: "new main release: 1.2.3".to_string(),
+        };
+        let card = teams_card(&published);
+        let content = &card["attachments"][0]["content"];
+        assert_eq!(content["body"][0]["text"], "App 1.2.3 published");
+        assert_eq!(
+            content["body"][1]["facts"][2],
+            serde_json::json!({ "title": "Platforms", "value": "windows-x86_64, win64" })
+        );
+        assert_eq!(
+            content["actions"][0]["url"],
+            "https://cdn.example.com/main/release-notes.json"
+        );
+    }
+
+    #[test]
+    fn test_telegram_message_is_truncated() {
+        assert_eq!(truncated("abcdef", 4), "abc…");
+        assert_eq!(truncated("abcd", 4), "abcd");
+        assert_eq!(truncated("żółw", 3), "żó…");
+    }
+}
@@ -0,0 +1,718 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::downloads::Download;
+use crate::release_notes_file::StagedRelease;
+use std::collections::BTreeMap;
+
+/// api key `package-manifests chocolatey --push` pushes the package with
+pub const CHOCOLATEY_API_KEY_ENV: &str = "DEPLOY_CHOCOLATEY_API_KEY";
+
+#[derive(clap::Args, Debug)]
+pub struct PackageManifestsArgs {
+    /// defaults to the version live on the branch
+    #[clap(long, value_name = "VERSION")]
+    pub version: Option<String>,
+    #[clap(subcommand)]
+    pub manager: PackageManager,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum PackageManager {
+    /// winget manifests (version, installer and default locale) of the windows installers
+    Winget(WingetArgs),
+    /// a chocolatey package (nuspec and install script) downloading the windows installer from the bucket, optionally pushed to a feed
+    Chocolatey(ChocolateyArgs),
+    /// a scoop manifest installing the nsis installer, with autoupdate urls
+    Scoop(ScoopArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct WingetArgs {
+    /// e.g. `GrupaPieprzyk.App`
+    #[clap(long, value_name = "ID")]
+    pub package_identifier: String,
+    #[clap(long, value_name = "NAME")]
+    pub publisher: String,
+    #[clap(long, default_value = "Proprietary", value_name = "LICENSE")]
+    pub license: String,
+    /// defaults to the product name
+    #[clap(long, value_name = "TEXT")]
+    pub short_description: Option<String>,
+    #[clap(long, default_value = "winget", value_name = "DIR")]
+    pub output_dir: PathBuf,
+    /// commit the manifests to a branch of `--winget-fork` and open a pull request against `--winget-repository` (needs DEPLOY_PACKAGES_GITHUB_TOKEN or GITHUB_TOKEN)
+    #[clap(long)]
+    pub pull_request: bool,
+    /// `owner/winget-pkgs`
+    #[clap(long, value_name = "OWNER/REPO")]
+    pub winget_fork: Option<String>,
+    #[clap(
+        long,
+        default_value = "microsoft/winget-pkgs",
+        value_name = "OWNER/REPO"
+    )]
+    pub winget_repository: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ChocolateyArgs {
+    /// e.g. `grupa-pieprzyk-app`
+    #[clap(long, value_name = "ID")]
+    pub package_id: String,
+    #[clap(long, value_name = "NAMES")]
+    pub authors: String,
+    /// defaults to the product name
+    #[clap(long, value_name = "TEXT")]
+    pub description: Option<String>,
+    #[clap(long, default_value = "chocolatey", value_name = "DIR")]
+    pub output_dir: PathBuf,
+    /// `choco pack` the package and `choco push` it to `--feed` with DEPLOY_CHOCOLATEY_API_KEY
+    #[clap(long)]
+    pub push: bool,
+    #[clap(
+        long,
+        default_value = "https://push.chocolatey.org/",
+        value_name = "URL"
+    )]
+    pub feed: String,
+    /// chocolatey cli used for `--push`
+    #[clap(long, default_value = "choco", value_name = "COMMAND")]
+    pub choco: String,
+}
+
+/// also flattened into publish, which updates the bucket after every publish when `--scoop-bucket` is given
+#[derive(clap::Args, Debug, Default)]
+pub struct ScoopArgs {
+    /// directory the `{app}.json` manifest is written to, e.g. a checkout of the scoop bucket repository - `package-manifests scoop` defaults to `scoop`
+    #[clap(long, value_name = "DIR")]
+    pub scoop_bucket: Option<PathBuf>,
+    /// manifest name, defaults to the product name in lower case
+    #[clap(long, value_name = "NAME")]
+    pub scoop_app: Option<String>,
+    #[clap(long, value_name = "LICENSE")]
+    pub scoop_license: Option<String>,
+    /// commit the manifest in the bucket checkout and push it
+    #[clap(long, requires = "scoop-bucket")]
+    pub scoop_push: bool,
+}
+
+const WINGET_MANIFEST_VERSION: &str = "1.6.0";
+
+const WINGET_LOCALE: &str = "en-US";
+
+/// a windows installer of the release, with the architecture of the target it was built for
+#[derive(Debug, Clone)]
+struct WindowsInstaller {
+    architecture: &'static str,
+    download: Download,
+}
+
+impl WindowsInstaller {
+    /// `wix` for the msi, `nullsoft` for the nsis `-setup.exe`
+    fn winget_type(&self) -> Option<&'static str> {
+        let file_name = self.download.file_name.to_lowercase();
+        if file_name.ends_with(".msi") {
+            Some("wix")
+        } else if file_name.ends_with("-setup.exe") {
+            Some("nullsoft")
+        } else {
+            None
+        }
+    }
+}
+
+/// the msi when both installers were uploaded for an architecture - chocolatey installs one of each at most
+fn preferred_installer<'a>(
+    installers: &'a [WindowsInstaller],
+    architecture: &str,
+) -> Option<&'a WindowsInstaller> {
+    installers
+        .iter()
+        .filter(|installer| installer.architecture == architecture)
+        .filter_map(|installer| Some((installer.winget_type()?, installer)))
+        .min_by_key(|(installer_type, _)| *installer_type != "wix")
+        .map(|(_, installer)| installer)
+}
+
+/// single quoted powershell string
+fn powershell_string(val: &str) -> String {
+    format!("'{}'", val.replace('\'', "''"))
+}
+
+/// single quoted yaml scalar
+fn yaml_string(val: &str) -> String {
+    format!("'{}'", val.replace('\'', "''"))
+}
+
+/// the staged releases of the version for both windows targets, the ones that were not built are left out
+async fn windows_installers(
+    deployment: &Deployment<'_>,
+    version: &str,
+) -> Result<Vec<WindowsInstaller>> {
+    let mut installers = vec![];
+    for (target, architecture) in [(RustTarget::Win64, "x64"), (RustTarget::Win32, "x86")] {
+        let key =
+            namespacing::derive_staged_release_file_s3_key(deployment.branch, &target, version);
+        let staged: Option<StagedRelease> =
+            storage::fetch_json(deployment.s3_config, &key)
+                .await
+                .wrap_err_with(|| format!("fetching staged release [{key}]"))?;
+        installers.extend(
+            staged
+                .into_iter()
+                .flat_map(|staged| staged.downloads)
+                .map(|download| WindowsInstaller {
+                    architecture,
+                    download,
+                }),
+        );
+    }
+    Ok(installers)
+}
+
+/// file name and content of the three manifests of a winget package version
+fn winget_manifests(
+    args: &WingetArgs,
+    package_name: &str,
+    version: &str,
+    installers: &[WindowsInstaller],
+) -> Result<Vec<(String, String)>> {
+    let id = &args.package_identifier;
+    let header = format!(
+        "PackageIdentifier: {}\nPackageVersion: {}\n",
+        yaml_string(id),
+        yaml_string(version)
+    );
+    let footer = |manifest_type: &str| {
+        format!("ManifestType: {manifest_type}\nManifestVersion: {WINGET_MANIFEST_VERSION}\n")
+    };
+    let installer_entries = installers
+        .iter()
+        .filter_map(|installer| {
+            Some(format!(
+                "- Architecture: {}\n  InstallerType: {}\n  InstallerUrl: {}\n  InstallerSha256: {}\n",
+                installer.architecture,
+                installer.winget_type()?,
+                yaml_string(&installer.download.url),
+                installer.download.sha256.to_uppercase()
+            ))
+        })
+        .join("");
+    if installer_entries.is_empty() {
+        bail!("no msi or nsis installer was uploaded for {version}, winget has nothing to install");
+    }
+    Ok(vec![
+        (
+            format!("{id}.yaml"),
+            format!(
+                "{header}DefaultLocale: {WINGET_LOCALE}\n{}",
+                footer("version")
+            ),
+        ),
+        (
+            format!("{id}.installer.yaml"),
+            format!("{header}Installers:\n{installer_entries}{}", footer("installer")),
+        ),
+        (
+            format!("{id}.locale.{WINGET_LOCALE}.yaml"),
+            format!(
+                "{header}PackageLocale: {WINGET_LOCALE}\nPublisher: {}\nPackageName: {}\nLicense: {}\nShortDescription: {}\n{}",
+                yaml_string(&args.publisher),
+                yaml_string(package_name),
+                yaml_string(&args.license),
+                yaml_string(args.short_description.as_deref().unwrap_or(package_name)),
+                footer("defaultLocale")
+            ),
+        ),
+    ])
+}
+
+/// the nuspec and `tools/chocolateyinstall.ps1` of the package
+fn chocolatey_package(
+    args: &ChocolateyArgs,
+    product_name: &str,
+    version: &str,
+    installers: &[WindowsInstaller],
+) -> Result<Vec<(String, String)>> {
+    let x64 = preferred_installer(installers, "x64");
+    let x86 = preferred_installer(installers, "x86");
+    let main = x64
+        .or(x86)
+        .ok_or_else(|| eyre::eyre!("no msi or nsis installer was uploaded for {version}, chocolatey has nothing to install"))?;
+    // both architectures have to be installed the same way
+    let x86 = x86.filter(|x86| x86.winget_type() == main.winget_type());
+    let (file_type, silent_args) = match main.winget_type() {
+        Some("wix") => ("msi", "/qn /norestart"),
+        _ => ("exe", "/S"),
+    };
+    let urls = [
+        ("url", "checksum", "checksumType", x86),
+        ("url64bit", "checksum64", "checksumType64", x64),
+    ]
+    .into_iter()
+    .filter_map(|(url, checksum, checksum_type, installer)| {
+        let installer = installer?;
+        Some(format!(
+            "  {url} = {}\n  {checksum} = {}\n  {checksum_type} = 'sha256'\n",
+            powershell_string(&installer.download.url),
+            powershell_string(&installer.download.sha256)
+        ))
+    })
+    .join("");
+    let install_script = format!(
+        "$ErrorActionPreference = 'Stop'\n$packageArgs = @{{\n  packageName = $env:ChocolateyPackageName\n  fileType = '{file_type}'\n{urls}  silentArgs = '{silent_args}'\n  validExitCodes = @(0, 3010, 1641)\n}}\nInstall-ChocolateyPackage @packageArgs\n"
+    );
+    let escape = downloads::escape_html;
+    let nuspec = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://schemas.microsoft.com/packaging/2015/06/nuspec.xsd">
+  <metadata>
+    <id>{id}</id>
+    <version>{version}</version>
+    <title>{title}</title>
+    <authors>{authors}</authors>
+    <description>{description}</description>
+  </metadata>
+  <files>
+    <file src="tools\**" target="tools" />
+  </files>
+</package>
+"#,
+        id = escape(&args.package_id),
+        version = escape(version),
+        title = escape(product_name),
+        authors = escape(&args.authors),
+        description = escape(args.description.as_deref().unwrap_or(product_name)),
+    );
+    Ok(vec![
+        (format!("{}.nuspec", args.package_id), nuspec),
+        ("tools/chocolateyinstall.ps1".to_string(), install_script),
+    ])
+}
+
+/// writes (path, content) files below the dir
+async fn write_files(dir: &Path, files: &[(String, String)]) -> Result<()> {
+    for (file_name, content) in files {
+        let path = dir.join(file_name);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .wrap_err_with(|| format!("creating [{}]", parent.display()))?;
+        }
+        tokio::fs::write(&path, content)
+            .await
+            .wrap_err_with(|| format!("writing [{}]", path.display()))?;
+        info!("package manifests :: wrote [{}]", path.display());
+    }
+    Ok(())
+}
+
+/// runs the chocolatey cli, `secret` is left out of the logged command
+async fn choco(command: &str, args: &[&str], secret: Option<&str>) -> Result<()> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| eyre::eyre!("--choco is empty"))?;
+    let shown = args
+        .iter()
+        .map(|arg| match secret {
+            Some(secret) if *arg == secret => "***",
+            _ => arg,
+        })
+        .join(" ");
+    info!("chocolatey :: `{command} {shown}`");
+    let output = tokio::process::Command::new(program)
+        .args(words)
+        .args(args)
+        .output()
+        .await
+        .wrap_err_with(|| format!("running `{command} {shown}`"))?;
+    if !output.status.success() {
+        bail!(
+            "`{command} {shown}` failed ({}):\n{}{}",
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+async fn chocolatey(
+    deployment: &Deployment<'_>,
+    version: &str,
+    args: &ChocolateyArgs,
+) -> Result<()> {
+    let installers = windows_installers(deployment, version).await?;
+    let files = chocolatey_package(
+        args,
+        deployment.tauri_conf_json.product_name(),
+        version,
+        &installers,
+    )?;
+    write_files(&args.output_dir, &files).await?;
+    if !args.push {
+        return Ok(());
+    }
+    let api_key = std::env::var(CHOCOLATEY_API_KEY_ENV)
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .ok_or_else(|| eyre::eyre!("--push needs {CHOCOLATEY_API_KEY_ENV}"))?;
+    let output_dir = args.output_dir.to_string_lossy();
+    let nuspec = args.output_dir.join(format!("{}.nuspec", args.package_id));
+    choco(
+        &args.choco,
+        &[
+            "pack",
+            &nuspec.to_string_lossy(),
+            "--output-directory",
+            &output_dir,
+        ],
+        None,
+    )
+    .await?;
+    let package = args
+        .output_dir
+        .join(format!("{}.{version}.nupkg", args.package_id));
+    // push picks the key stored for the feed up, it's never on the push command line - removed again afterwards so it
+    // doesn't stay in the chocolatey config of a shared runner
+    choco(
+        &args.choco,
+        &[
+            "apikey",
+            "add",
+            "--source",
+            &args.feed,
+            "--api-key",
+            &api_key,
+        ],
+        Some(&api_key),
+    )
+    .await?;
+    let pushed = choco(
+        &args.choco,
+        &["push", &package.to_string_lossy(), "--source", &args.feed],
+        None,
+    )
+    .await;
+    if let Err(e) = choco(
+        &args.choco,
+        &["apikey", "remove", "--source", &args.feed],
+        None,
+    )
+    .await
+    {
+        warn!(
+            "chocolatey :: failed to remove the api key stored for {} :: {e:?}",
+            args.feed
+        );
+    }
+    pushed?;
+    info!(
+        "chocolatey :: pushed {} {version} to {}",
+        args.package_id, args.feed
+    );
+    Ok(())
+}
+
+/// the scoop manifest name of the product - `My App` is `my-app`
+fn scoop_app_name(product_name: &str) -> String {
+    product_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .join("-")
+}
+
+/// the scoop manifest of the nsis installers, `checkver` follows the updater manifest of the branch and `autoupdate` swaps the version in the installer urls
+fn scoop_manifest(
+    args: &ScoopArgs,
+    product_name: &str,
+    version: &str,
+    release_file_url: &str,
+    installers: &[WindowsInstaller],
+) -> Result<String> {
+    let architectures = installers
+        .iter()
+        .filter(|installer| installer.winget_type() == Some("nullsoft"))
+        .map(|installer| {
+            let bits = match installer.architecture {
+                "x86" => "32bit",
+                _ => "64bit",
+            };
+            (bits, &installer.download)
+        })
+        .collect::<BTreeMap<_, _>>();
+    if architectures.is_empty() {
+        bail!("no nsis installer was uploaded for {version}, scoop has nothing to install");
+    }
+    let manifest = serde_json::json!({
+        "version": version,
+        "description": product_name,
+        "license": args.scoop_license.as_deref().unwrap_or("Proprietary"),
+        "architecture": architectures
+            .iter()
+            .map(|(bits, download)| (*bits, serde_json::json!({
+                "url": download.url,
+                "hash": download.sha256,
+            })))
+            .collect::<BTreeMap<_, _>>(),
+        "installer": { "args": ["/S", "/D=$dir"] },
+        "uninstaller": { "file": "uninstall.exe", "args": ["/S"] },
+        "shortcuts": [[format!("{product_name}.exe"), product_name]],
+        "checkver": { "url": release_file_url, "jsonpath": "$.version" },
+        "autoupdate": {
+            "architecture": architectures
+                .iter()
+                .map(|(bits, download)| (*bits, serde_json::json!({
+                    "url": download.url.replace(version, "$version"),
+                })))
+                .collect::<BTreeMap<_, _>>(),
+        },
+    });
+    serde_json::to_string_pretty(&manifest)
+        .map(|manifest| manifest + "\n")
+        .wrap_err("serializing scoop manifest")
+}
+
+/// writes the scoop manifest of the version into the bucket, committing and pushing it with `--scoop-push`
+pub async fn scoop(deployment: &Deployment<'_>, version: &str, args: &ScoopArgs) -> Result<()> {
+    let product_name = deployment.tauri_conf_json.product_name();
+    let installers = windows_installers(deployment, version).await?;
+    let manifest = scoop_manifest(
+        args,
+        product_name,
+        version,
+        &deployment.release_file_url(),
+        &installers,
+    )?;
+    let app = args
+        .scoop_app
+        .clone()
+        .unwrap_or_else(|| scoop_app_name(product_name));
+    let bucket = args
+        .scoop_bucket
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("scoop"));
+    let file_name = format!("{app}.json");
+    write_files(&bucket, &[(file_name.clone(), manifest)]).await?;
+    if !args.scoop_push {
+        return Ok(());
+    }
+    let bucket = bucket.to_string_lossy();
+    let changed = metadata::git(&["-C", &bucket, "status", "--porcelain", "--", &file_name])?;
+    if changed.is_empty() {
+        info!("scoop :: {app} {version} is already in the bucket");
+        return Ok(());
+    }
+    metadata::git(&["-C", &bucket, "add", "--", &file_name])?;
+    metadata::git(&[
+        "-C",
+        &bucket,
+        "commit",
+        "-m",
+        &format!("{app}: Update to version {version}"),
+        "--",
+        &file_name,
+    ])?;
+    metadata::git(&["-C", &bucket, "push"])?;
+    info!("scoop :: pushed {app} {version} to the bucket");
+    Ok(())
+}
+
+/// `manifests/g/GrupaPieprzyk/App/1.2.3` - where winget-pkgs keeps the manifests of a version
+fn winget_repository_dir(package_identifier: &str, version: &str) -> String {
+    let first = package_identifier
+        .chars()
+        .next()
+        .map(|c| c.to_ascii_lowercase())
+        .unwrap_or_default();
+    format!(
+        "manifests/{first}/{}/{version}",
+        package_identifier.replace('.', "/")
+    )
+}
+
+async fn winget(deployment: &Deployment<'_>, version: &str, args: &WingetArgs) -> Result<()> {
+    let installers = windows_installers(deployment, version).await?;
+    let manifests = winget_manifests(
+        args,
+        deployment.tauri_conf_json.product_name(),
+        version,
+        &installers,
+    )?;
+    write_files(&args.output_dir, &manifests).await?;
+    if !args.pull_request {
+        return Ok(());
+    }
+    let fork = args
+        .winget_fork
+        .as_deref()
+        .ok_or_else(|| eyre::eyre!("--pull-request needs --winget-fork"))?;
+    let dir = winget_repository_dir(&args.package_identifier, version);
+    let files = manifests
+        .into_iter()
+        .map(|(file_name, content)| (format!("{dir}/{file_name}"), content))
+        .collect_vec();
+    let title = format!("New version: {} version {version}", args.package_identifier);
+    let url = github::open_pull_request(
+        &args.winget_repository,
+        fork,
+        &format!("{}-{version}", args.package_identifier),
+        &files,
+        &title,
+        &format!(
+            "{} {version}, published by tauri-static-deployer",
+            deployment.tauri_conf_json.product_name()
+        ),
+    )
+    .await?;
+    info!("winget :: opened {url}");
+    Ok(())
+}
+
+/// renders the package manager manifests of a published version
+pub async fn run(deployment: &Deployment<'_>, args: &PackageManifestsArgs) -> Result<()> {
+    let version = match &args.version {
+        Some(version) => version.clone(),
+        None => deployment.live_version().await?.ok_or_else(|| {
+            eyre::eyre!(
+                "nothing is live on [{}] - pass --version",
+                deployment.branch
+            )
+        })?,
+    };
+    match &args.manager {
+        PackageManager::Winget(winget_args) => winget(deployment, &version, winget_args).await,
+        PackageManager::Chocolatey(chocolatey_args) => {
+            chocolatey(deployment, &version, chocolatey_args).await
+        }
+        PackageManager::Scoop(scoop_args) => scoop(deployment, &version, scoop_args).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_winget_manifests() -> Result<()> {
+        let args = WingetArgs {
+            package_identifier: "GrupaPieprzyk.App".to_string(),
+            publisher: "Grupa Pieprzyk".to_string(),
+            license: "Proprietary".to_string(),
+            short_description: None,
+            output_dir: "winget".into(),
+            pull_request: false,
+            winget_fork: None,
+            winget_repository: "microsoft/winget-pkgs".to_string(),
+        };
+        let download: Download = serde_json::from_value(serde_json::json!({
+            "key": "main/downloads/win64/1.2.3/App_1.2.3_x64_en-US.msi",
+            "file_name": "App_1.2.3_x64_en-US.msi",
+            "version": "1.2.3",
+            "url": "https://cdn.example.com/main/downloads/win64/1.2.3/App_1.2.3_x64_en-US.msi",
+            "content_type": "application/x-msi",
+            "sha256": "abcd",
+            "size": 42,
+        }))?;
+        let installers = [WindowsInstaller {
+            architecture: "x64",
+            download,
+        }];
+        let manifests = winget_manifests(&args, "App", "1.2.3", &installers)?;
+        assert_eq!(
+            manifests
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect_vec(),
+            vec![
+                "GrupaPieprzyk.App.yaml",
+                "GrupaPieprzyk.App.installer.yaml",
+                "GrupaPieprzyk.App.locale.en-US.yaml"
+            ]
+        );
+        assert!(manifests[1].1.contains("  InstallerType: wix\n"));
+        assert!(manifests[1].1.contains("  InstallerSha256: ABCD\n"));
+        assert!(manifests[2].1.contains("Publisher: 'Grupa Pieprzyk'\n"));
+        assert_eq!(
+            winget_repository_dir("GrupaPieprzyk.App", "1.2.3"),
+            "manifests/g/GrupaPieprzyk/App/1.2.3"
+        );
+        assert!(winget_manifests(&args, "App", "1.2.3", &[]).is_err());
+        Ok(())
+    }
+
+    fn installer(architecture: &'static str, file_name: &str) -> Result<WindowsInstaller> {
+        Ok(WindowsInstaller {
+            architecture,
+            download: serde_json::from_value(serde_json::json!({
+                "key": file_name,
+                "file_name": file_name,
+                "version": "1.2.3",
+                "url": format!("https://cdn.example.com/1.2.3/{file_name}"),
+                "content_type": "application/octet-stream",
+                "sha256": "abcd",
+                "size": 42,
+            }))?,
+        })
+    }
+
+    #[test]
+    fn test_chocolatey_package() -> Result<()> {
+        let args = ChocolateyArgs {
+            package_id: "grupa-pieprzyk-app".to_string(),
+            authors: "Grupa Pieprzyk".to_string(),
+            description: None,
+            output_dir: "chocolatey".into(),
+            push: false,
+            feed: "https://push.chocolatey.org/".to_string(),
+            choco: "choco".to_string(),
+        };
+        let installers = [
+            installer("x64", "App_1.2.3_x64-setup.exe")?,
+            installer("x64", "App_1.2.3_x64_en-US.msi")?,
+            installer("x86", "App_1.2.3_x86-setup.exe")?,
+        ];
+        let files = chocolatey_package(&args, "App & Co", "1.2.3", &installers)?;
+        assert_eq!(files[0].0, "grupa-pieprzyk-app.nuspec");
+        assert!(files[0].1.contains("<title>App &amp; Co</title>"));
+        let script = &files[1].1;
+        assert!(script.contains("  fileType = 'msi'\n"));
+        assert!(script
+            .contains("  url64bit = 'https://cdn.example.com/1.2.3/App_1.2.3_x64_en-US.msi'\n"));
+        // the x86 installer is an nsis one, it can't share the msi arguments
+        assert!(!script.contains("  url = "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoop_manifest() -> Result<()> {
+        assert_eq!(scoop_app_name("My App 2"), "my-app-2");
+        let installers = [
+            installer("x64", "App_1.2.3_x64-setup.exe")?,
+            installer("x64", "App_1.2.3_x64_en-US.msi")?,
+            installer("x86", "App_1.2.3_x86_en-US.msi")?,
+        ];
+        let manifest: serde_json::Value = serde_json::from_str(&scoop_manifest(
+            &Default::default(),
+            "App",
+            "1.2.3",
+            "https://cdn.example.com/main/latest.json",
+            &installers,
+        )?)?;
+        assert_eq!(manifest["version"], "1.2.3");
+        assert_eq!(
+            manifest["architecture"]["64bit"]["url"],
+            "https://cdn.example.com/1.2.3/App_1.2.3_x64-setup.exe"
+        );
+        // only nsis installers can be installed by scoop
+        assert!(manifest["architecture"].get("32bit").is_none());
+        assert_eq!(
+            manifest["autoupdate"]["architecture"]["64bit"]["url"],
+            "https://cdn.example.com/$version/App_$version_x64-setup.exe"
+        );
+        assert!(scoop_manifest(&Default::default(), "App", "1.2.3", "", &installers[1..]).is_err());
+        Ok(())
+    }
+}
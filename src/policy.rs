@@ -0,0 +1,122 @@
+use super::*;
+
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct PolicyArgs {
+    /// record `minimum_version` in the manifests - apps below it should force the update, must not be newer than the published version
+    #[clap(long, value_name = "VERSION")]
+    pub minimum_version: Option<String>,
+    /// mark the release as `mandatory` in the manifests, for the app to install it without asking
+    #[clap(long)]
+    pub mandatory: bool,
+}
+
+impl PolicyArgs {
+    /// `minimum_version` has to be semver and not above the version being published
+    pub fn check(&self, version: &str) -> Result<()> {
+        let minimum = match &self.minimum_version {
+            Some(minimum) => minimum,
+            None => return Ok(()),
+        };
+        let parsed = semver::Version::parse(minimum)
+            .wrap_err_with(|| format!("--minimum-version [{minimum}] is not valid semver"))?;
+        let version = semver::Version::parse(version)
+            .wrap_err_with(|| format!("version [{version}] is not valid semver"))?;
+        if parsed > version {
+            bail!("--minimum-version {minimum} is above the published version {version}, every app would be forced to update to a version it can't get");
+        }
+        Ok(())
+    }
+}
+
+/// the published version has to be above the live one, or equal to it when `allow_equal`
+pub fn check_monotonic(live: Option<&str>, version: &str, allow_equal: bool) -> Result<()> {
+    let live = match live {
+        Some(live) => live,
+        None => return Ok(()),
+    };
+    let parsed_live = match semver::Version::parse(live) {
+        Ok(parsed_live) => parsed_live,
+        Err(e) => {
+            warn!("live version [{live}] is not valid semver ({e}), not checking it is older than {version}");
+            return Ok(());
+        }
+    };
+    let parsed = semver::Version::parse(version)
+        .wrap_err_with(|| format!("version [{version}] is not valid semver"))?;
+    match parsed.cmp(&parsed_live) {
+        std::cmp::Ordering::Greater => Ok(()),
+        std::cmp::Ordering::Equal if allow_equal => Ok(()),
+        std::cmp::Ordering::Equal => bail!("{version} is live already - pass --force to publish it again"),
+        std::cmp::Ordering::Less => bail!("{version} is older than the live {live}, publishing it would downgrade the app - pass --force if that's intended"),
+    }
+}
+
+/// an uploaded binary that would be replaced by different content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overwrite {
+    pub key: String,
+    pub remote_size: u64,
+    pub remote_etag: String,
+    pub local_size: u64,
+    pub local_etag: String,
+}
+
+/// one `- uploaded` / `+ local` pair per binary
+pub fn overwrite_report(overwrites: &[Overwrite]) -> String {
+    overwrites
+        .iter()
+        .map(|overwrite| {
+            format!(
+                "{}\n  - {} bytes, etag {}\n  + {} bytes, etag {}",
+                overwrite.key,
+                overwrite.remote_size,
+                overwrite.remote_etag,
+                overwrite.local_size,
+                overwrite.local_etag
+            )
+        })
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_version_is_not_above_the_version() {
+        let policy = |minimum: &str| PolicyArgs {
+            minimum_version: Some(minimum.to_string()),
+            mandatory: false,
+        };
+        assert!(PolicyArgs::default().check("1.2.3").is_ok());
+        assert!(policy("1.2.3").check("1.2.3").is_ok());
+        assert!(policy("1.0.0").check("1.2.3").is_ok());
+        assert!(policy("1.2.4").check("1.2.3").is_err());
+        assert!(policy("1.2").check("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_monotonic() {
+        assert!(check_monotonic(None, "1.2.3", false).is_ok());
+        assert!(check_monotonic(Some("1.2.2"), "1.2.3", false).is_ok());
+        assert!(check_monotonic(Some("1.2.3"), "1.2.3", false).is_err());
+        assert!(check_monotonic(Some("1.2.3"), "1.2.3", true).is_ok());
+        assert!(check_monotonic(Some("1.3.0-beta.1"), "1.2.3", true).is_err());
+        assert!(check_monotonic(Some("1.3.0-beta.1"), "1.3.0", false).is_ok());
+    }
+
+    #[test]
+    fn test_overwrite_report() {
+        let report = overwrite_report(&[Overwrite {
+            key: "main/win64/1.2.3/abcd1234/App.msi.zip".to_string(),
+            remote_size: 10,
+            remote_etag: "aa".to_string(),
+            local_size: 12,
+            local_etag: "bb".to_string(),
+        }]);
+        assert_eq!(
+            report,
+            "main/win64/1.2.3/abcd1234/App.msi.zip\n  - 10 bytes, etag aa\n  + 12 bytes, etag bb"
+        );
+    }
+}
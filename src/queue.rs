@@ -0,0 +1,245 @@
+use super::*;
+use crate::deploy::Deployment;
+use std::future::Future;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct QueueArgs {
+    /// wait in an object-based queue in the bucket so concurrent deploys of the same branch run one after another, in submission order
+    #[clap(long)]
+    pub queue: bool,
+    /// tickets not refreshed within this time are considered abandoned (crashed or cancelled CI jobs) and skipped
+    #[clap(long, default_value = "1h", value_name = "DURATION")]
+    pub queue_ttl: humantime::Duration,
+    /// how often the queue is checked while waiting, and the ticket refreshed while waiting and deploying
+    #[clap(long, default_value = "10s", value_name = "DURATION")]
+    pub queue_poll_interval: humantime::Duration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Ticket {
+    branch: String,
+    target: RustTarget,
+    commit: String,
+    version: String,
+}
+
+/// keys sort in submission order, whether a ticket is abandoned goes by when it was last refreshed
+fn ticket_key(prefix: &str, submitted_at_millis: u128) -> String {
+    format!(
+        "{prefix}{submitted_at_millis:020}-{}.json",
+        uuid::Uuid::new_v4()
+    )
+}
+
+fn ticket_submitted_at_millis(prefix: &str, key: &str) -> Option<u128> {
+    key.strip_prefix(prefix)?.split('-').next()?.parse().ok()
+}
+
+fn now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// when the ticket was last written, the submission time when the listing didn't say
+fn ticket_refreshed_at_millis(prefix: &str, ticket: &storage::ListedObject) -> Option<u128> {
+    ticket_submitted_at_millis(prefix, &ticket.key)?;
+    match ticket.last_modified {
+        Some(last_modified) => {
+            u128::try_from(last_modified.unix_timestamp_nanos() / 1_000_000).ok()
+        }
+        None => ticket_submitted_at_millis(prefix, &ticket.key),
+    }
+}
+
+/// live tickets in submission order, a ticket stays live as long as its job keeps refreshing it
+fn live_tickets(
+    prefix: &str,
+    tickets: Vec<storage::ListedObject>,
+    ttl: Duration,
+    now_millis: u128,
+) -> Vec<String> {
+    tickets
+        .into_iter()
+        .filter(|ticket| {
+            ticket_refreshed_at_millis(prefix, ticket)
+                .map(|refreshed_at| now_millis.saturating_sub(refreshed_at) < ttl.as_millis())
+                .unwrap_or_default()
+        })
+        .map(|ticket| ticket.key)
+        .sorted()
+        .collect()
+}
+
+/// average time a deploy spent at the head of the queue, as observed while waiting
+#[derive(Debug, Default)]
+struct EtaEstimator {
+    head: Option<(String, Instant)>,
+    observed: Vec<Duration>,
+}
+
+impl EtaEstimator {
+    fn observe_head(&mut self, head: &str) {
+        match &self.head {
+            Some((current, _)) if current == head => {}
+            Some((_, since)) => {
+                self.observed.push(since.elapsed());
+                self.head = Some((head.to_string(), Instant::now()));
+            }
+            None => self.head = Some((head.to_string(), Instant::now())),
+        }
+    }
+
+    fn eta(&self, position: usize) -> Option<Duration> {
+        if self.observed.is_empty() {
+            return None;
+        }
+        let average = self.observed.iter().sum::<Duration>() / self.observed.len() as u32;
+        Some(average * position as u32)
+    }
+}
+
+/// (re)writes the ticket, which keeps it from being considered abandoned for another `--queue-ttl`
+async fn put_ticket(deployment: &Deployment<'_>, key: &str, ticket: &Ticket) -> Result<()> {
+    storage::upload_json(
+        ticket,
+        deployment.s3_config,
+        deployment.fallback_endpoints,
+        key,
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn wait_for_turn(
+    deployment: &Deployment<'_>,
+    args: &QueueArgs,
+    key: &str,
+    ticket: &Ticket,
+) -> Result<()> {
+    let prefix = derive_deploy_queue_prefix(deployment.branch);
+    put_ticket(deployment, key, ticket)
+        .await
+        .wrap_err("submitting deploy queue ticket")?;
+    info!("deploy queue :: submitted ticket [{key}]");
+    let mut estimator = EtaEstimator::default();
+    loop {
+        let objects = storage::list_objects(deployment.s3_config, &prefix)
+            .await
+            .wrap_err("listing deploy queue")?;
+        let tickets = live_tickets(&prefix, objects, args.queue_ttl.into(), now_millis());
+        let position = tickets
+            .iter()
+            .position(|t| t == key)
+            .ok_or_else(|| eyre::eyre!("deploy queue ticket [{key}] disappeared from the queue"))?;
+        if position == 0 {
+            info!("deploy queue :: it's our turn");
+            return Ok(());
+        }
+        estimator.observe_head(&tickets[0]);
+        let eta = estimator
+            .eta(position)
+            .map(|eta| humantime::format_duration(Duration::from_secs(eta.as_secs())).to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        info!(
+            "deploy queue :: position {position}/{}, waiting for [{}], eta: {eta}",
+            tickets.len() - 1,
+            tickets[0]
+        );
+        tokio::time::sleep(args.queue_poll_interval.into()).await;
+        put_ticket(deployment, key, ticket)
+            .await
+            .wrap_err("refreshing deploy queue ticket")?;
+    }
+}
+
+/// keeps refreshing the ticket while the deploy holds the head of the queue, so a deploy running longer than
+/// `--queue-ttl` isn't skipped by the ones waiting behind it
+async fn hold_head(deployment: &Deployment<'_>, args: &QueueArgs, key: &str, ticket: &Ticket) {
+    loop {
+        tokio::time::sleep(args.queue_poll_interval.into()).await;
+        if let Err(e) = put_ticket(deployment, key, ticket).await {
+            warn!("deploy queue :: failed to refresh ticket [{key}] :: {e:?}");
+        }
+    }
+}
+
+/// runs the deploy once every deploy of the same branch submitted earlier has finished, the ticket is removed whatever the outcome
+pub async fn run_queued<T, F: Future<Output = Result<T>>>(
+    deployment: &Deployment<'_>,
+    args: &QueueArgs,
+    deploy: F,
+) -> Result<T> {
+    if !args.queue {
+        return deploy.await;
+    }
+    let key = ticket_key(&derive_deploy_queue_prefix(deployment.branch), now_millis());
+    let ticket = Ticket {
+        branch: deployment.branch.to_string(),
+        target: deployment.target.clone(),
+        commit: deployment.git_hash.to_string(),
+        version: deployment.tauri_conf_json.version().to_string(),
+    };
+    deployment.staged_objects.lease(&key);
+    wait_for_turn(deployment, args, &key, &ticket).await?;
+    let deployed = tokio::select! {
+        deployed = deploy => deployed,
+        () = hold_head(deployment, args, &key, &ticket) => unreachable!("the ticket is refreshed until the deploy is done"),
+    };
+    if let Err(e) = storage::delete(deployment.s3_config, &key).await {
+        warn!(
+            "deploy queue :: failed to remove ticket [{key}], it will expire on its own :: {e:?}"
+        );
+    }
+    deployed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_tickets_are_ordered_and_expired() {
+        let prefix = "release/deploy-queue/";
+        let listed = |key: &str, last_modified_millis: Option<i128>| storage::ListedObject {
+            key: key.to_string(),
+            last_modified: last_modified_millis.map(|millis| {
+                time::OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).unwrap()
+            }),
+        };
+        let first = ticket_key(prefix, 2_000);
+        let second = ticket_key(prefix, 3_000);
+        let abandoned = ticket_key(prefix, 10);
+        // submitted long ago, but its job keeps refreshing it
+        let refreshed = ticket_key(prefix, 20);
+        assert_eq!(
+            live_tickets(
+                prefix,
+                vec![
+                    listed(&second, None),
+                    listed(&abandoned, Some(10)),
+                    listed(&first, Some(2_000)),
+                    listed(&refreshed, Some(3_400)),
+                    listed(&format!("{prefix}garbage"), Some(3_400)),
+                ],
+                Duration::from_secs(2),
+                3_500
+            ),
+            vec![refreshed, first, second]
+        );
+    }
+
+    #[test]
+    fn test_eta_is_unknown_until_head_changes() {
+        let mut estimator = EtaEstimator::default();
+        estimator.observe_head("a");
+        assert_eq!(estimator.eta(2), None);
+        estimator.observe_head("b");
+        assert!(estimator.eta(2).is_some());
+    }
+}
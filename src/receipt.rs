@@ -0,0 +1,429 @@
+use super::*;
+use crate::{
+    deploy::Deployment,
+    release_notes_file::StagedRelease,
+};
+use ed25519_dalek::{
+    Signature,
+    Signer,
+    SigningKey,
+    Verifier,
+    VerifyingKey,
+};
+use std::collections::BTreeMap;
+
+/// base64 encoded 32 byte ed25519 seed used to sign receipts
+pub const SIGNING_KEY_ENV: &str = "DEPLOY_RECEIPT_SIGNING_KEY";
+/// base64 encoded ed25519 public key receipts are verified against
+pub const PUBLIC_KEY_ENV: &str = "DEPLOY_RECEIPT_PUBLIC_KEY";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Receipt {
+    pub deploy_id: String,
+    pub branch: String,
+    pub target: RustTarget,
+    pub version: String,
+    pub commit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub train: Option<String>,
+    /// RFC3339
+    pub published_at: String,
+    pub manifest_key: String,
+    pub manifest_url: String,
+    pub manifest_sha256: String,
+    /// artifact url -> sha256
+    pub artifacts: BTreeMap<String, String>,
+    /// team-only notes, kept out of the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub internal_notes: Option<String>,
+    /// the manifest exactly as it was served, answers `status --at` long after it was replaced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    /// the manifest was deleted by `unpublish --delete` at `published_at`, nothing was served from then on
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReceipt {
+    pub receipt: Receipt,
+    /// base64 ed25519 signature of the json serialized `receipt`
+    pub signature: Option<String>,
+    /// base64 public key of the signer, informational only - verification uses the configured key
+    pub public_key: Option<String>,
+}
+
+pub fn decode_key(val: &str) -> Result<[u8; 32]> {
+    data_encoding::BASE64
+        .decode(val.trim().as_bytes())
+        .wrap_err("key is not valid base64")?
+        .try_into()
+        .map_err(|key: Vec<u8>| eyre::eyre!("key must be 32 bytes long, got {}", key.len()))
+}
+
+pub fn signing_key_from_env() -> Result<Option<SigningKey>> {
+    match std::env::var(SIGNING_KEY_ENV) {
+        Ok(val) => decode_key(&val)
+            .map(|seed| Some(SigningKey::from_bytes(&seed)))
+            .wrap_err_with(|| format!("bad {SIGNING_KEY_ENV}")),
+        Err(_) => Ok(None),
+    }
+}
+
+pub fn public_key_from_env() -> Result<Option<VerifyingKey>> {
+    match std::env::var(PUBLIC_KEY_ENV) {
+        Ok(val) => decode_key(&val)
+            .and_then(|key| VerifyingKey::from_bytes(&key).wrap_err("invalid ed25519 public key"))
+            .map(Some)
+            .wrap_err_with(|| format!("bad {PUBLIC_KEY_ENV}")),
+        Err(_) => Ok(None),
+    }
+}
+
+/// base64 ed25519 signature of the json serialized value and base64 public key of the signer
+pub fn sign_json<T: Serialize>(value: &T, signing_key: &SigningKey) -> Result<(String, String)> {
+    let message = serde_json::to_vec(value).wrap_err("serializing signed value")?;
+    Ok((
+        data_encoding::BASE64.encode(&signing_key.sign(&message).to_bytes()),
+        data_encoding::BASE64.encode(signing_key.verifying_key().as_bytes()),
+    ))
+}
+
+pub fn check_json_signature<T: Serialize>(
+    value: &T,
+    signature: &str,
+    public_key: &VerifyingKey,
+) -> Result<()> {
+    let signature = data_encoding::BASE64
+        .decode(signature.as_bytes())
+        .wrap_err("signature is not valid base64")
+        .and_then(|bytes| Signature::from_slice(&bytes).wrap_err("malformed signature"))?;
+    let message = serde_json::to_vec(value).wrap_err("serializing signed value")?;
+    public_key
+        .verify(&message, &signature)
+        .wrap_err("signature does not match")
+}
+
+impl Receipt {
+    fn signed(self, signing_key: Option<&SigningKey>) -> Result<SignedReceipt> {
+        let (signature, public_key) = match signing_key {
+            Some(signing_key) => {
+                let (signature, public_key) = sign_json(&self, signing_key)?;
+                (Some(signature), Some(public_key))
+            }
+            None => (None, None),
+        };
+        Ok(SignedReceipt {
+            receipt: self,
+            signature,
+            public_key,
+        })
+    }
+}
+
+impl SignedReceipt {
+    fn check_signature(&self, public_key: &VerifyingKey) -> Result<()> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("receipt is not signed"))?;
+        check_json_signature(&self.receipt, signature, public_key)
+            .wrap_err("receipt signature does not match")
+    }
+}
+
+/// records what exactly went live, uploads the receipt next to the release and prints it
+pub async fn emit(
+    deployment: &Deployment<'_>,
+    staged: &StagedRelease,
+    notes: &notes::Notes,
+    manifest_key: &str,
+    manifest_url: &str,
+    manifest_content: &[u8],
+) -> Result<SignedReceipt> {
+    let receipt = Receipt {
+        deploy_id: uuid::Uuid::new_v4().to_string(),
+        branch: deployment.branch.to_string(),
+        target: deployment.target.clone(),
+        version: staged.version.clone(),
+        commit: staged.commit.clone(),
+        train: deployment.train(staged),
+        published_at: time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .wrap_err("formatting publish date")?,
+        manifest_key: manifest_key.to_string(),
+        manifest_url: manifest_url.to_string(),
+        manifest_sha256: checksum::sha256_bytes(manifest_content),
+        artifacts: staged
+            .artifacts
+            .iter()
+            .map(|artifact| (artifact.url.clone(), artifact.sha256.clone()))
+            .collect(),
+        internal_notes: notes.internal.clone(),
+        manifest: Some(String::from_utf8_lossy(manifest_content).to_string()),
+        deleted: false,
+    };
+    store(deployment, receipt).await
+}
+
+/// records that the manifests serving the version were deleted, so `status --at` doesn't report it live afterwards
+pub async fn emit_deleted(deployment: &Deployment<'_>, version: &str) -> Result<SignedReceipt> {
+    let published = latest_of_version(deployment, version).await?;
+    let receipt = Receipt {
+        deploy_id: uuid::Uuid::new_v4().to_string(),
+        branch: deployment.branch.to_string(),
+        target: deployment.target.clone(),
+        version: version.to_string(),
+        commit: published
+            .as_ref()
+            .map(|published| published.commit.clone())
+            .unwrap_or_default(),
+        train: published.and_then(|published| published.train),
+        published_at: time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .wrap_err("formatting delete date")?,
+        manifest_key: deployment.release_file_key(),
+        manifest_url: deployment.release_file_url(),
+        manifest_sha256: String::new(),
+        artifacts: Default::default(),
+        internal_notes: None,
+        manifest: None,
+        deleted: true,
+    };
+    store(deployment, receipt).await
+}
+
+/// signs the receipt if a key is configured, uploads it next to the release and prints it
+async fn store(deployment: &Deployment<'_>, receipt: Receipt) -> Result<SignedReceipt> {
+    let signing_key = signing_key_from_env()?;
+    if signing_key.is_none() {
+        warn!("{SIGNING_KEY_ENV} is not set, the deploy receipt will not be signed");
+    }
+    let signed = receipt.signed(signing_key.as_ref())?;
+    let key = namespacing::derive_receipt_s3_key(
+        deployment.branch,
+        deployment.target,
+        &signed.receipt.version,
+        &signed.receipt.deploy_id,
+    );
+    let url = storage::upload_json(
+        &signed,
+        deployment.s3_config,
+        deployment.fallback_endpoints,
+        &key,
+    )
+    .await
+    .wrap_err("uploading deploy receipt")?;
+    info!(
+        " :: deploy receipt [{key}] ({url}) ::\n{}\n\n",
+        serde_json::to_string_pretty(&signed).unwrap_or_default()
+    );
+    Ok(signed)
+}
+
+async fn fetch_all(deployment: &Deployment<'_>, keys: &[String]) -> Result<Vec<SignedReceipt>> {
+    use futures::{
+        StreamExt,
+        TryStreamExt,
+    };
+    futures::stream::iter(keys)
+        .map(|key| async move {
+            let content = storage::get_bytes(deployment.s3_config, key)
+                .await?
+                .ok_or_else(|| eyre::eyre!("receipt [{key}] disappeared"))?;
+            serde_json::from_slice::<SignedReceipt>(&content)
+                .wrap_err_with(|| format!("parsing receipt [{key}]"))
+        })
+        .buffered(8)
+        .try_collect()
+        .await
+}
+
+/// every receipt of the branch, all targets and versions
+pub async fn list(deployment: &Deployment<'_>) -> Result<Vec<SignedReceipt>> {
+    let keys = storage::list_keys(
+        deployment.s3_config,
+        &namespacing::derive_receipts_prefix(deployment.branch),
+    )
+    .await
+    .wrap_err("listing deploy receipts")?;
+    fetch_all(deployment, &keys).await
+}
+
+/// the receipt of the last time the version of the deployment's target went live, `None` if it never did
+pub async fn latest_of_version(
+    deployment: &Deployment<'_>,
+    version: &str,
+) -> Result<Option<Receipt>> {
+    let prefix =
+        namespacing::derive_version_receipts_prefix(deployment.branch, deployment.target, version);
+    let keys = storage::list_keys(deployment.s3_config, &prefix)
+        .await
+        .wrap_err("listing deploy receipts")?;
+    Ok(fetch_all(deployment, &keys)
+        .await?
+        .into_iter()
+        .map(|signed| signed.receipt)
+        .filter(|receipt| !receipt.deleted)
+        .max_by(|a, b| a.published_at.cmp(&b.published_at)))
+}
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyArgs {
+    /// receipt to check the live deployment against - a local file or a key in the bucket
+    #[clap(long, value_name = "FILE_OR_KEY")]
+    pub receipt: String,
+    #[clap(flatten)]
+    pub report: report::ReportArgs,
+}
+
+async fn load_receipt(s3_config: &S3Config, receipt: &str) -> Result<SignedReceipt> {
+    let content = if Path::new(receipt).is_file() {
+        tokio::fs::read(receipt)
+            .await
+            .wrap_err_with(|| format!("reading [{receipt}]"))?
+    } else {
+        storage::get_bytes(s3_config, receipt)
+            .await?
+            .ok_or_else(|| eyre::eyre!("receipt [{receipt}] not found locally nor in the bucket"))?
+    };
+    serde_json::from_slice(&content).wrap_err("parsing receipt")
+}
+
+/// checks the receipt signature, the live manifest and every artifact hash, reporting all mismatches at once
+pub async fn verify(s3_config: &S3Config, args: &VerifyArgs) -> Result<()> {
+    let signed = load_receipt(s3_config, &args.receipt).await?;
+    let receipt = &signed.receipt;
+    info!(
+        "verifying deploy {} of {} [{}] ({})",
+        receipt.deploy_id, receipt.version, receipt.branch, receipt.commit
+    );
+    let mut findings = vec![];
+    match public_key_from_env()? {
+        Some(public_key) => findings.push(match signed.check_signature(&public_key) {
+            Ok(()) => report::Finding::pass("receipt-signature", &args.receipt),
+            Err(e) => report::Finding::error("receipt-signature", &args.receipt, format!("{e:?}")),
+        }),
+        None => warn!("{PUBLIC_KEY_ENV} is not set, receipt signature is NOT checked"),
+    }
+    let manifest_key = &receipt.manifest_key;
+    let manifest = storage::get_bytes(s3_config, manifest_key).await?;
+    findings.push(match &manifest {
+        None if receipt.deleted => report::Finding::pass("manifest-hash", manifest_key),
+        Some(_) if receipt.deleted => report::Finding::error(
+            "manifest-hash",
+            manifest_key,
+            format!("[{manifest_key}] was deleted by this deploy, but something was published since"),
+        ),
+        Some(manifest) if checksum::sha256_bytes(manifest) == receipt.manifest_sha256 => {
+            report::Finding::pass("manifest-hash", manifest_key)
+        }
+        Some(_) => report::Finding::error(
+            "manifest-hash",
+            manifest_key,
+            format!(
+                "[{manifest_key}] changed since the deploy - another release was published or it was tampered with"
+            ),
+        ),
+        None => report::Finding::error(
+            "manifest-hash",
+            manifest_key,
+            format!("[{manifest_key}] does not exist"),
+        ),
+    });
+    match (minisign::manifest_public_key_from_env()?, &manifest) {
+        (Some(public_key), Some(manifest)) => {
+            let signature_key = format!("{manifest_key}.sig");
+            let signature = storage::get_bytes(s3_config, &signature_key)
+                .await?
+                .map(|signature| String::from_utf8_lossy(&signature).to_string());
+            findings.push(match signature {
+                Some(signature) => {
+                    match minisign::verify_manifest(manifest, &signature, &public_key) {
+                        Ok(()) => report::Finding::pass("manifest-signature", &signature_key),
+                        Err(e) => report::Finding::error(
+                            "manifest-signature",
+                            &signature_key,
+                            format!("[{manifest_key}] doesn't match its signature: {e}"),
+                        ),
+                    }
+                }
+                None => report::Finding::error(
+                    "manifest-signature",
+                    &signature_key,
+                    format!("[{signature_key}] does not exist"),
+                ),
+            });
+        }
+        (Some(_), None) => {}
+        (None, _) => warn!(
+            "{} is not set, manifest signature is NOT checked",
+            minisign::MANIFEST_PUBLIC_KEY_ENV
+        ),
+    }
+    for (url, expected) in &receipt.artifacts {
+        findings.push(match cache::sha256(url).await {
+            Ok(actual) if &actual == expected => report::Finding::pass("artifact-hash", url),
+            Ok(actual) => report::Finding::error(
+                "artifact-hash",
+                url,
+                format!("[{url}] has sha256 {actual}, expected {expected}"),
+            ),
+            Err(e) => report::Finding::error("artifact-hash", url, format!("{e:?}")),
+        });
+    }
+    report::write(&args.report, "verify", &findings)?;
+    for finding in &findings {
+        match finding.level {
+            report::Level::Pass => info!("OK :: [{}]", finding.subject),
+            report::Level::Error => error!("FAILED :: {}", finding.message),
+        }
+    }
+    let problems = findings.iter().filter(|finding| finding.is_error()).count();
+    if problems > 0 {
+        bail!(
+            "deployment does not match receipt {} ({problems} problems)",
+            receipt.deploy_id
+        );
+    }
+    info!(" ::: deployment matches receipt {} :::", receipt.deploy_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt() -> Receipt {
+        Receipt {
+            deploy_id: "deploy".to_string(),
+            branch: "release".to_string(),
+            target: RustTarget::Win64,
+            version: "1.7.9".to_string(),
+            commit: "deadbeef".to_string(),
+            train: None,
+            published_at: "2022-04-01T13:06:47Z".to_string(),
+            manifest_key: "release/x86_64-pc-windows-msvc/release-notes.json".to_string(),
+            manifest_url: "https://example.com/release-notes.json".to_string(),
+            manifest_sha256: checksum::sha256_bytes(b"{}"),
+            artifacts: Default::default(),
+            internal_notes: None,
+            manifest: None,
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn test_signed_receipt_verifies() -> Result<()> {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let signed = receipt().signed(Some(&signing_key))?;
+        signed.check_signature(&signing_key.verifying_key())?;
+
+        let mut tampered = signed;
+        tampered.receipt.version = "6.6.6".to_string();
+        assert!(tampered
+            .check_signature(&signing_key.verifying_key())
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use super::*;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReportArgs {
+    /// also write the results of the checks to this file for CI annotations - JUnit XML for `.xml`, json otherwise
+    #[clap(long, value_name = "FILE")]
+    pub report_file: Option<PathBuf>,
+}
+
+/// outcome of a single check
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Finding {
+    /// what was checked, e.g. `artifact-hash` or `forbidden-word`
+    pub rule: String,
+    /// the file, key or url the check was about
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub level: Level,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Pass,
+    Error,
+}
+
+impl Finding {
+    pub fn pass(rule: &str, subject: &str) -> Self {
+        Self {
+            rule: rule.to_string(),
+            subject: subject.to_string(),
+            line: None,
+            level: Level::Pass,
+            message: "ok".to_string(),
+        }
+    }
+
+    pub fn error(rule: &str, subject: &str, message: String) -> Self {
+        Self {
+            rule: rule.to_string(),
+            subject: subject.to_string(),
+            line: None,
+            level: Level::Error,
+            message,
+        }
+    }
+
+    pub fn at_line(self, line: usize) -> Self {
+        Self {
+            line: Some(line),
+            ..self
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    tool: &'a str,
+    version: &'a str,
+    /// `verify` or `notes-lint`
+    check: &'a str,
+    errors: usize,
+    results: &'a [Finding],
+}
+
+fn to_junit(check: &str, findings: &[Finding]) -> String {
+    let testcases = findings
+        .iter()
+        .map(|finding| {
+            let name = match finding.line {
+                Some(line) => format!("{} ({}:{line})", finding.rule, finding.subject),
+                None => format!("{} ({})", finding.rule, finding.subject),
+            };
+            let failure = match finding.level {
+                Level::Pass => String::new(),
+                Level::Error => format!(
+                    "\n      <failure message=\"{}\"/>\n    ",
+                    downloads::escape_html(&finding.message)
+                ),
+            };
+            format!(
+                "    <testcase classname=\"{check}\" name=\"{}\">{failure}</testcase>\n",
+                downloads::escape_html(&name)
+            )
+        })
+        .join("");
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"{check}\" tests=\"{}\" failures=\"{}\">\n{testcases}</testsuite>\n",
+        findings.len(),
+        findings.iter().filter(|finding| finding.is_error()).count()
+    )
+}
+
+/// writes the findings when `--report-file` was given, before the caller fails on them
+pub fn write(args: &ReportArgs, check: &str, findings: &[Finding]) -> Result<()> {
+    let path = match &args.report_file {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let content = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("xml") => to_junit(check, findings),
+        _ => serde_json::to_string_pretty(&Report {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            check,
+            errors: findings.iter().filter(|finding| finding.is_error()).count(),
+            results: findings,
+        })
+        .wrap_err("serializing report")?,
+    };
+    files::write_atomic(path, content)?;
+    info!("report :: written to [{}]", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_junit_report() {
+        let findings = [
+            Finding::pass("manifest-hash", "release/release-notes.json"),
+            Finding::error(
+                "forbidden-word",
+                "notes",
+                "forbidden word [<TODO>]".to_string(),
+            )
+            .at_line(2),
+        ];
+        assert_eq!(
+            to_junit("verify", &findings),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"verify\" tests=\"2\" failures=\"1\">\n    <testcase classname=\"verify\" name=\"manifest-hash (release/release-notes.json)\"></testcase>\n    <testcase classname=\"verify\" name=\"forbidden-word (notes:2)\">\n      <failure message=\"forbidden word [&lt;TODO&gt;]\"/>\n    </testcase>\n</testsuite>\n"
+        );
+    }
+}
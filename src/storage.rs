@@ -0,0 +1,1164 @@
+use super::*;
+use std::time::Duration;
+
+/// comma separated list of additional endpoint hosts serving the same bucket, tried in order when the primary one can't be reached
+pub const FALLBACK_ENDPOINTS_ENV: &str = "S3_FALLBACK_ENDPOINTS";
+
+pub fn fallback_endpoints_from_env() -> Vec<String> {
+    std::env::var(FALLBACK_ENDPOINTS_ENV)
+        .map(|v| parse_endpoints(&v))
+        .unwrap_or_default()
+}
+
+fn parse_endpoints(val: &str) -> Vec<String> {
+    val.split(',')
+        .map(|host| {
+            host.trim()
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_end_matches('/')
+                .to_string()
+        })
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+/// only errors that happen before the request reaches the server are worth retrying on another host
+fn is_connect_error(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "error trying to connect",
+        "dns error",
+        "failed to lookup address",
+        "connection refused",
+        "connection reset",
+        "tcp connect error",
+        "operation timed out",
+    ];
+    let message = message.to_lowercase();
+    MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// transient failures a repeated request has a good chance of getting past
+fn is_retryable(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "broken pipe",
+        "connection closed",
+        "incomplete message",
+        "timed out",
+        "got http 429",
+        "got http 5",
+        "429 too many requests",
+        "server error (5",
+        "slowdown",
+    ];
+    let lowercase = message.to_lowercase();
+    is_connect_error(message) || MARKERS.iter().any(|marker| lowercase.contains(marker))
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RetryArgs {
+    /// how many times a failing s3 request is tried in total
+    #[clap(long, default_value_t = 4, value_name = "N")]
+    pub retry_attempts: u32,
+    /// delay before the first retry, doubled for every next one and randomized
+    #[clap(long, default_value = "500ms", value_name = "DURATION")]
+    pub retry_backoff: humantime::Duration,
+    /// upper bound of a single delay between retries
+    #[clap(long, default_value = "20s", value_name = "DURATION")]
+    pub retry_max_backoff: humantime::Duration,
+}
+
+impl Default for RetryArgs {
+    fn default() -> Self {
+        Self {
+            retry_attempts: 4,
+            retry_backoff: Duration::from_millis(500).into(),
+            retry_max_backoff: Duration::from_secs(20).into(),
+        }
+    }
+}
+
+impl RetryArgs {
+    /// exponential backoff capped at `--retry-max-backoff`, `attempt` is the one that just failed
+    fn backoff_ceiling(&self, attempt: u32) -> Duration {
+        let max: Duration = self.retry_max_backoff.into();
+        Duration::from(self.retry_backoff)
+            .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .map_or(max, |backoff| backoff.min(max))
+    }
+
+    /// somewhere between half of the ceiling and the ceiling, so parallel uploads don't retry in lockstep
+    fn backoff(&self, attempt: u32) -> Duration {
+        use rand::Rng;
+        let ceiling = self.backoff_ceiling(attempt);
+        rand::thread_rng().gen_range(ceiling / 2..=ceiling)
+    }
+}
+
+static RETRY_POLICY: std::sync::OnceLock<RetryArgs> = std::sync::OnceLock::new();
+
+/// every s3 request made afterwards follows this policy, the defaults apply until it's set
+pub fn set_retry_policy(args: RetryArgs) {
+    if RETRY_POLICY.set(args).is_err() {
+        warn!("retry policy is set already, keeping the first one");
+    }
+}
+
+/// runs `op` again after a growing delay as long as it fails with a transient error and attempts are left
+pub async fn retry<T, F, Fut>(what: &str, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let policy = RETRY_POLICY.get_or_init(Default::default);
+    let attempts = policy.retry_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < attempts && is_retryable(&format!("{e:?}")) => {
+                let backoff = policy.backoff(attempt);
+                warn!(
+                    "retry :: {what} failed ({attempt}/{attempts}), trying again in {} :: {e}",
+                    humantime::format_duration(Duration::from_millis(backoff.as_millis() as u64))
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn with_endpoint(s3_config: &S3Config, endpoint: &str) -> Result<S3Config> {
+    let mut config = s3_config.clone();
+    let bucket = config
+        .bucket
+        .as_mut()
+        .ok_or_else(|| eyre::eyre!("s3 bucket is not initialized, can't switch endpoint"))?;
+    bucket.region = s3::Region::Custom {
+        region: s3_config.bucket_config.region_name.clone(),
+        endpoint: endpoint.to_string(),
+    };
+    Ok(config)
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheControlArgs {
+    /// Cache-Control of the versioned binaries and installers, they never change once uploaded - empty leaves it unset
+    #[clap(
+        long,
+        default_value = "public, max-age=31536000, immutable",
+        value_name = "HEADER"
+    )]
+    pub cache_control_immutable: String,
+    /// Cache-Control of everything that's overwritten - manifests, downloads pages, permalinks, history - empty leaves it unset
+    #[clap(long, default_value = "no-cache", value_name = "HEADER")]
+    pub cache_control_mutable: String,
+}
+
+impl Default for CacheControlArgs {
+    fn default() -> Self {
+        Self {
+            cache_control_immutable: "public, max-age=31536000, immutable".to_string(),
+            cache_control_mutable: "no-cache".to_string(),
+        }
+    }
+}
+
+static CACHE_CONTROL: std::sync::OnceLock<CacheControlArgs> = std::sync::OnceLock::new();
+
+/// every object uploaded afterwards gets these headers, the defaults apply until it's set
+pub fn set_cache_control(args: CacheControlArgs) {
+    if CACHE_CONTROL.set(args).is_err() {
+        warn!("cache control is set already, keeping the first one");
+    }
+}
+
+static DEFAULT_HEADERS: std::sync::RwLock<Vec<(String, String)>> =
+    std::sync::RwLock::new(Vec::new());
+
+/// headers every object uploaded afterwards is sent with, on top of the ones of its class
+///
+/// replaces the previous ones, a multi-target run sets them again for every target it processes
+pub fn set_default_headers(headers: Vec<(String, String)>) {
+    *DEFAULT_HEADERS
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = headers;
+}
+
+/// comma separated `KEY=VALUE` metadata every uploaded object is stamped with, `--meta` adds to it
+pub const METADATA_ENV: &str = "DEPLOY_METADATA";
+
+/// `x-amz-meta-*` headers of `KEY=VALUE` entries, later entries win over earlier ones with the same key
+pub fn metadata_headers<'a>(
+    entries: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<(String, String)>> {
+    let mut headers = std::collections::BTreeMap::new();
+    for entry in entries {
+        let (key, value) = entry
+            .split_once('=')
+            .ok_or_else(|| eyre::eyre!("metadata [{entry}] is not KEY=VALUE"))?;
+        let key = key.trim().to_lowercase();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            bail!("metadata key [{key}] may only hold ascii letters, digits and `-`");
+        }
+        if !value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+            bail!("metadata value of [{key}] must be printable ascii");
+        }
+        headers.insert(format!("x-amz-meta-{key}"), value.trim().to_string());
+    }
+    Ok(headers.into_iter().collect())
+}
+
+pub fn metadata_from_env() -> Vec<String> {
+    std::env::var(METADATA_ENV)
+        .map(|val| {
+            val.split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// characters left as they are in a tag key or value of `x-amz-tagging`
+const TAG_CHAR: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_');
+
+/// `x-amz-tagging` value of the tags, url-encoded like a query string
+pub fn tagging(tags: &[(&str, &str)]) -> String {
+    let encode = |val| percent_encoding::utf8_percent_encode(val, TAG_CHAR).to_string();
+    tags.iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .join("&")
+}
+
+/// decides the Cache-Control an object is uploaded with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectClass {
+    Immutable,
+    Mutable,
+}
+
+impl ObjectClass {
+    fn cache_control(self) -> Option<&'static str> {
+        let args = CACHE_CONTROL.get_or_init(Default::default);
+        let header = match self {
+            Self::Immutable => &args.cache_control_immutable,
+            Self::Mutable => &args.cache_control_mutable,
+        };
+        Some(header.trim()).filter(|header| !header.is_empty())
+    }
+}
+
+/// Cache-Control of the versioned objects, for copies that have to keep it
+pub fn immutable_cache_control() -> Option<&'static str> {
+    ObjectClass::Immutable.cache_control()
+}
+
+/// the bucket handle sending the headers of the object class (and any extra ones) with every request
+fn bucket_for(
+    s3_config: &S3Config,
+    class: ObjectClass,
+    headers: &[(&str, &str)],
+) -> Result<s3::Bucket> {
+    let mut bucket = bucket(s3_config)?.clone();
+    for (header, value) in DEFAULT_HEADERS
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .iter()
+    {
+        bucket.add_header(header, value);
+    }
+    if let Some(cache_control) = class.cache_control() {
+        bucket.add_header("Cache-Control", cache_control);
+    }
+    for (header, value) in headers {
+        bucket.add_header(header, value);
+    }
+    Ok(bucket)
+}
+
+/// content types by file name suffix, matched case-insensitively - the longest suffix wins
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    (".json", "application/json"),
+    (".html", "text/html; charset=utf-8"),
+    (".txt", "text/plain; charset=utf-8"),
+    (".md", "text/markdown; charset=utf-8"),
+    (".sig", "text/plain; charset=utf-8"),
+    (".asc", "application/pgp-signature"),
+    (".zip", "application/zip"),
+    (".gz", "application/gzip"),
+    (".tar.gz", "application/gzip"),
+    (".msi", "application/x-msi"),
+    (".exe", "application/vnd.microsoft.portable-executable"),
+    (".msix", "application/msix"),
+    (".appimage", "application/vnd.appimage"),
+    (".dmg", "application/x-apple-diskimage"),
+    (".deb", "application/vnd.debian.binary-package"),
+    (".rpm", "application/x-rpm"),
+    (".flatpak", "application/vnd.flatpak"),
+    (".xml", "application/xml"),
+    // web frontends
+    (".htm", "text/html; charset=utf-8"),
+    (".js", "text/javascript; charset=utf-8"),
+    (".mjs", "text/javascript; charset=utf-8"),
+    (".css", "text/css; charset=utf-8"),
+    (".map", "application/json"),
+    (".webmanifest", "application/manifest+json"),
+    (".wasm", "application/wasm"),
+    (".svg", "image/svg+xml"),
+    (".png", "image/png"),
+    (".jpg", "image/jpeg"),
+    (".jpeg", "image/jpeg"),
+    (".gif", "image/gif"),
+    (".webp", "image/webp"),
+    (".avif", "image/avif"),
+    (".ico", "image/x-icon"),
+    (".woff", "font/woff"),
+    (".woff2", "font/woff2"),
+    (".ttf", "font/ttf"),
+    (".otf", "font/otf"),
+];
+
+/// content type the object is served with, `application/octet-stream` when the suffix is unknown
+pub fn content_type_for(key: &str) -> &'static str {
+    let key = key.to_lowercase();
+    CONTENT_TYPES
+        .iter()
+        .filter(|(suffix, _)| key.ends_with(suffix))
+        .max_by_key(|(suffix, _)| suffix.len())
+        .map_or("application/octet-stream", |(_, content_type)| content_type)
+}
+
+/// uploads a file trying the primary endpoint first and then every fallback endpoint, but only on connection errors
+pub async fn upload<T: AsRef<Path>>(
+    file: T,
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+) -> Result<String> {
+    upload_as(
+        (ObjectClass::Mutable, content_type_for(key), &[]),
+        file,
+        s3_config,
+        fallback_endpoints,
+        key,
+    )
+    .await
+}
+
+#[instrument(skip(file, s3_config, fallback_endpoints), fields(file=%file.as_ref().display()))]
+async fn upload_as<T: AsRef<Path>>(
+    (class, content_type, headers): (ObjectClass, &str, &[(&str, &str)]),
+    file: T,
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+) -> Result<String> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    // s3_url of the raw path is not percent-encoded
+    let url = namespacing::s3_key_url(s3_config, key);
+    let upload_to = |config: S3Config| {
+        let (file, s3_path) = (file.as_ref(), &s3_path);
+        retry(s3_path, move || {
+            let config = config.clone();
+            async move {
+                let mut reader = tokio::fs::File::open(file)
+                    .await
+                    .wrap_err_with(|| format!("opening [{}]", file.display()))?;
+                let status = bucket_for(&config, class, headers)?
+                    .put_object_stream_with_content_type(&mut reader, s3_path, content_type)
+                    .await
+                    .map_err(|e| eyre::eyre!("{e:?}"))?;
+                if status != 200 {
+                    bail!("Got HTTP {status} for [{}] -> [{s3_path}]", file.display());
+                }
+                Ok(())
+            }
+        })
+    };
+    match with_failover(s3_config, fallback_endpoints, upload_to).await? {
+        Ok(()) => Ok(url),
+        Err(last_error) => bail!(
+            "uploading [{}] to [{s3_path}] failed :: {last_error}",
+            file.as_ref().display()
+        ),
+    }
+}
+
+/// tries the primary endpoint first and then every fallback endpoint, but only on connection errors - the inner error
+/// is the last one when all of them failed
+async fn with_failover<F, Fut>(
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    upload_to: F,
+) -> Result<std::result::Result<(), String>>
+where
+    F: Fn(S3Config) -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    let mut last_error = match upload_to(s3_config.clone()).await {
+        Ok(()) => return Ok(Ok(())),
+        Err(e) => format!("{e:?}"),
+    };
+    for endpoint in fallback_endpoints {
+        if !is_connect_error(&last_error) {
+            break;
+        }
+        warn!("primary endpoint unreachable, failing over to [{endpoint}] :: {last_error}");
+        match upload_to(with_endpoint(s3_config, endpoint)?).await {
+            Ok(()) => return Ok(Ok(())),
+            Err(e) => last_error = format!("{e:?}"),
+        }
+    }
+    Ok(Err(last_error))
+}
+
+/// s3 refuses parts smaller than this, except for the last one
+const MIN_PART_SIZE_MIB: u64 = 5;
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MultipartArgs {
+    /// files of at least this many MiB are uploaded in parts
+    #[clap(long, default_value_t = 64, value_name = "MIB")]
+    pub multipart_threshold: u64,
+    /// size of a single part in MiB, at least 5
+    #[clap(long, default_value_t = 16, value_name = "MIB")]
+    pub part_size: u64,
+    /// how many parts are uploaded at the same time
+    #[clap(long, default_value_t = 4, value_name = "N")]
+    pub parallel_parts: usize,
+}
+
+impl MultipartArgs {
+    fn applies_to(&self, size: u64) -> bool {
+        size >= self.multipart_threshold * MIB
+    }
+
+    /// part size the object of this size is uploaded in, `None` when it goes up in one go
+    pub fn part_size_for(&self, size: u64) -> Option<u64> {
+        self.applies_to(size).then_some(self.part_size * MIB)
+    }
+}
+
+/// `(offset, length)` of every part, the last one takes the rest
+fn part_ranges(size: u64, part_size: u64) -> Vec<(u64, u64)> {
+    (0..size)
+        .step_by(part_size as usize)
+        .map(|offset| (offset, part_size.min(size - offset)))
+        .collect()
+}
+
+/// uploads the parts `read_part` produces in parallel, an upload that fails half way is aborted so no orphaned parts keep taking space in the bucket
+async fn upload_multipart<F, Fut>(
+    s3_config: &S3Config,
+    key: &str,
+    content_type: &str,
+    size: u64,
+    args: &MultipartArgs,
+    headers: &[(&str, &str)],
+    read_part: F,
+) -> Result<String>
+where
+    F: Fn(u64, u64) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<u8>>>,
+{
+    use futures::{
+        StreamExt,
+        TryStreamExt,
+    };
+    if args.part_size < MIN_PART_SIZE_MIB {
+        bail!("--part-size must be at least {MIN_PART_SIZE_MIB} MiB");
+    }
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let bucket = &bucket_for(s3_config, ObjectClass::Immutable, headers)?;
+    let upload_id = retry(&s3_path, || async {
+        bucket
+            .initiate_multipart_upload(&s3_path, content_type)
+            .await
+            .wrap_err_with(|| format!("starting multipart upload of [{s3_path}]"))
+    })
+    .await?
+    .upload_id;
+    let ranges = part_ranges(size, args.part_size * MIB);
+    info!(
+        "multipart :: [{s3_path}] in {} parts of {} MiB",
+        ranges.len(),
+        args.part_size
+    );
+    let uploaded = async {
+        let parts = futures::stream::iter(ranges.into_iter().enumerate())
+            .map(|(index, (offset, length))| {
+                let (read_part, s3_path, upload_id) = (&read_part, &s3_path, &upload_id);
+                async move {
+                    let chunk = read_part(offset, length).await?;
+                    let part_number = index as u32 + 1;
+                    retry(s3_path, || async {
+                        bucket
+                            .put_multipart_chunk(
+                                chunk.clone(),
+                                s3_path,
+                                part_number,
+                                upload_id,
+                                content_type,
+                            )
+                            .await
+                            .wrap_err_with(|| {
+                                format!("uploading part {part_number} of [{s3_path}]")
+                            })
+                    })
+                    .await
+                }
+            })
+            .buffered(args.parallel_parts.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        retry(&s3_path, || async {
+            bucket
+                .complete_multipart_upload(&s3_path, &upload_id, parts.clone())
+                .await
+                .wrap_err_with(|| format!("completing multipart upload of [{s3_path}]"))
+        })
+        .await
+    }
+    .await;
+    if let Err(e) = uploaded {
+        if let Err(abort_error) = bucket.abort_upload(&s3_path, &upload_id).await {
+            warn!("aborting multipart upload {upload_id} of [{s3_path}] failed :: {abort_error:?}");
+        }
+        return Err(e);
+    }
+    Ok(namespacing::s3_key_url(s3_config, key))
+}
+
+/// size and etag of the object, `None` if it doesn't exist
+pub async fn head(s3_config: &S3Config, key: &str) -> Result<Option<(u64, Option<String>)>> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let bucket = bucket(s3_config)?;
+    retry(&s3_path, || async {
+        match bucket.head_object(&s3_path).await {
+            Ok((_, 404)) => Ok(None),
+            Ok((head, _)) => Ok(Some((
+                head.content_length.unwrap_or_default().max(0) as u64,
+                head.e_tag.map(|etag| etag.trim_matches('"').to_string()),
+            ))),
+            Err(e) if http_status(&e) == Some(404) => Ok(None),
+            Err(e) => Err(e).wrap_err_with(|| format!("reading metadata of [{s3_path}]")),
+        }
+    })
+    .await
+}
+
+/// whether the object under the key has exactly this size and etag, a failed check only means the upload isn't skipped
+async fn is_uploaded(s3_config: &S3Config, key: &str, size: u64, etag: &str) -> bool {
+    match head(s3_config, key).await {
+        Ok(Some((remote_size, remote_etag))) => {
+            remote_size == size && remote_etag.as_deref() == Some(etag)
+        }
+        Ok(None) => false,
+        Err(e) => {
+            debug!("can't tell whether [{key}] is uploaded already :: {e:?}");
+            false
+        }
+    }
+}
+
+/// uploads the content unless the key holds it already, returns whether it was uploaded - `immutable` picks its Cache-Control
+pub async fn sync_bytes(
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+    immutable: bool,
+    headers: &[(&str, &str)],
+) -> Result<bool> {
+    let etag = checksum::s3_etag_bytes(content, None);
+    if is_uploaded(s3_config, key, content.len() as u64, &etag).await {
+        debug!("cached :: [{key}] is up to date");
+        return Ok(false);
+    }
+    let class = match immutable {
+        true => ObjectClass::Immutable,
+        false => ObjectClass::Mutable,
+    };
+    put_bytes_as(class, s3_config, key, content, content_type, headers).await?;
+    Ok(true)
+}
+
+/// like [`upload`], but files past `--multipart-threshold` go up in parts - only through the primary endpoint. a file the key holds already is not uploaded again
+pub async fn upload_large<T: AsRef<Path>>(
+    file: T,
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+    args: &MultipartArgs,
+    headers: &[(&str, &str)],
+) -> Result<String> {
+    upload_large_as(
+        file,
+        s3_config,
+        fallback_endpoints,
+        key,
+        content_type_for(key),
+        args,
+        headers,
+    )
+    .await
+    .map(|(url, _)| url)
+}
+
+/// like [`upload_large`] with an explicit content type, returns the url and the sha256 of the file - hashed in the
+/// same pass that computes its etag, so the file is never read into memory as a whole
+pub async fn upload_large_as<T: AsRef<Path>>(
+    file: T,
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+    content_type: &str,
+    args: &MultipartArgs,
+    headers: &[(&str, &str)],
+) -> Result<(String, String)> {
+    let path = file.as_ref();
+    let size = tokio::fs::metadata(path)
+        .await
+        .wrap_err_with(|| format!("reading size of [{}]", path.display()))?
+        .len();
+    let (etag, sha256) = checksum::s3_etag_and_sha256_file(path, args.part_size_for(size)).await?;
+    if is_uploaded(s3_config, key, size, &etag).await {
+        info!("cached :: [{key}] is uploaded already");
+        return Ok((namespacing::s3_key_url(s3_config, key), sha256));
+    }
+    if !args.applies_to(size) {
+        let url = upload_as(
+            (ObjectClass::Immutable, content_type, headers),
+            path,
+            s3_config,
+            fallback_endpoints,
+            key,
+        )
+        .await?;
+        return Ok((url, sha256));
+    }
+    let url = upload_multipart(
+        s3_config,
+        key,
+        content_type,
+        size,
+        args,
+        headers,
+        |offset, length| async move {
+            use tokio::io::{
+                AsyncReadExt,
+                AsyncSeekExt,
+            };
+            let mut file = tokio::fs::File::open(path)
+                .await
+                .wrap_err_with(|| format!("opening [{}]", path.display()))?;
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .wrap_err_with(|| format!("seeking in [{}]", path.display()))?;
+            let mut chunk = vec![0; length as usize];
+            file.read_exact(&mut chunk)
+                .await
+                .wrap_err_with(|| format!("reading [{}]", path.display()))?;
+            Ok(chunk)
+        },
+    )
+    .await?;
+    Ok((url, sha256))
+}
+
+/// like [`put_bytes`], but content past `--multipart-threshold` goes up in parts, content the key holds already is not uploaded again
+pub async fn put_bytes_large(
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+    args: &MultipartArgs,
+    headers: &[(&str, &str)],
+) -> Result<String> {
+    let size = content.len() as u64;
+    let etag = checksum::s3_etag_bytes(content, args.part_size_for(size));
+    if is_uploaded(s3_config, key, size, &etag).await {
+        info!("cached :: [{key}] is uploaded already");
+        return Ok(namespacing::s3_key_url(s3_config, key));
+    }
+    if !args.applies_to(size) {
+        return put_bytes_as(
+            ObjectClass::Immutable,
+            s3_config,
+            key,
+            content,
+            content_type,
+            headers,
+        )
+        .await;
+    }
+    upload_multipart(
+        s3_config,
+        key,
+        content_type,
+        content.len() as u64,
+        args,
+        headers,
+        |offset, length| async move {
+            Ok(content[offset as usize..(offset + length) as usize].to_vec())
+        },
+    )
+    .await
+}
+
+/// the bucket handle configured by s3_helpers, for operations it doesn't cover
+pub fn bucket(s3_config: &S3Config) -> Result<&s3::Bucket> {
+    s3_config
+        .bucket
+        .as_ref()
+        .ok_or_else(|| eyre::eyre!("s3 bucket is not initialized"))
+}
+
+/// an object found by [`list_objects`]
+#[derive(Debug, Clone)]
+pub struct ListedObject {
+    /// relative to the bucket subdirectory
+    pub key: String,
+    /// `None` if the server sent a timestamp that isn't RFC 3339
+    pub last_modified: Option<time::OffsetDateTime>,
+}
+
+/// lists objects under the prefix, both the prefix and the returned keys are relative to the bucket subdirectory
+pub async fn list_objects(s3_config: &S3Config, prefix: &str) -> Result<Vec<ListedObject>> {
+    let full_prefix = handle_s3::s3_path_with_subdirectory(s3_config, prefix);
+    let bucket = bucket(s3_config)?;
+    let pages = retry(&full_prefix, || async {
+        bucket
+            .list(full_prefix.clone(), None)
+            .await
+            .wrap_err_with(|| format!("listing [{full_prefix}]"))
+    })
+    .await?;
+    Ok(pages
+        .into_iter()
+        .flat_map(|page| page.contents)
+        .filter_map(|object| {
+            Some(ListedObject {
+                key: format!("{prefix}{}", object.key.strip_prefix(&full_prefix)?),
+                last_modified: time::OffsetDateTime::parse(
+                    &object.last_modified,
+                    &time::format_description::well_known::Rfc3339,
+                )
+                .ok(),
+            })
+        })
+        .collect())
+}
+
+/// lists keys under the prefix, both the prefix and the returned keys are relative to the bucket subdirectory
+pub async fn list_keys(s3_config: &S3Config, prefix: &str) -> Result<Vec<String>> {
+    Ok(list_objects(s3_config, prefix)
+        .await?
+        .into_iter()
+        .map(|object| object.key)
+        .collect())
+}
+
+fn http_status(e: &s3::error::S3Error) -> Option<u16> {
+    match e {
+        s3::error::S3Error::Http(status, _) => Some(*status),
+        _ => None,
+    }
+}
+
+async fn get_object(
+    s3_config: &S3Config,
+    key: &str,
+) -> Result<Option<s3::request_trait::ResponseData>> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let bucket = bucket(s3_config)?;
+    retry(&s3_path, || async {
+        match bucket.get_object(&s3_path).await {
+            Ok(response) if response.status_code() == 404 => Ok(None),
+            Ok(response) => Ok(Some(response)),
+            Err(e) if http_status(&e) == Some(404) => Ok(None),
+            Err(e) => Err(e).wrap_err_with(|| format!("reading [{s3_path}]")),
+        }
+    })
+    .await
+}
+
+/// reads an object through the s3 api (bypassing any cdn in front of the bucket), returns `None` if it doesn't exist
+pub async fn get_bytes(s3_config: &S3Config, key: &str) -> Result<Option<Vec<u8>>> {
+    Ok(get_object(s3_config, key)
+        .await?
+        .map(|response| response.bytes().to_vec()))
+}
+
+/// object content together with the etag it had when it was read
+#[derive(Debug, Clone)]
+pub struct VersionedObject {
+    pub content: Vec<u8>,
+    pub etag: Option<String>,
+}
+
+/// like [`get_bytes`], but also remembers the etag so the object can be written back with [`put_if_match`]
+pub async fn get_versioned(s3_config: &S3Config, key: &str) -> Result<Option<VersionedObject>> {
+    Ok(get_object(s3_config, key)
+        .await?
+        .map(|response| VersionedObject {
+            content: response.bytes().to_vec(),
+            etag: response
+                .headers()
+                .into_iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case("etag"))
+                .map(|(_, etag)| etag),
+        }))
+}
+
+/// writes the object only if the precondition header holds, returns `false` when the server rejected it with 412
+async fn put_with_precondition(
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+    (header, value): (&str, &str),
+) -> Result<bool> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let bucket = bucket_for(s3_config, ObjectClass::Mutable, &[(header, value)])?;
+    retry(&s3_path, || async {
+        match bucket
+            .put_object_with_content_type(&s3_path, content, content_type)
+            .await
+        {
+            Ok(response) if response.status_code() == 412 => Ok(false),
+            Ok(_) => Ok(true),
+            Err(e) if http_status(&e) == Some(412) => Ok(false),
+            Err(e) => Err(e).wrap_err_with(|| format!("writing [{s3_path}]")),
+        }
+    })
+    .await
+}
+
+/// creates the object only if it doesn't exist yet
+pub async fn put_if_none_match(
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+) -> Result<bool> {
+    put_with_precondition(
+        s3_config,
+        key,
+        content,
+        content_type,
+        ("If-None-Match", "*"),
+    )
+    .await
+}
+
+/// overwrites the object only if nobody changed it since it was read with the given etag
+pub async fn put_if_match(
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+    etag: &str,
+) -> Result<bool> {
+    put_with_precondition(s3_config, key, content, content_type, ("If-Match", etag)).await
+}
+
+/// read-modify-write guarded by the etag that was read, `modify` is redone on a fresh read whenever another writer got in between
+pub async fn update_if_match<F>(
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+    content_type: &str,
+    attempts: usize,
+    mut modify: F,
+) -> Result<Vec<u8>>
+where
+    F: FnMut(Option<&[u8]>) -> Result<Vec<u8>>,
+{
+    for attempt in 1..=attempts {
+        let current = get_versioned(s3_config, key)
+            .await
+            .wrap_err_with(|| format!("reading [{key}] for updating"))?;
+        let content = modify(current.as_ref().map(|current| current.content.as_slice()))?;
+        let written = match current.map(|current| current.etag) {
+            Some(Some(etag)) => put_if_match(s3_config, key, &content, content_type, &etag).await?,
+            Some(None) => {
+                warn!("no etag returned for [{key}], overwriting it unconditionally");
+                upload_bytes(&content, s3_config, fallback_endpoints, key).await?;
+                true
+            }
+            None => put_if_none_match(s3_config, key, &content, content_type).await?,
+        };
+        if written {
+            return Ok(content);
+        }
+        warn!("[{key}] was changed by someone else in the meantime, updating again ({attempt}/{attempts})");
+    }
+    bail!("[{key}] kept changing, gave up updating it after {attempts} attempts")
+}
+
+/// uploads the content with an explicit content type, returns the public url
+pub async fn put_bytes(
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+) -> Result<String> {
+    put_bytes_as(
+        ObjectClass::Mutable,
+        s3_config,
+        key,
+        content,
+        content_type,
+        &[],
+    )
+    .await
+}
+
+async fn put_bytes_as(
+    class: ObjectClass,
+    s3_config: &S3Config,
+    key: &str,
+    content: &[u8],
+    content_type: &str,
+    headers: &[(&str, &str)],
+) -> Result<String> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let bucket = bucket_for(s3_config, class, headers)?;
+    retry(&s3_path, || async {
+        bucket
+            .put_object_with_content_type(&s3_path, content, content_type)
+            .await
+            .wrap_err_with(|| format!("writing [{s3_path}]"))
+    })
+    .await?;
+    Ok(namespacing::s3_key_url(s3_config, key))
+}
+
+/// server side copy within the bucket, returns the public url of the copy. the copy is mutable (a permalink), so its headers are replaced instead of copied
+pub async fn copy(
+    s3_config: &S3Config,
+    from_key: &str,
+    to_key: &str,
+    headers: &[(&str, &str)],
+) -> Result<String> {
+    let from = handle_s3::s3_path_with_subdirectory(s3_config, from_key);
+    let to = handle_s3::s3_path_with_subdirectory(s3_config, to_key);
+    let mut bucket = bucket_for(s3_config, ObjectClass::Mutable, headers)?;
+    bucket.add_header("x-amz-metadata-directive", "REPLACE");
+    if !headers
+        .iter()
+        .any(|(header, _)| header.eq_ignore_ascii_case("Content-Type"))
+    {
+        bucket.add_header("Content-Type", content_type_for(to_key));
+    }
+    retry(&to, || async {
+        match bucket.copy_object_internal(&from, &to).await {
+            Ok(status) if (200..300).contains(&status) => {
+                Ok(namespacing::s3_key_url(s3_config, to_key))
+            }
+            Ok(status) => bail!("copying [{from}] to [{to}] failed, Got HTTP {status}"),
+            Err(e) => Err(e).wrap_err_with(|| format!("copying [{from}] to [{to}]")),
+        }
+    })
+    .await
+}
+
+pub async fn delete(s3_config: &S3Config, key: &str) -> Result<()> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let bucket = bucket(s3_config)?;
+    retry(&s3_path, || async {
+        bucket
+            .delete_object(&s3_path)
+            .await
+            .wrap_err_with(|| format!("deleting [{s3_path}]"))
+    })
+    .await?;
+    Ok(())
+}
+
+/// deletes the object only if nobody changed it since it was read with the given etag, returns `false` when the server
+/// rejected it with 412
+pub async fn delete_if_match(s3_config: &S3Config, key: &str, etag: &str) -> Result<bool> {
+    let s3_path = handle_s3::s3_path_with_subdirectory(s3_config, key);
+    let mut bucket = bucket(s3_config)?.clone();
+    bucket.add_header("If-Match", etag);
+    retry(&s3_path, || async {
+        match bucket.delete_object(&s3_path).await {
+            Ok(response) if response.status_code() == 412 => Ok(false),
+            Ok(_) => Ok(true),
+            Err(e) if http_status(&e) == Some(412) => Ok(false),
+            Err(e) => Err(e).wrap_err_with(|| format!("deleting [{s3_path}]")),
+        }
+    })
+    .await
+}
+
+/// uploads the content under the given key, failing over like [`upload`]
+pub async fn upload_bytes(
+    content: &[u8],
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+) -> Result<String> {
+    let upload_to = |config: S3Config| async move {
+        put_bytes(&config, key, content, content_type_for(key))
+            .await
+            .map(|_| ())
+    };
+    match with_failover(s3_config, fallback_endpoints, upload_to).await? {
+        Ok(()) => Ok(namespacing::s3_key_url(s3_config, key)),
+        Err(last_error) => bail!("uploading to [{key}] failed :: {last_error}"),
+    }
+}
+
+pub async fn upload_json<T: Serialize>(
+    value: &T,
+    s3_config: &S3Config,
+    fallback_endpoints: &[String],
+    key: &str,
+) -> Result<String> {
+    let content = serde_json::to_string_pretty(value).wrap_err("serializing json file")?;
+    upload_bytes(content.as_bytes(), s3_config, fallback_endpoints, key).await
+}
+
+/// downloads a json object through its public url, returns `None` if it doesn't exist
+pub async fn fetch_json<T: serde::de::DeserializeOwned>(
+    s3_config: &S3Config,
+    key: &str,
+) -> Result<Option<T>> {
+    let url = namespacing::s3_key_url(s3_config, key);
+    debug!("fetching [{url}]");
+    let body = retry(&url, || async {
+        let response = reqwest::get(&url)
+            .await
+            .wrap_err_with(|| format!("requesting [{url}]"))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        response
+            .error_for_status()
+            .wrap_err_with(|| format!("fetching [{url}]"))?
+            .text()
+            .await
+            .wrap_err("reading response body")
+            .map(Some)
+    })
+    .await?;
+    let Some(body) = body else {
+        return Ok(None);
+    };
+    serde_json::from_str(&body)
+        .wrap_err_with(|| format!("parsing [{url}]"))
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_ranges() {
+        assert_eq!(part_ranges(12, 5), vec![(0, 5), (5, 5), (10, 2)]);
+        assert_eq!(part_ranges(10, 5), vec![(0, 5), (5, 5)]);
+    }
+
+    #[test]
+    fn test_parse_endpoints() {
+        assert_eq!(
+            parse_endpoints(" https://fra1.example.com/, ams3.example.com ,,"),
+            vec![
+                "fra1.example.com".to_string(),
+                "ams3.example.com".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metadata_headers() -> Result<()> {
+        assert_eq!(
+            metadata_headers(["CI-Run-Id=123", "builder=runner 1", "ci-run-id=124"])?,
+            vec![
+                ("x-amz-meta-builder".to_string(), "runner 1".to_string()),
+                ("x-amz-meta-ci-run-id".to_string(), "124".to_string()),
+            ]
+        );
+        assert!(metadata_headers(["run id=1"]).is_err());
+        assert!(metadata_headers(["builder"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tagging() {
+        assert_eq!(
+            tagging(&[("branch", "feature/x y"), ("version", "1.7.9")]),
+            "branch=feature%2Fx%20y&version=1.7.9"
+        );
+    }
+
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(
+            content_type_for("main/release-notes.json"),
+            "application/json"
+        );
+        assert_eq!(
+            content_type_for("app_1.0.0_amd64.AppImage"),
+            "application/vnd.appimage"
+        );
+        assert_eq!(content_type_for("app.app.tar.gz"), "application/gzip");
+        assert_eq!(
+            content_type_for("app.msi.zip.sig"),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(content_type_for("app.pdb"), "application/octet-stream");
+        assert_eq!(
+            content_type_for("assets/index-BkG3x9aZ.js"),
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(content_type_for("assets/index.js.map"), "application/json");
+        assert_eq!(content_type_for("fonts/Inter.WOFF2"), "font/woff2");
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable("Got HTTP 503 with content 'SlowDown'"));
+        assert!(is_retryable(
+            "HTTP status client error (429 Too Many Requests) for url (https://example.com/)"
+        ));
+        assert!(is_retryable("connection reset by peer"));
+        assert!(!is_retryable(
+            "Got HTTP 412 with content 'PreconditionFailed'"
+        ));
+        assert!(!is_retryable("Got HTTP 403 with content 'AccessDenied'"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryArgs::default();
+        assert_eq!(policy.backoff_ceiling(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff_ceiling(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff_ceiling(10), Duration::from_secs(20));
+        assert_eq!(policy.backoff_ceiling(100), Duration::from_secs(20));
+        let backoff = policy.backoff(2);
+        assert!(backoff >= Duration::from_millis(500) && backoff <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_is_connect_error() {
+        assert!(is_connect_error(
+            "reqwest::Error { kind: Request, source: hyper::Error(Connect, ConnectError(\"dns error\")) } error trying to connect"
+        ));
+        assert!(!is_connect_error(
+            "Got HTTP 403 with content 'AccessDenied'"
+        ));
+    }
+}
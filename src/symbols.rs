@@ -0,0 +1,184 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::release_notes_file::StagedArtifact;
+
+/// debug info rustc writes next to the binaries: windows pdbs and `split-debuginfo = "packed"` files on linux
+const SYMBOL_FILE_SUFFIXES: &[&str] = &[".pdb", ".dwp", ".debug"];
+
+/// macos keeps the debug info of a binary in a `.dSYM` bundle next to it, uploaded as a whole
+const DSYM_SUFFIX: &str = ".dSYM";
+
+/// cargo output that is not debug info of the shipped binaries - `deps` holds the pdbs of every crate
+const SKIPPED_DIRS: &[&str] = &[
+    "deps",
+    "build",
+    "bundle",
+    "incremental",
+    "examples",
+    ".fingerprint",
+];
+
+#[derive(clap::Args, Debug, Default)]
+pub struct SymbolsArgs {
+    /// upload the debug symbols (`.pdb`, `.dSYM`, `.dwp`, `.debug`) of the build to `{branch}/symbols/{target}/{version}/{commit}/` for crash analysis
+    #[clap(long)]
+    pub symbols: bool,
+    /// where the symbols are collected from, defaults to the cargo release dir the bundle dir is in
+    #[clap(long, value_name = "DIR")]
+    pub symbols_dir: Option<PathBuf>,
+}
+
+fn is_symbol_file(name: &str) -> bool {
+    SYMBOL_FILE_SUFFIXES
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+/// debug symbol files below the dir, every file inside a `.dSYM` bundle included
+fn collect(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    let entries = walkdir::WalkDir::new(dir).into_iter().filter_entry(|e| {
+        let name = e.file_name().to_string_lossy();
+        !(e.depth() == 1 && e.file_type().is_dir() && SKIPPED_DIRS.contains(&name.as_ref()))
+    });
+    for entry in entries {
+        let entry = entry.wrap_err("reading symbols dir entries")?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        let in_dsym = relative.components().any(|component| {
+            component
+                .as_os_str()
+                .to_string_lossy()
+                .ends_with(DSYM_SUFFIX)
+        });
+        if in_dsym || is_symbol_file(&entry.file_name().to_string_lossy()) {
+            found.push(entry.into_path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// the cargo `release` dir for the default `.../release/bundle` release dir
+fn default_dir(release_dir: &Path) -> PathBuf {
+    match release_dir.file_name() {
+        Some(name) if name == "bundle" => release_dir
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| release_dir.to_path_buf()),
+        _ => release_dir.to_path_buf(),
+    }
+}
+
+/// uploads the debug symbols of the build under their own prefix (keeping their paths relative to the symbols dir), in the storage class of `symbols` artifacts
+pub async fn upload(
+    deployment: &Deployment<'_>,
+    release_dir: &Path,
+    args: &SymbolsArgs,
+    multipart: &storage::MultipartArgs,
+) -> Result<Vec<StagedArtifact>> {
+    let dir = args
+        .symbols_dir
+        .clone()
+        .unwrap_or_else(|| default_dir(release_dir));
+    let paths = collect(&dir)?;
+    if paths.is_empty() {
+        warn!(
+            "symbols :: no debug symbols in [{}] - is `debug` enabled for the release profile?",
+            dir.display()
+        );
+        return Ok(vec![]);
+    }
+    let prefix = namespacing::derive_symbols_prefix(
+        deployment.branch,
+        deployment.target,
+        deployment.tauri_conf_json.version(),
+        deployment.git_hash,
+    );
+    let headers = deployment
+        .classifier
+        .kind_storage_class(artifacts::ArtifactKind::Symbols)
+        .map(|class| vec![("x-amz-storage-class", class)])
+        .unwrap_or_default();
+    let mut uploaded = vec![];
+    for path in paths {
+        let relative = path
+            .strip_prefix(&dir)
+            .unwrap_or(&path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .join("/");
+        let key = format!("{prefix}{relative}");
+        deployment.stage_artifact(&key).await?;
+        let sha256 = checksum::sha256_file(&path).await?;
+        let size = tokio::fs::metadata(&path)
+            .await
+            .wrap_err_with(|| format!("reading size of [{}]", path.display()))?
+            .len();
+        let url = storage::upload_large(
+            &path,
+            deployment.s3_config,
+            deployment.fallback_endpoints,
+            &key,
+            multipart,
+            &headers,
+        )
+        .await
+        .wrap_err_with(|| format!("uploading symbols [{}]", path.display()))?;
+        debug!("symbols :: {url}");
+        uploaded.push(StagedArtifact {
+            key,
+            url,
+            sha256,
+            size,
+            kind: Some(artifacts::ArtifactKind::Symbols),
+        });
+    }
+    info!("symbols :: {} files under [{prefix}]", uploaded.len());
+    Ok(uploaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_symbols() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("symbols-{}", uuid::Uuid::new_v4()));
+        for file in [
+            "app.exe",
+            "app.pdb",
+            "deps/serde.pdb",
+            "bundle/msi/app.msi",
+            "app.dSYM/Contents/Info.plist",
+            "app.dSYM/Contents/Resources/DWARF/app",
+            "app.dwp",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap_or(&dir))?;
+            std::fs::write(&path, file)?;
+        }
+        let found = collect(&dir)?
+            .iter()
+            .map(|path| path.strip_prefix(&dir).map(Path::to_path_buf))
+            .collect::<Result<Vec<_>, _>>()?;
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(
+            found,
+            [
+                "app.dSYM/Contents/Info.plist",
+                "app.dSYM/Contents/Resources/DWARF/app",
+                "app.dwp",
+                "app.pdb",
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            default_dir(Path::new("src-tauri/target/release/bundle")),
+            Path::new("src-tauri/target/release")
+        );
+        Ok(())
+    }
+}
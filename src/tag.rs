@@ -0,0 +1,98 @@
+use super::*;
+use crate::release_notes_file::StagedRelease;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TagArgs {
+    /// tag the commit `v{version}` (annotated) and push the tag once the release is live
+    #[clap(long)]
+    pub git_tag: bool,
+    /// gpg (or ssh) sign the tag with the key git is configured with
+    #[clap(long, requires = "git-tag")]
+    pub sign_tag: bool,
+    /// remote the tag is pushed to
+    #[clap(long, default_value = "origin", value_name = "REMOTE")]
+    pub tag_remote: String,
+}
+
+impl Default for TagArgs {
+    fn default() -> Self {
+        Self {
+            git_tag: false,
+            sign_tag: false,
+            tag_remote: "origin".to_string(),
+        }
+    }
+}
+
+fn tag_args<'a>(tag: &'a str, message: &'a str, sign: bool) -> Vec<&'a str> {
+    let kind = match sign {
+        true => "--sign",
+        false => "--annotate",
+    };
+    vec!["tag", kind, tag, "--message", message]
+}
+
+/// the commit a tag points to, `None` when there's no such tag
+fn tagged_commit(tag: &str) -> Option<String> {
+    metadata::git(&[
+        "rev-parse",
+        "--quiet",
+        "--verify",
+        &format!("refs/tags/{tag}^{{commit}}"),
+    ])
+    .ok()
+}
+
+/// tags HEAD, which has to be the commit the release was built from, and pushes the tag - an existing tag on that commit is pushed as is
+pub fn tag_and_push(product_name: &str, staged: &StagedRelease, args: &TagArgs) -> Result<()> {
+    if !args.git_tag {
+        return Ok(());
+    }
+    let tag = format!("v{}", staged.version);
+    let head = metadata::git(&["rev-parse", "HEAD"])?;
+    if !head.starts_with(&staged.commit) {
+        bail!(
+            "HEAD ({head}) is not the commit {} was built from ({}), not tagging it",
+            staged.version,
+            staged.commit
+        );
+    }
+    match tagged_commit(&tag) {
+        Some(commit) if commit == head => info!("tag :: [{tag}] is on {head} already"),
+        Some(commit) => bail!("tag [{tag}] exists already and points to {commit}, not {head}"),
+        None => {
+            let message = format!("{product_name} {}", staged.version);
+            metadata::git(&tag_args(&tag, &message, args.sign_tag))?;
+        }
+    }
+    metadata::git(&["push", &args.tag_remote, &format!("refs/tags/{tag}")])?;
+    info!("tag :: pushed [{tag}] to {}", args.tag_remote);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_args() {
+        assert_eq!(
+            tag_args("v1.2.3", "App 1.2.3", false),
+            vec!["tag", "--annotate", "v1.2.3", "--message", "App 1.2.3"]
+        );
+        assert_eq!(tag_args("v1.2.3", "App 1.2.3", true)[1], "--sign");
+    }
+
+    #[test]
+    fn test_sign_tag_requires_git_tag() {
+        #[derive(clap::Parser, Debug)]
+        struct Cli {
+            #[clap(flatten)]
+            tag: TagArgs,
+        }
+        use clap::Parser;
+        assert!(Cli::try_parse_from(["cli"]).is_ok());
+        assert!(Cli::try_parse_from(["cli", "--sign-tag"]).is_err());
+        assert!(Cli::try_parse_from(["cli", "--git-tag", "--sign-tag"]).is_ok());
+    }
+}
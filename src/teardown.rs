@@ -0,0 +1,209 @@
+use super::*;
+use crate::deploy::Deployment;
+
+/// left in the root of the namespace, so it's clear why it's empty
+pub const TOMBSTONE_KEY: &str = "tombstone.json";
+
+#[derive(clap::Args, Debug)]
+pub struct TeardownArgs {
+    /// product name of the discontinued app, must match tauri.conf.json as a safeguard against tearing down the wrong one
+    #[clap(long, value_name = "NAME")]
+    pub app: String,
+    /// only list what would be removed
+    #[clap(long)]
+    pub dry_run: bool,
+    /// don't ask for confirmation - required when stdin isn't a terminal
+    #[clap(long)]
+    pub yes: bool,
+    /// shown by the tombstone manifests and downloads pages
+    #[clap(
+        long,
+        default_value = "this product has been discontinued",
+        value_name = "TEXT"
+    )]
+    pub message: String,
+}
+
+/// served in place of the updater manifests and written to [`TOMBSTONE_KEY`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub app: String,
+    pub discontinued: bool,
+    pub message: String,
+    pub discontinued_at: String,
+    pub removed_objects: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    /// updater manifests keep answering, with the tombstone
+    Tombstone(String),
+    /// downloads pages keep answering, with a short notice
+    Notice(String),
+    Delete(String),
+}
+
+fn is_manifest(key: &str) -> bool {
+    key.ends_with("/release-notes.json") || key.ends_with("/latest.json")
+}
+
+fn is_downloads_page(key: &str) -> bool {
+    key.ends_with("/downloads/index.html")
+}
+
+fn plan(keys: &[String]) -> Vec<Action> {
+    keys.iter()
+        .filter(|key| key.as_str() != TOMBSTONE_KEY)
+        .map(|key| match key {
+            key if is_manifest(key) => Action::Tombstone(key.clone()),
+            key if is_downloads_page(key) => Action::Notice(key.clone()),
+            key => Action::Delete(key.clone()),
+        })
+        .collect()
+}
+
+fn notice_page(tombstone: &Tombstone) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{app}</title></head>\n<body><h1>{app}</h1><p>{message}</p></body></html>\n",
+        app = downloads::escape_html(&tombstone.app),
+        message = downloads::escape_html(&tombstone.message)
+    )
+}
+
+fn confirm(app: &str, removed: usize) -> Result<()> {
+    use std::io::{
+        BufRead,
+        IsTerminal,
+        Write,
+    };
+    if !std::io::stdin().is_terminal() {
+        bail!("stdin is not a terminal, pass --yes to tear down [{app}] without confirmation");
+    }
+    print!("type the app name ({app}) to remove {removed} objects: ");
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .wrap_err("reading confirmation")?;
+    if answer.trim() != app {
+        bail!("not confirmed, nothing was removed");
+    }
+    Ok(())
+}
+
+/// removes everything the app has in the bucket, leaving tombstones where clients and users still look
+pub async fn teardown(deployment: &Deployment<'_>, args: &TeardownArgs) -> Result<()> {
+    use futures::{
+        StreamExt,
+        TryStreamExt,
+    };
+    let product_name = deployment.tauri_conf_json.product_name();
+    if args.app != product_name {
+        bail!(
+            "--app {} doesn't match the product name in tauri.conf.json ({product_name})",
+            args.app
+        );
+    }
+    let keys = storage::list_keys(deployment.s3_config, "").await?;
+    let actions = plan(&keys);
+    for action in &actions {
+        match action {
+            Action::Tombstone(key) => println!("tombstone: {key}"),
+            Action::Notice(key) => println!("notice:    {key}"),
+            Action::Delete(key) => println!("delete:    {key}"),
+        }
+    }
+    let removed = actions
+        .iter()
+        .filter(|action| matches!(action, Action::Delete(_)))
+        .count();
+    if args.dry_run {
+        info!("teardown :: dry run, {removed} objects would be removed");
+        return Ok(());
+    }
+    if !args.yes {
+        confirm(&args.app, removed)?;
+    }
+    let tombstone = Tombstone {
+        app: args.app.clone(),
+        discontinued: true,
+        message: args.message.clone(),
+        discontinued_at: time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .wrap_err("formatting teardown time")?,
+        removed_objects: removed,
+    };
+    let content = serde_json::to_vec_pretty(&tombstone).wrap_err("serializing tombstone")?;
+    // written first, an interrupted teardown is still recognizable and can simply be run again
+    storage::put_bytes(
+        deployment.s3_config,
+        TOMBSTONE_KEY,
+        &content,
+        "application/json",
+    )
+    .await?;
+    let notice = notice_page(&tombstone);
+    futures::stream::iter(&actions)
+        .map(|action| {
+            let (content, notice) = (&content, &notice);
+            async move {
+                match action {
+                    Action::Tombstone(key) => {
+                        storage::put_bytes(deployment.s3_config, key, content, "application/json")
+                            .await
+                            .map(|_| ())
+                    }
+                    Action::Notice(key) => storage::put_bytes(
+                        deployment.s3_config,
+                        key,
+                        notice.as_bytes(),
+                        "text/html; charset=utf-8",
+                    )
+                    .await
+                    .map(|_| ()),
+                    Action::Delete(key) => storage::delete(deployment.s3_config, key).await,
+                }
+            }
+        })
+        .buffer_unordered(8)
+        .try_collect::<Vec<_>>()
+        .await
+        .wrap_err("tearing down")?;
+    info!(
+        "teardown :: [{}] removed {removed} objects, tombstone at {}",
+        args.app,
+        namespacing::s3_key_url(deployment.s3_config, TOMBSTONE_KEY)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_keeps_manifests_and_pages_answering() {
+        let keys = [
+            "main/release-notes.json",
+            "main/x86_64-pc-windows-msvc/latest.json",
+            "main/x86_64-pc-windows-msvc/1.0.0/abcd1234/app.msi.zip",
+            "main/downloads/index.html",
+            "main/downloads/x86_64-unknown-linux-gnu/latest.deb",
+            TOMBSTONE_KEY,
+        ]
+        .map(String::from);
+        assert_eq!(
+            plan(&keys),
+            vec![
+                Action::Tombstone("main/release-notes.json".to_string()),
+                Action::Tombstone("main/x86_64-pc-windows-msvc/latest.json".to_string()),
+                Action::Delete(
+                    "main/x86_64-pc-windows-msvc/1.0.0/abcd1234/app.msi.zip".to_string()
+                ),
+                Action::Notice("main/downloads/index.html".to_string()),
+                Action::Delete("main/downloads/x86_64-unknown-linux-gnu/latest.deb".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use super::*;
+use crate::{
+    deploy::Deployment,
+    release_notes_file::Contents,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct TriageArgs {
+    /// app version from the crash report
+    #[clap(long, value_name = "VERSION")]
+    pub version: String,
+    /// commit from the crash report, full or abbreviated
+    #[clap(long, value_name = "COMMIT")]
+    pub commit: Option<String>,
+    /// download the installers of the release into this dir, checked against the recorded sha256
+    #[clap(long, value_name = "DIR")]
+    pub download: Option<PathBuf>,
+}
+
+/// crash reporters send the full hash, releases record the abbreviated one
+fn commits_match(reported: &str, released: &str) -> bool {
+    let (reported, released) = (reported.to_lowercase(), released.to_lowercase());
+    !reported.is_empty()
+        && !released.is_empty()
+        && (reported.starts_with(&released) || released.starts_with(&reported))
+}
+
+fn kind_name(kind: Option<artifacts::ArtifactKind>) -> &'static str {
+    kind.and_then(|kind| serde_variant::to_variant_name(&kind).ok())
+        .unwrap_or("unknown")
+}
+
+/// everything support needs about the release a crash report came from: where it was published and when, its artifacts, debug symbols and installers
+pub async fn triage(deployment: &Deployment<'_>, args: &TriageArgs) -> Result<()> {
+    let staged = deployment.fetch_staged_version(&args.version).await?;
+    if let Some(commit) = &args.commit {
+        if !commits_match(commit, &staged.commit) {
+            warn!(
+                "the crash report says commit {commit}, but {} was released from {} - a local or re-staged build?",
+                args.version, staged.commit
+            );
+        }
+    }
+    let contents_key = namespacing::derive_contents_s3_key(
+        deployment.branch,
+        deployment.target,
+        &staged.version,
+        &staged.commit,
+    );
+    let contents = storage::get_bytes(deployment.s3_config, &contents_key)
+        .await?
+        .map(|content| serde_json::from_slice::<Contents>(&content))
+        .transpose()
+        .wrap_err("parsing contents.json")?;
+    let receipts = receipt::list(deployment)
+        .await?
+        .into_iter()
+        .filter(|signed| {
+            signed.receipt.version == staged.version && &signed.receipt.target == deployment.target
+        })
+        .sorted_by(|a, b| a.receipt.published_at.cmp(&b.receipt.published_at))
+        .collect_vec();
+    let record = history::fetch_record(deployment, &staged.version).await?;
+    let objects = contents
+        .as_ref()
+        .map(|contents| contents.objects.as_slice())
+        .unwrap_or(&staged.artifacts);
+
+    println!(
+        "{} ({}) [{}] {:?}",
+        staged.version, staged.commit, deployment.branch, deployment.target
+    );
+    println!("published:");
+    if receipts.is_empty() {
+        println!("  never - only staged");
+    }
+    for signed in &receipts {
+        println!(
+            "  {} deploy {} -> {} ({})",
+            signed.receipt.published_at,
+            signed.receipt.deploy_id,
+            signed.receipt.manifest_key,
+            if signed.signature.is_some() {
+                "signed receipt"
+            } else {
+                "unsigned receipt"
+            }
+        );
+    }
+    for label in &record.labels {
+        println!("label: {}", label.label);
+    }
+    for sign_off in &record.sign_offs {
+        println!(
+            "signed off: {} by {} at {}",
+            sign_off.entry.role, sign_off.entry.by, sign_off.entry.signed_at
+        );
+    }
+    println!("artifacts:");
+    for object in objects {
+        println!(
+            "  {:<15} {} ({} bytes, sha256 {})",
+            kind_name(object.kind),
+            object.url,
+            object.size,
+            object.sha256
+        );
+    }
+    let symbols = objects
+        .iter()
+        .filter(|object| object.kind == Some(artifacts::ArtifactKind::Symbols))
+        .collect_vec();
+    if symbols.is_empty() {
+        println!("symbols: none uploaded");
+    }
+    for object in symbols {
+        println!("symbols: {}", object.url);
+    }
+    if contents.is_none() {
+        warn!("[{contents_key}] is missing - the upload of this release may have been interrupted");
+    }
+
+    let dir = match &args.download {
+        Some(dir) => dir,
+        None => {
+            if !staged.downloads.is_empty() {
+                info!("pass --download <DIR> to fetch the installers");
+            }
+            return Ok(());
+        }
+    };
+    tokio::fs::create_dir_all(dir)
+        .await
+        .wrap_err_with(|| format!("creating [{}]", dir.display()))?;
+    for download in &staged.downloads {
+        let path = dir.join(&download.file_name);
+        let sha256 = cache::download_to(&download.url, &path).await?;
+        if sha256 != download.sha256 {
+            bail!(
+                "[{}] has sha256 {sha256}, but {} was recorded at release time",
+                path.display(),
+                download.sha256
+            );
+        }
+        info!("downloaded {} :: sha256 OK", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commits_match() {
+        assert!(commits_match(
+            "DEADBEEF0123456789abcdef0123456789abcdef",
+            "deadbeef"
+        ));
+        assert!(commits_match("deadbee", "deadbeef"));
+        assert!(!commits_match("cafebabe", "deadbeef"));
+        assert!(!commits_match("", "deadbeef"));
+    }
+}
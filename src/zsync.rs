@@ -0,0 +1,90 @@
+use super::*;
+use crate::deploy::Deployment;
+use crate::downloads::Download;
+
+/// control file next to an AppImage, AppImageUpdate reads it to fetch only the changed blocks of a new one with range requests
+pub const ZSYNC_SUFFIX: &str = ".zsync";
+
+pub fn is_appimage(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("appimage"))
+        .unwrap_or(false)
+}
+
+/// `-u` is the absolute url of the uploaded AppImage, so copies of the control file (e.g. the permalink) point at it too
+fn zsyncmake_args(url: &str, output: &Path, appimage: &Path) -> Vec<std::ffi::OsString> {
+    vec![
+        "-u".into(),
+        url.into(),
+        "-o".into(),
+        output.into(),
+        appimage.into(),
+    ]
+}
+
+/// runs `{zsyncmake}` over the uploaded AppImage and puts the `.zsync` next to it, returns its url
+pub async fn upload(
+    deployment: &Deployment<'_>,
+    appimage: &Path,
+    download: &Download,
+    zsyncmake: &str,
+) -> Result<String> {
+    let mut command = zsyncmake.split_whitespace();
+    let program = command
+        .next()
+        .ok_or_else(|| eyre::eyre!("--zsyncmake is empty"))?;
+    let output_path = std::env::temp_dir().join(format!("{}{ZSYNC_SUFFIX}", uuid::Uuid::new_v4()));
+    info!("zsync :: `{zsyncmake}` [{}]", appimage.display());
+    let output = tokio::process::Command::new(program)
+        .args(command)
+        .args(zsyncmake_args(&download.url, &output_path, appimage))
+        .output()
+        .await
+        .wrap_err_with(|| format!("running `{zsyncmake}`"))?;
+    if !output.status.success() {
+        bail!(
+            "`{zsyncmake}` failed ({}):\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let content = tokio::fs::read(&output_path).await;
+    tokio::fs::remove_file(&output_path).await.ok();
+    let content =
+        content.wrap_err_with(|| format!("`{zsyncmake}` succeeded but wrote no control file"))?;
+    let key = format!("{}{ZSYNC_SUFFIX}", download.key);
+    deployment.stage_artifact(&key).await?;
+    storage::put_bytes(deployment.s3_config, &key, &content, "application/x-zsync")
+        .await
+        .wrap_err("uploading zsync control file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zsyncmake_args() {
+        assert!(is_appimage(Path::new(
+            "bundle/appimage/app_1.7.9_amd64.AppImage"
+        )));
+        assert!(!is_appimage(Path::new(
+            "bundle/appimage/app_1.7.9_amd64.AppImage.tar.gz"
+        )));
+        assert_eq!(
+            zsyncmake_args(
+                "https://cdn.example.com/app.AppImage",
+                Path::new("/tmp/out.zsync"),
+                Path::new("app.AppImage")
+            ),
+            [
+                "-u",
+                "https://cdn.example.com/app.AppImage",
+                "-o",
+                "/tmp/out.zsync",
+                "app.AppImage"
+            ]
+            .map(std::ffi::OsString::from)
+        );
+    }
+}